@@ -1,31 +1,118 @@
+use anyhow::{Context, Result};
 use dioxus::{
-    desktop::{use_muda_event_handler, Config, WindowBuilder},
+    desktop::{
+        tao::event::{Event, WindowEvent},
+        use_muda_event_handler, use_wry_event_handler, Config, WindowBuilder, WindowCloseBehaviour,
+    },
     prelude::*,
     LaunchBuilder,
 };
-use std::fmt::Display;
-
-use ui::{ApplicationState, DocumentUI};
+use ui::{
+    perform, proceed, report_failure, report_warning, request, write_atomically, ActionHost,
+    ApplicationState, DocumentAction, DocumentUI, ExportDialog, UnsavedChangesPrompt,
+};
 
 mod platform;
-use platform::{create_menu_bar, file_from_open_dialog, path_from_save_dialog};
+use platform::{
+    copy_to_clipboard, create_menu_bar, file_from_open_dialog, image_from_open_dialog, offers,
+    paste_from_clipboard, path_from_export_dialog, path_from_save_dialog, set_recent_files,
+    take_menu_problems, update_action_items, RECENT_ITEM_PREFIX,
+};
+
+/// The desktop application's side of carrying out actions.
+#[derive(Clone, Copy)]
+struct DesktopHost {
+    state: Signal<ApplicationState>,
+}
+
+impl ActionHost for DesktopHost {
+    fn state(&self) -> Signal<ApplicationState> {
+        self.state
+    }
+
+    fn offers(&self, id: &str) -> bool {
+        offers(id)
+    }
+
+    fn open(&self) -> Result<()> {
+        let mut state = self.state;
+        match file_from_open_dialog() {
+            Some(file_path) => state.write().load_document(&file_path),
+            None => Ok(()),
+        }
+    }
+
+    fn save(&self) -> Result<bool> {
+        let mut state = self.state;
+        let can_save = state.read().current().file_path.is_some();
+        if can_save {
+            state.write().save_document()?;
+        } else if let Some(file_path) = path_from_save_dialog() {
+            state.write().save_document_as(&file_path)?;
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
 
-/// Handles `result` with consistent error reporting for `operation`.
-fn handle_file_result<T, E: Display>(result: Result<T, E>, operation: &str) {
-    match result {
-        Ok(_) => {}
-        Err(e) => eprintln!("Failed to {operation}: {e}"),
+    fn save_as(&self) -> Result<()> {
+        let mut state = self.state;
+        match path_from_save_dialog() {
+            Some(file_path) => state.write().save_document_as(&file_path),
+            None => Ok(()),
+        }
+    }
+
+    fn export(&self, content: &[u8], file_name: &str) -> Result<()> {
+        match path_from_export_dialog(file_name) {
+            Some(file_path) => write_atomically(&file_path, content, 0)
+                .with_context(|| format!("Failed to write {file_path:?}")),
+            None => Ok(()),
+        }
+    }
+
+    fn insert_image(&self) -> Result<()> {
+        let mut state = self.state;
+        let Some(file_path) = image_from_open_dialog() else {
+            return Ok(());
+        };
+        let data =
+            std::fs::read(&file_path).with_context(|| format!("Failed to read {file_path:?}"))?;
+        let inserted = state.write().current_mut().insert_image(data);
+        inserted
+    }
+
+    fn copy(&self) -> Result<()> {
+        let state = self.state;
+        spawn(async move { report_failure(state, "Copy failed", copy_to_clipboard(state).await) });
+        Ok(())
+    }
+
+    fn paste(&self) -> Result<()> {
+        let state = self.state;
+        spawn(
+            async move { report_failure(state, "Paste failed", paste_from_clipboard(state).await) },
+        );
+        Ok(())
+    }
+
+    fn quit(&self) {
+        let window = dioxus::desktop::window();
+        window.set_close_behavior(WindowCloseBehaviour::WindowCloses);
+        window.close();
     }
 }
 
 /// Runs the application.
 fn main() {
     // Nonstandard startup so the application window doesn't float on
-    // top of those of other applications.
+    // top of those of other applications, and only hides when asked to
+    // close, which quits once unsaved changes are dealt with.
     LaunchBuilder::desktop()
         .with_cfg(
             Config::default()
                 .with_window(WindowBuilder::new().with_always_on_top(false))
+                .with_close_behaviour(WindowCloseBehaviour::WindowHides)
                 .with_menu(create_menu_bar()),
         )
         .launch(AppUI);
@@ -37,39 +124,89 @@ fn AppUI() -> Element {
     // The state of the whole application
     let mut state = use_signal(ApplicationState::new);
 
+    // Tell the user of the shortcuts missing from the menus
+    use_effect(move || {
+        for problem in take_menu_problems() {
+            state
+                .write()
+                .notifications
+                .warning("Couldn't show a shortcut in the menus", &problem);
+        }
+    });
+
+    // Keep File ▸ Open Recent up to date
+    let recent_files = use_memo(move || state.read().recent_files.paths().to_vec());
+    use_effect(move || set_recent_files(&recent_files.read()));
+
+    // Enable the menu items of the actions that can be carried out
+    use_effect(move || update_action_items(&state.read()));
+
     // Handle menu events
+    let host = DesktopHost { state };
     use_muda_event_handler(move |event| match event.id.0.as_str() {
-        "new" => {
-            state.write().new_document();
+        "clear_recent" => {
+            let cleared = state.write().recent_files.clear();
+            report_warning(state, "Couldn't clear the recent files", cleared);
         }
-        "open" => {
-            if let Some(file_path) = file_from_open_dialog() {
-                handle_file_result(state.write().load_document(&file_path), "open file");
-            }
-        }
-        "save" => {
-            let can_save = state.read().current_file_path.is_some();
-            if can_save {
-                handle_file_result(state.read().save_document(), "save file");
-            } else if let Some(file_path) = path_from_save_dialog() {
-                handle_file_result(state.write().save_document_as(&file_path), "save file");
-            }
-        }
-        "save_as" => {
-            if let Some(file_path) = path_from_save_dialog() {
-                handle_file_result(state.write().save_document_as(&file_path), "save file");
+        id if id.starts_with(RECENT_ITEM_PREFIX) => {
+            let path = id[RECENT_ITEM_PREFIX.len()..]
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| state.read().recent_files.paths().get(index).cloned());
+            if let Some(path) = path {
+                let result = state.write().open_recent(&path);
+                report_failure(state, "Failed to open recent document", result);
             }
         }
+        id if ui::action(id).is_some() => perform(&host, id),
         _ => {
             unreachable!("unknown menu item {event:?}")
         }
     });
 
+    // Closing the window from its title bar quits as File ▸ Quit does
+    use_wry_event_handler(move |event, _| {
+        if let Event::WindowEvent {
+            event: WindowEvent::CloseRequested,
+            ..
+        } = event
+        {
+            // After the window has hidden itself
+            spawn(async move {
+                request(&host, DocumentAction::Quit);
+                if state.peek().pending_action.is_some() {
+                    dioxus::desktop::window().set_visible(true);
+                }
+            });
+        }
+    });
+
     rsx! {
         // Global app resources
         document::Link { rel: "stylesheet", href: asset!("/assets/main.css") }
 
         DocumentUI { application_state: state }
 
+        UnsavedChangesPrompt {
+            application_state: state,
+            on_save: move |_| match host.save() {
+                Ok(saved) => saved,
+                Err(e) => {
+                    host.report("Save", e);
+                    false
+                }
+            },
+            on_proceed: move |action| proceed(&host, action),
+        }
+
+        ExportDialog {
+            application_state: state,
+            on_export: move |(content, file_name): (Vec<u8>, String)| {
+                if let Err(e) = host.export(&content, &file_name) {
+                    host.report("Export", e);
+                }
+            },
+        }
+
     }
 }
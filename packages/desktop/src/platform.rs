@@ -2,50 +2,166 @@
 //!
 //! This module factors out cfg-dependent code to improve rust-analyzer support.
 
-use std::path::PathBuf;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
 
-use dioxus::desktop::muda::accelerator::{Accelerator, Code, Modifiers};
+use anyhow::{anyhow, Context, Result};
+use ui::{
+    copied_content, insert_into_field, openable_extensions, paste_content, selected_field_text,
+    ApplicationState, ExportFormat, FileFormat, Keybindings, MenuName, Notifications,
+    PastedContent, Shortcut, ACTIONS, SVG_EXTENSION,
+};
+
+use arboard::Clipboard;
+use dioxus::prelude::*;
+
+use dioxus::desktop::muda::accelerator::Accelerator;
 use dioxus::desktop::muda::{Menu, MenuId, MenuItem, PredefinedMenuItem, Submenu};
 use rfd::FileDialog;
 
-/// Returns the platform-appropriate base modifier key (Cmd on macOS, Ctrl elsewhere).
-fn base_modifier() -> Modifiers {
-    if cfg!(target_os = "macos") {
-        Modifiers::META
-    } else {
-        Modifiers::CONTROL
-    }
+/// The prefix of the ids of File ▸ Open Recent menu items, followed by the
+/// index of the file they open.
+pub const RECENT_ITEM_PREFIX: &str = "recent:";
+
+thread_local! {
+    /// The File ▸ Open Recent submenu, kept for listing the recent files as
+    /// they change.
+    static RECENT_MENU: RefCell<Option<Submenu>> = const { RefCell::new(None) };
+
+    /// The menu items of the actions, kept for enabling them as the state
+    /// changes.
+    static ACTION_ITEMS: RefCell<Vec<(&'static str, MenuItem)>> = const { RefCell::new(Vec::new()) };
+
+    /// The shortcuts that couldn't be shown in the menus, which are built
+    /// before there's an application state to tell the user in.
+    static MENU_PROBLEMS: RefCell<Vec<anyhow::Error>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Returns the shortcuts that couldn't be shown in the menus since last
+/// called, for telling the user.
+pub fn take_menu_problems() -> Vec<anyhow::Error> {
+    MENU_PROBLEMS.with(|problems| problems.take())
 }
 
-/// Returns an accelerator triggered by `key` with the platform's base modifier.
-fn menu_key(key: Code) -> Option<Accelerator> {
-    Some(Accelerator::new(Some(base_modifier()), key))
+/// Returns `true` iff the desktop application offers the action `id`.
+pub fn offers(id: &str) -> bool {
+    // Documents are shared through the file system.
+    id != "share"
 }
 
-/// Returns an accelerator triggered by `key` with base modifier plus `additional` modifiers.
-fn extended_menu_key(key: Code, additional: Modifiers) -> Option<Accelerator> {
-    Some(Accelerator::new(Some(base_modifier() | additional), key))
+/// Returns the accelerator of the menu item for `shortcut`.
+fn accelerator(shortcut: &Shortcut) -> Option<Accelerator> {
+    let mut keys = String::new();
+    if shortcut.primary {
+        keys.push_str("CmdOrCtrl+");
+    }
+    if shortcut.shift {
+        keys.push_str("Shift+");
+    }
+    if shortcut.alt {
+        keys.push_str("Alt+");
+    }
+    keys.push_str(&shortcut.key);
+    keys.parse()
+        .inspect_err(|e| {
+            let problem = anyhow!("Invalid shortcut {keys}: {e}");
+            MENU_PROBLEMS.with(|problems| problems.borrow_mut().push(problem));
+        })
+        .ok()
 }
 
 /// Presents an open file dialog and returns the user's selection (or `None` if canceled).
 pub fn file_from_open_dialog() -> Option<PathBuf> {
-    FileDialog::new()
-        .add_filter("JSON Documents", &["json"])
+    let mut dialog = FileDialog::new().add_filter("Documents", &openable_extensions());
+    for format in FileFormat::ALL {
+        dialog = dialog.add_filter(format.description(), &[format.extension()]);
+    }
+    dialog
+        .add_filter("SVG Images", &[SVG_EXTENSION])
         .add_filter("All Files", &["*"])
         .set_title("Open Document")
         .pick_file()
 }
 
+/// Presents an open file dialog for images and returns the user's selection
+/// (or `None` if canceled).
+pub fn image_from_open_dialog() -> Option<PathBuf> {
+    FileDialog::new()
+        .add_filter("Images", &["png", "jpg", "jpeg", "gif"])
+        .set_title("Insert Image")
+        .pick_file()
+}
+
 /// Presents a save file dialog and returns the user's selection (or `None` if canceled).
+///
+/// The document is saved in the format named by the selected file's extension.
 pub fn path_from_save_dialog() -> Option<PathBuf> {
-    FileDialog::new()
-        .add_filter("JSON Documents", &["json"])
+    let mut dialog = FileDialog::new();
+    for format in FileFormat::ALL {
+        dialog = dialog.add_filter(format.description(), &[format.extension()]);
+    }
+    dialog
         .add_filter("All Files", &["*"])
         .set_title("Save Document")
         .set_file_name("document.json")
         .save_file()
 }
 
+/// Presents a save file dialog for an export, suggesting `file_name`, and
+/// returns the user's selection (or `None` if canceled).
+pub fn path_from_export_dialog(file_name: &str) -> Option<PathBuf> {
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    let description = ExportFormat::from_extension(extension).map_or("Export", |f| f.description());
+    FileDialog::new()
+        .add_filter(description, &[extension])
+        .set_title("Export")
+        .set_file_name(file_name)
+        .save_file()
+}
+
+/// Puts the text selected in the focused text field on the system clipboard,
+/// or else the selected shape or the displayed page, as SVG markup in plain
+/// text and as an image in HTML.
+pub async fn copy_to_clipboard(state: Signal<ApplicationState>) -> Result<()> {
+    // The menu's shortcut keeps Copy from reaching text fields, so it's done
+    // for them.
+    let selected = selected_field_text().await;
+    let mut clipboard = Clipboard::new().context("The system clipboard is unavailable")?;
+    match selected {
+        Some(text) => clipboard.set_text(text),
+        None => {
+            let content = copied_content(&state.read())?;
+            clipboard.set_html(content.html(), Some(content.svg))
+        }
+    }
+    .context("The system clipboard refused the content")
+}
+
+/// Types the text on the system clipboard into the focused text field, or
+/// else adds the SVG markup or image on the clipboard to the displayed page.
+pub async fn paste_from_clipboard(mut state: Signal<ApplicationState>) -> Result<()> {
+    let mut clipboard = Clipboard::new().context("The system clipboard is unavailable")?;
+    let text = clipboard.get_text().ok();
+    if let Some(text) = &text {
+        if insert_into_field(text).await? {
+            return Ok(());
+        }
+    }
+    let content = match text.as_deref().and_then(PastedContent::from_text) {
+        Some(content) => content,
+        None => {
+            let image = clipboard
+                .get_image()
+                .context("The clipboard holds no SVG markup or image")?;
+            PastedContent::from_rgba(image.width as u32, image.height as u32, &image.bytes)?
+        }
+    };
+    paste_content(&mut state.write(), content)
+}
+
 /// Returns the application menu bar.
 pub fn create_menu_bar() -> Menu {
     let menu_bar = Menu::new();
@@ -53,34 +169,108 @@ pub fn create_menu_bar() -> Menu {
     // Add platform-specific app menu on macOS
     add_app_menu_if_needed(&menu_bar);
 
-    // Create File submenu
-    let file_menu = Submenu::new("File", true);
-
-    // Add File menu items with explicit IDs
-    append_menu_item(&file_menu, "new", "New", menu_key(Code::KeyN));
-    append_menu_item(&file_menu, "open", "Open", menu_key(Code::KeyO));
-    append_menu_item(&file_menu, "save", "Save", menu_key(Code::KeyS));
-    append_menu_item(
-        &file_menu,
-        "save_as",
-        "Save As...",
-        extended_menu_key(Code::KeyS, Modifiers::SHIFT),
-    );
-    file_menu
-        .append(&PredefinedMenuItem::separator())
-        .expect("Failed to append separator to File menu");
-    file_menu
-        .append(&PredefinedMenuItem::quit(Some("Quit")))
-        .expect("Failed to append Quit menu item");
-
-    // Add File submenu to main menu
-    menu_bar
-        .append(&file_menu)
-        .expect("Failed to append File menu to menu bar");
+    // The application state loads the keybindings too, and tells the user
+    // if the setting is invalid.
+    let keybindings = Keybindings::load(&mut Notifications::default());
+    for menu in [MenuName::File, MenuName::Edit] {
+        let submenu = Submenu::new(menu.title(), true);
+        append_actions(&submenu, menu, &keybindings);
+        menu_bar
+            .append(&submenu)
+            .unwrap_or_else(|_| panic!("Failed to append {} menu to menu bar", menu.title()));
+    }
 
     menu_bar
 }
 
+/// Appends the menu items of the actions listed in `menu` to `submenu`,
+/// separating their groups, with the shortcuts `keybindings` bind them to;
+/// File ▸ Open Recent follows File ▸ Open.
+fn append_actions(submenu: &Submenu, menu: MenuName, keybindings: &Keybindings) {
+    let mut group = None;
+    for action in ACTIONS
+        .iter()
+        .filter(|action| action.menu == menu && offers(action.id))
+    {
+        if group.is_some_and(|group| group != action.group) {
+            submenu
+                .append(&PredefinedMenuItem::separator())
+                .unwrap_or_else(|_| panic!("Failed to append separator to {} menu", menu.title()));
+        }
+        group = Some(action.group);
+        // Not the predefined Quit item, which quits without asking about
+        // unsaved changes
+        let item = MenuItem::with_id(
+            MenuId::new(action.id),
+            action.label,
+            true,
+            keybindings.shortcut(action.id).and_then(accelerator),
+        );
+        submenu
+            .append(&item)
+            .unwrap_or_else(|_| panic!("Failed to append menu item '{}' to submenu", action.label));
+        ACTION_ITEMS.with(|items| items.borrow_mut().push((action.id, item)));
+        if action.id == "open" {
+            let recent_menu = Submenu::new("Open Recent", true);
+            submenu
+                .append(&recent_menu)
+                .expect("Failed to append Open Recent menu to File menu");
+            RECENT_MENU.with(|menu| *menu.borrow_mut() = Some(recent_menu));
+            set_recent_files(&[]);
+        }
+    }
+}
+
+/// Enables the menu items of the actions that can be carried out in `state`.
+pub fn update_action_items(state: &ApplicationState) {
+    ACTION_ITEMS.with(|items| {
+        for (id, item) in &*items.borrow() {
+            if let Some(action) = ui::action(id) {
+                item.set_enabled((action.enabled)(state));
+            }
+        }
+    });
+}
+
+/// Lists `paths`, most recent first, in the File ▸ Open Recent submenu.
+pub fn set_recent_files(paths: &[PathBuf]) {
+    RECENT_MENU.with(|menu| {
+        let Some(menu) = &*menu.borrow() else {
+            return;
+        };
+        while menu.remove_at(0).is_some() {}
+        if paths.is_empty() {
+            menu.append(&MenuItem::new("No Recent Documents", false, None))
+                .expect("Failed to append placeholder to Open Recent menu");
+            return;
+        }
+        for (index, path) in paths.iter().enumerate() {
+            append_menu_item(
+                menu,
+                &format!("{RECENT_ITEM_PREFIX}{index}"),
+                &recent_file_label(path),
+                None,
+            );
+        }
+        menu.append(&PredefinedMenuItem::separator())
+            .expect("Failed to append separator to Open Recent menu");
+        append_menu_item(menu, "clear_recent", "Clear Menu", None);
+    });
+}
+
+/// Returns the label of the Open Recent menu item for `path`: its file name
+/// and the directory it's in.
+fn recent_file_label(path: &Path) -> String {
+    let name = path.file_name().map_or_else(
+        || path.display().to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    );
+    match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(directory) => format!("{name} — {}", directory.display()),
+        None => name,
+    }
+}
+
 /// Adds application menu on macOS to ensure File menu shows correctly.
 fn add_app_menu_if_needed(menu_bar: &Menu) {
     if cfg!(target_os = "macos") {
@@ -95,5 +285,5 @@ fn add_app_menu_if_needed(menu_bar: &Menu) {
 fn append_menu_item(submenu: &Submenu, id: &str, text: &str, accelerator: Option<Accelerator>) {
     submenu
         .append(&MenuItem::with_id(MenuId::new(id), text, true, accelerator))
-        .expect(&format!("Failed to append menu item '{}' to submenu", text));
+        .unwrap_or_else(|_| panic!("Failed to append menu item '{text}' to submenu"));
 }
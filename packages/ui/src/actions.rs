@@ -0,0 +1,516 @@
+//! The actions the user can carry out, shared by every platform's menus.
+//!
+//! Each [`Action`] has an id, a label, a default shortcut, a predicate
+//! saying whether it's enabled and a handler, and every platform builds its
+//! menus from [`ACTIONS`]. What an action does differently on each platform,
+//! such as how a file is chosen, is left to the platform's [`ActionHost`].
+
+use crate::application_state::{ApplicationState, DocumentAction};
+use crate::clipboard::{copied_content, paste_content, read_clipboard_api, write_clipboard_api};
+use crate::keybindings::Keybindings;
+use crate::notifications::report_failure;
+use crate::platform::{save_document, uses_command_key};
+use anyhow::{bail, Result};
+use dioxus::prelude::*;
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+/// The menu an action is listed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuName {
+    File,
+    Edit,
+}
+
+impl MenuName {
+    /// Returns the title of the menu.
+    pub fn title(self) -> &'static str {
+        match self {
+            MenuName::File => "File",
+            MenuName::Edit => "Edit",
+        }
+    }
+}
+
+/// A key combination that carries out an action.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Shortcut {
+    /// Whether the platform's primary modifier (Cmd on macOS, Ctrl
+    /// elsewhere) is held.
+    pub primary: bool,
+
+    /// Whether Shift is held.
+    pub shift: bool,
+
+    /// Whether Alt (Option on macOS) is held.
+    pub alt: bool,
+
+    /// The key, named as in accelerators: an upper-case letter or digit, or
+    /// a name such as "F5" or "Delete".
+    pub key: Cow<'static, str>,
+}
+
+impl Shortcut {
+    /// Returns the shortcut pressing `key` with the primary modifier, and
+    /// with Shift iff `shift`.
+    pub const fn primary(key: &'static str, shift: bool) -> Self {
+        Self {
+            primary: true,
+            shift,
+            alt: false,
+            key: Cow::Borrowed(key),
+        }
+    }
+
+    /// Returns the shortcut pressing `key` with Alt.
+    pub const fn alt(key: &'static str) -> Self {
+        Self {
+            primary: false,
+            shift: false,
+            alt: true,
+            key: Cow::Borrowed(key),
+        }
+    }
+
+    /// Returns the shortcut as written in the keybindings setting, e.g.
+    /// "Primary+Shift+S".
+    pub fn notation(&self) -> String {
+        let primary = if self.primary { "Primary+" } else { "" };
+        let shift = if self.shift { "Shift+" } else { "" };
+        let alt = if self.alt { "Alt+" } else { "" };
+        format!("{primary}{shift}{alt}{}", self.key)
+    }
+}
+
+impl FromStr for Shortcut {
+    type Err = anyhow::Error;
+
+    /// Parses a shortcut written as modifiers and a key joined by "+", such
+    /// as "Primary+Shift+S"; "Cmd", "Ctrl" and "CmdOrCtrl" also name the
+    /// primary modifier.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.split('+').map(str::trim);
+        let key = match parts.next_back() {
+            Some(key) if !key.is_empty() => key,
+            _ => bail!("No key in shortcut '{s}'"),
+        };
+        let mut shortcut = Shortcut {
+            primary: false,
+            shift: false,
+            alt: false,
+            key: Cow::Owned(if key.chars().count() == 1 {
+                key.to_uppercase()
+            } else {
+                key.to_string()
+            }),
+        };
+        for modifier in parts {
+            match modifier.to_ascii_lowercase().as_str() {
+                "primary" | "cmd" | "command" | "ctrl" | "control" | "cmdorctrl" => {
+                    shortcut.primary = true
+                }
+                "shift" => shortcut.shift = true,
+                "alt" | "option" => shortcut.alt = true,
+                _ => bail!("Unknown modifier '{modifier}' in shortcut '{s}'"),
+            }
+        }
+        Ok(shortcut)
+    }
+}
+
+impl fmt::Display for Shortcut {
+    /// Writes the shortcut as the platform shows it, e.g. "⇧⌘S" on macOS
+    /// and "Ctrl+Shift+S" elsewhere.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if uses_command_key() {
+            let alt = if self.alt { "⌥" } else { "" };
+            let shift = if self.shift { "⇧" } else { "" };
+            let primary = if self.primary { "⌘" } else { "" };
+            write!(f, "{alt}{shift}{primary}{}", self.key)
+        } else {
+            let primary = if self.primary { "Ctrl+" } else { "" };
+            let shift = if self.shift { "Shift+" } else { "" };
+            let alt = if self.alt { "Alt+" } else { "" };
+            write!(f, "{primary}{shift}{alt}{}", self.key)
+        }
+    }
+}
+
+/// Something the user can do from a menu or with a shortcut.
+pub struct Action {
+    /// Identifies the action, e.g. as the id of its menu item.
+    pub id: &'static str,
+
+    /// The name of the action in menus.
+    pub label: &'static str,
+
+    /// What the action does, for tooltips and menu subtitles.
+    pub description: &'static str,
+
+    /// The menu listing the action.
+    pub menu: MenuName,
+
+    /// The group of related actions the action belongs to in its menu;
+    /// groups are separated where the menu allows.
+    pub group: u8,
+
+    /// The shortcut carrying out the action unless remapped, if any; in
+    /// browsers, some are replaced by others (see [`Keybindings`]).
+    pub shortcut: Option<Shortcut>,
+
+    /// Returns `true` iff the action can be carried out in the given state.
+    pub enabled: fn(&ApplicationState) -> bool,
+
+    /// Carries out the action through the given host.
+    pub handler: fn(&dyn ActionHost) -> Result<()>,
+}
+
+impl Action {
+    /// Returns the tooltip of the action: its description and the shortcut
+    /// `keybindings` bind it to.
+    pub fn tooltip(&self, keybindings: &Keybindings) -> String {
+        match keybindings.shortcut(self.id) {
+            Some(shortcut) => format!("{} ({shortcut})", self.description),
+            None => self.description.to_string(),
+        }
+    }
+}
+
+/// A platform's side of carrying out actions: the state they act on, and
+/// what each platform does its own way.
+pub trait ActionHost {
+    /// Returns the state the actions act on.
+    fn state(&self) -> Signal<ApplicationState>;
+
+    /// Returns `true` iff the platform offers the action `id`.
+    fn offers(&self, id: &str) -> bool;
+
+    /// Lets the user choose a document to open, and opens it.
+    fn open(&self) -> Result<()>;
+
+    /// Saves the current document, asking where if needed; returns `true`
+    /// iff it was saved.
+    fn save(&self) -> Result<bool>;
+
+    /// Saves the current document under a name the user chooses.
+    fn save_as(&self) -> Result<()>;
+
+    /// Saves `content`, exported from the current document, where the user
+    /// chooses, suggesting the name `file_name`.
+    fn export(&self, content: &[u8], file_name: &str) -> Result<()> {
+        save_document(content, file_name, 0)?;
+        let mut state = self.state();
+        state
+            .write()
+            .notifications
+            .info(format!("Exported \"{file_name}\""));
+        Ok(())
+    }
+
+    /// Puts the selected shape, or else the displayed page, on the
+    /// clipboard, as SVG markup and PNG.
+    fn copy(&self) -> Result<()> {
+        let state = self.state();
+        let content = copied_content(&state.read())?;
+        spawn(async move {
+            report_failure(state, "Copy failed", write_clipboard_api(content).await);
+        });
+        Ok(())
+    }
+
+    /// Adds the SVG markup or image on the clipboard to the displayed page.
+    fn paste(&self) -> Result<()> {
+        let mut state = self.state();
+        spawn(async move {
+            let result = match read_clipboard_api().await {
+                Ok(content) => paste_content(&mut state.write(), content),
+                Err(e) => Err(e),
+            };
+            report_failure(state, "Paste failed", result);
+        });
+        Ok(())
+    }
+
+    /// Lets the user choose an image, and adds it to the displayed page.
+    fn insert_image(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Shares the current document with other applications.
+    fn share(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Quits the application, once no document has unsaved changes left.
+    fn quit(&self) {}
+
+    /// Tells the user that the action `label` failed with `error`.
+    fn report(&self, label: &str, error: anyhow::Error) {
+        let mut state = self.state();
+        state
+            .write()
+            .notifications
+            .error(format!("{label} failed"), &error);
+    }
+}
+
+/// Returns `true`, for actions that are always enabled.
+fn always(_: &ApplicationState) -> bool {
+    true
+}
+
+/// Every action, in menu order.
+pub static ACTIONS: [Action; 15] = [
+    Action {
+        id: "new",
+        label: "New",
+        description: "New document",
+        menu: MenuName::File,
+        group: 0,
+        shortcut: Some(Shortcut::primary("N", false)),
+        enabled: always,
+        handler: |host| {
+            host.state().write().new_document();
+            Ok(())
+        },
+    },
+    Action {
+        id: "open",
+        label: "Open...",
+        description: "Open document",
+        menu: MenuName::File,
+        group: 0,
+        shortcut: Some(Shortcut::primary("O", false)),
+        enabled: always,
+        handler: |host| host.open(),
+    },
+    Action {
+        id: "close",
+        label: "Close",
+        description: "Close document",
+        menu: MenuName::File,
+        group: 0,
+        shortcut: Some(Shortcut::primary("W", false)),
+        enabled: always,
+        handler: |host| {
+            request(host, DocumentAction::Close);
+            Ok(())
+        },
+    },
+    Action {
+        id: "save",
+        label: "Save",
+        description: "Save document",
+        menu: MenuName::File,
+        group: 1,
+        shortcut: Some(Shortcut::primary("S", false)),
+        enabled: always,
+        handler: |host| host.save().map(drop),
+    },
+    Action {
+        id: "save_as",
+        label: "Save As...",
+        description: "Save document with a new name",
+        menu: MenuName::File,
+        group: 1,
+        shortcut: Some(Shortcut::primary("S", true)),
+        enabled: always,
+        handler: |host| host.save_as(),
+    },
+    Action {
+        id: "export",
+        label: "Export...",
+        description: "Export the page as an image, PDF or SVG",
+        menu: MenuName::File,
+        group: 1,
+        shortcut: Some(Shortcut::primary("E", false)),
+        enabled: always,
+        handler: |host| {
+            host.state().write().show_export = true;
+            Ok(())
+        },
+    },
+    Action {
+        id: "insert_image",
+        label: "Insert Image...",
+        description: "Insert an image into the page",
+        menu: MenuName::File,
+        group: 2,
+        shortcut: None,
+        enabled: always,
+        handler: |host| host.insert_image(),
+    },
+    Action {
+        id: "properties",
+        label: "Properties...",
+        description: "Title, author, description and tags",
+        menu: MenuName::File,
+        group: 2,
+        shortcut: None,
+        enabled: always,
+        handler: |host| {
+            let mut state = host.state();
+            let shown = state.read().show_properties;
+            state.write().show_properties = !shown;
+            Ok(())
+        },
+    },
+    Action {
+        id: "messages",
+        label: "Messages...",
+        description: "Recent messages and errors",
+        menu: MenuName::File,
+        group: 2,
+        shortcut: None,
+        enabled: always,
+        handler: |host| {
+            let mut state = host.state();
+            let shown = state.read().notifications.show_history;
+            state.write().notifications.show_history = !shown;
+            Ok(())
+        },
+    },
+    Action {
+        id: "share",
+        label: "Share",
+        description: "Share document with other apps",
+        menu: MenuName::File,
+        group: 3,
+        shortcut: None,
+        enabled: always,
+        handler: |host| host.share(),
+    },
+    Action {
+        id: "quit",
+        label: "Quit",
+        description: "Quit the application",
+        menu: MenuName::File,
+        group: 4,
+        shortcut: Some(Shortcut::primary("Q", false)),
+        enabled: always,
+        handler: |host| {
+            request(host, DocumentAction::Quit);
+            Ok(())
+        },
+    },
+    Action {
+        id: "undo",
+        label: "Undo",
+        description: "Undo the last change",
+        menu: MenuName::Edit,
+        group: 0,
+        shortcut: Some(Shortcut::primary("Z", false)),
+        enabled: |state| state.current().can_undo(),
+        handler: |host| host.state().write().current_mut().undo(),
+    },
+    Action {
+        id: "redo",
+        label: "Redo",
+        description: "Redo the last change undone",
+        menu: MenuName::Edit,
+        group: 0,
+        shortcut: Some(Shortcut::primary("Z", true)),
+        enabled: |state| state.current().can_redo(),
+        handler: |host| host.state().write().current_mut().redo(),
+    },
+    Action {
+        id: "copy",
+        label: "Copy",
+        description: "Copy the page as SVG and PNG",
+        menu: MenuName::Edit,
+        group: 1,
+        shortcut: Some(Shortcut::primary("C", false)),
+        enabled: always,
+        handler: |host| host.copy(),
+    },
+    Action {
+        id: "paste",
+        label: "Paste",
+        description: "Paste SVG or an image into the page",
+        menu: MenuName::Edit,
+        group: 1,
+        shortcut: Some(Shortcut::primary("V", false)),
+        enabled: always,
+        handler: |host| host.paste(),
+    },
+];
+
+/// Returns the action `id`, if there is one.
+pub fn action(id: &str) -> Option<&'static Action> {
+    ACTIONS.iter().find(|action| action.id == id)
+}
+
+/// Returns the actions listed in `menu` that `host` offers, in menu order.
+pub fn menu_actions(host: &dyn ActionHost, menu: MenuName) -> Vec<&'static Action> {
+    ACTIONS
+        .iter()
+        .filter(|action| action.menu == menu && host.offers(action.id))
+        .collect()
+}
+
+/// Carries out the action `id` through `host` if it's enabled, reporting
+/// any failure to the user.
+pub fn perform(host: &dyn ActionHost, id: &str) {
+    let Some(action) = action(id) else {
+        host.report(id, anyhow::anyhow!("No such action"));
+        return;
+    };
+    if !(action.enabled)(&host.state().read()) {
+        return;
+    }
+    if let Err(e) = (action.handler)(host) {
+        host.report(action.label.trim_end_matches("..."), e);
+    }
+}
+
+/// Carries out `action` unless the user must first decide what to do with
+/// unsaved changes.
+pub fn request(host: &dyn ActionHost, action: DocumentAction) {
+    let mut state = host.state();
+    if state.write().check_unsaved_changes(action) {
+        match action {
+            DocumentAction::Close => state.write().close_document(),
+            DocumentAction::Quit => host.quit(),
+        }
+    }
+}
+
+/// Closes the current document, whose unsaved changes the user saved or
+/// discarded, and goes on with `action`.
+pub fn proceed(host: &dyn ActionHost, action: DocumentAction) {
+    host.state().write().close_document();
+    if action == DocumentAction::Quit {
+        request(host, action);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortcuts_are_parsed_from_their_notation() {
+        for action in &ACTIONS {
+            if let Some(shortcut) = &action.shortcut {
+                assert_eq!(shortcut.notation().parse::<Shortcut>().unwrap(), *shortcut);
+            }
+        }
+        let shortcut = Shortcut {
+            primary: true,
+            shift: true,
+            alt: true,
+            key: Cow::Borrowed("F5"),
+        };
+        assert_eq!(shortcut.notation(), "Primary+Shift+Alt+F5");
+        assert_eq!(shortcut.notation().parse::<Shortcut>().unwrap(), shortcut);
+        assert_eq!(
+            "cmdorctrl + shift + s".parse::<Shortcut>().unwrap(),
+            Shortcut::primary("S", true)
+        );
+        assert_eq!("Option+n".parse::<Shortcut>().unwrap(), Shortcut::alt("N"));
+        for invalid in ["", "Primary+", "Hyper+S"] {
+            assert!(invalid.parse::<Shortcut>().is_err(), "{invalid}");
+        }
+    }
+}
@@ -1,14 +1,94 @@
+use crate::atomic_save::{backups_of, Backup, DEFAULT_BACKUP_GENERATIONS};
 use crate::document::Document;
-use anyhow::{bail, Result};
+use crate::export::ExportOptions;
+use crate::file_format::is_svg_file_name;
+use crate::history::DEFAULT_UNDO_DEPTH;
+use crate::keybindings::Keybindings;
+use crate::notifications::Notifications;
+use crate::open_document::OpenDocument;
+use crate::platform::{load_recent_document, recent_document_exists};
+use crate::recent_files::{RecentFiles, DEFAULT_RECENT_FILES_LIMIT};
+use crate::recovery::{
+    discard_recovery, recovered_documents, RecoveredDocument, DEFAULT_AUTOSAVE_INTERVAL,
+};
+use crate::validation::{Diagnostic, InvalidSvg};
+use anyhow::{anyhow, bail, Context, Result};
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// The state of the entire application.
 pub struct ApplicationState {
-    /// The one document that every application has open.
-    pub the_only_document: Document,
+    /// The open documents, in the order of their tabs (never empty).
+    documents: Vec<OpenDocument>,
 
-    /// Where the document will be saved (`None` for new unsaved documents).
-    pub current_file_path: Option<PathBuf>,
+    /// The index of the document being displayed.
+    current: usize,
+
+    /// The problems found in the markup of the document last opened (or
+    /// that failed to open), for display.
+    pub open_diagnostics: Vec<Diagnostic>,
+
+    /// Whether the document properties panel is shown.
+    pub show_properties: bool,
+
+    /// Whether the user is choosing how to export the displayed page.
+    pub show_export: bool,
+
+    /// How pages are exported.
+    pub export_options: ExportOptions,
+
+    /// How many backups of the previous content to keep when saving a file.
+    pub backup_generations: usize,
+
+    /// The backups offered after a file failed to open, if any.
+    pub restore_offer: Option<RestoreOffer>,
+
+    /// How often unsaved changes are autosaved for recovery.
+    pub autosave_interval: Duration,
+
+    /// The documents recovered from earlier sessions, offered for restoring.
+    pub recovered: Vec<RecoveredDocument>,
+
+    /// The documents opened or saved most recently.
+    pub recent_files: RecentFiles,
+
+    /// The action waiting for the user to save or discard the unsaved
+    /// changes it would lose, if any.
+    pub pending_action: Option<DocumentAction>,
+
+    /// The messages for the user.
+    pub notifications: Notifications,
+
+    /// The shortcut bound to each action.
+    pub keybindings: Keybindings,
+
+    /// The action whose shortcut was pressed, waiting for the platform's
+    /// menu to carry it out, if any.
+    pub pressed_action: Option<&'static str>,
+
+    /// How many changes to each document can be undone.
+    undo_depth: usize,
+}
+
+/// An action that closes documents, losing any unsaved changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentAction {
+    /// Closing the current document.
+    Close,
+
+    /// Quitting the application, closing every document.
+    Quit,
+}
+
+/// An offer to restore a file that failed to open from one of its backups.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestoreOffer {
+    /// The file that failed to open.
+    pub path: PathBuf,
+
+    /// Its backups, newest first (never empty).
+    pub backups: Vec<Backup>,
 }
 
 impl Default for ApplicationState {
@@ -20,39 +100,380 @@ impl Default for ApplicationState {
 impl ApplicationState {
     /// Returns the state of a newly-launched application.
     pub fn new() -> Self {
-        // Start with a default document
+        // Problems loading what's kept across sessions are told once the
+        // application is shown.
+        let mut notifications = Notifications::default();
         Self {
-            the_only_document: Document::new(),
-            current_file_path: None,
+            // Start with a default document
+            documents: vec![OpenDocument::new(Document::new(), None, DEFAULT_UNDO_DEPTH)],
+            current: 0,
+            open_diagnostics: Vec::new(),
+            show_properties: false,
+            show_export: false,
+            export_options: ExportOptions::default(),
+            backup_generations: DEFAULT_BACKUP_GENERATIONS,
+            restore_offer: None,
+            autosave_interval: DEFAULT_AUTOSAVE_INTERVAL,
+            recovered: recovered_documents(&mut notifications),
+            recent_files: RecentFiles::load(DEFAULT_RECENT_FILES_LIMIT, &mut notifications),
+            pending_action: None,
+            keybindings: Keybindings::load(&mut notifications),
+            notifications,
+            pressed_action: None,
+            undo_depth: DEFAULT_UNDO_DEPTH,
         }
     }
 
-    /// Creates a new document.
+    /// Returns the open documents, in the order of their tabs.
+    pub fn documents(&self) -> &[OpenDocument] {
+        &self.documents
+    }
+
+    /// Returns the index of the document being displayed.
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    /// Returns the document being displayed.
+    pub fn current(&self) -> &OpenDocument {
+        &self.documents[self.current]
+    }
+
+    /// Returns the document being displayed, for changing.
+    pub fn current_mut(&mut self) -> &mut OpenDocument {
+        &mut self.documents[self.current]
+    }
+
+    /// Displays the document at `index`, if there is one.
+    pub fn select_document(&mut self, index: usize) {
+        if index < self.documents.len() {
+            self.current_mut().end_gesture();
+            self.current = index;
+        }
+    }
+
+    /// Returns how many changes to each document can be undone.
+    pub fn undo_depth(&self) -> usize {
+        self.undo_depth
+    }
+
+    /// Lets `depth` changes to each document be undone.
+    pub fn set_undo_depth(&mut self, depth: usize) {
+        self.undo_depth = depth;
+        for document in &mut self.documents {
+            document.set_undo_depth(depth);
+        }
+    }
+
+    /// Opens a new document.
     pub fn new_document(&mut self) {
-        self.the_only_document = Document::new();
-        self.current_file_path = None;
+        self.open_diagnostics.clear();
+        self.restore_offer = None;
+        self.add(OpenDocument::new(Document::new(), None, self.undo_depth));
     }
 
-    /// Loads a document from `path`.
+    /// Opens the document at `path`, or displays it if it's already open;
+    /// a plain SVG file is imported as a new document.
     pub fn load_document(&mut self, path: &Path) -> Result<()> {
-        self.the_only_document = Document::new_from_file(path)?;
-        self.current_file_path = Some(path.to_path_buf());
-        Ok(())
+        let name = path.to_string_lossy();
+        if is_svg_file_name(&name) {
+            let content = fs::read(path).with_context(|| format!("Failed to open: {path:?}"))?;
+            return self.import_svg(&name, &content);
+        }
+        if let Some(index) = self.index_of(path) {
+            self.select_document(index);
+            self.remember_current();
+            return Ok(());
+        }
+        match Document::new_from_file(path) {
+            Ok(document) => {
+                self.open_document(document, Some(path.to_path_buf()));
+                Ok(())
+            }
+            Err(e) => {
+                self.record_open_failure(&e);
+                self.offer_backups(path);
+                Err(e)
+            }
+        }
     }
 
-    /// Saves the current document.
-    pub fn save_document(&self) -> Result<()> {
-        if let Some(path) = &self.current_file_path {
-            self.the_only_document.save_to_file(path)
+    /// Opens `document`, stored at `path` (if any), and displays it.
+    pub fn open_document(&mut self, document: Document, path: Option<PathBuf>) {
+        self.open_diagnostics = document.diagnostics().to_vec();
+        self.restore_offer = None;
+        self.add(OpenDocument::new(document, path, self.undo_depth));
+        self.remember_current();
+    }
+
+    /// Opens `content`, the file named `name` read where there's no path to
+    /// load it from, such as in the browser: a document in any format,
+    /// remembered by name so that saving it keeps the name and format, or a
+    /// plain SVG file, imported as a new document.
+    pub fn open_file_content(&mut self, name: &str, content: &[u8]) -> Result<()> {
+        if is_svg_file_name(name) {
+            return self.import_svg(name, content);
+        }
+        match Document::from_bytes(content) {
+            Ok(document) => {
+                self.open_document(document, Some(PathBuf::from(name)));
+                Ok(())
+            }
+            Err(e) => {
+                self.record_open_failure(&e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Opens `content`, the plain SVG file named `name`, as a new document
+    /// titled after the file. It's opened unsaved, so that it's saved as a
+    /// document rather than over the SVG file.
+    pub fn import_svg(&mut self, name: &str, content: &[u8]) -> Result<()> {
+        let imported = std::str::from_utf8(content)
+            .context("The file isn't UTF-8 text")
+            .and_then(Document::from_html)
+            .with_context(|| format!("Failed to import SVG {name:?}"));
+        match imported {
+            Ok(mut document) => {
+                if let Some(stem) = Path::new(name).file_stem().and_then(|s| s.to_str()) {
+                    document.metadata_mut().title = stem.to_string();
+                }
+                self.open_diagnostics = document.diagnostics().to_vec();
+                self.restore_offer = None;
+                self.add(OpenDocument::new_unsaved(document, None, self.undo_depth));
+                Ok(())
+            }
+            Err(e) => {
+                self.record_open_failure(&e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Opens the recent document at `path`, or displays it if it's already
+    /// open, no longer listing it if it can't be opened anymore.
+    pub fn open_recent(&mut self, path: &Path) -> Result<()> {
+        if let Some(index) = self.index_of(path) {
+            self.select_document(index);
+            self.remember_current();
+            return Ok(());
+        }
+        let document = load_recent_document(path)
+            .and_then(|content| Document::from_bytes(&content))
+            .with_context(|| format!("Failed to open recent document {path:?}"));
+        match document {
+            Ok(document) => {
+                self.open_document(document, Some(path.to_path_buf()));
+                Ok(())
+            }
+            Err(e) => {
+                self.record_open_failure(&e);
+                if !recent_document_exists(path) {
+                    let removed = self.recent_files.remove(path);
+                    self.warn("Couldn't update the recent files", removed);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Lists the current document among the recent files, if it has one.
+    fn remember_current(&mut self) {
+        let current = &self.documents[self.current];
+        if let Some(path) = &current.file_path {
+            let added = self.recent_files.add(path, &current.document);
+            self.warn("Couldn't update the recent files", added);
+        }
+    }
+
+    /// Discards the recovery entry of the current document, just saved, and
+    /// lists it among the recent files.
+    fn finish_saving_current(&mut self) {
+        let discarded = self.current().discard_recovery();
+        self.warn(
+            "Couldn't discard the recovery copy of the document",
+            discarded,
+        );
+        self.remember_current();
+    }
+
+    /// Tells the user in a toast that `message`, if `result` is a failure.
+    fn warn(&mut self, message: &str, result: Result<()>) {
+        if let Err(e) = result {
+            self.notifications.warning(message, &e);
+        }
+    }
+
+    /// Closes the current document, discarding any unsaved changes, and
+    /// displays its neighbour; closing the last one leaves a new document.
+    pub fn close_document(&mut self) {
+        let closed = self.documents.remove(self.current);
+        let discarded = closed.discard_recovery();
+        self.warn(
+            "Couldn't discard the recovery copy of the document",
+            discarded,
+        );
+        if self.documents.is_empty() {
+            self.documents
+                .push(OpenDocument::new(Document::new(), None, self.undo_depth));
+        }
+        self.current = self.current.min(self.documents.len() - 1);
+    }
+
+    /// Adds `document` as a tab and displays it, in place of the current
+    /// document if that's an untouched new one.
+    fn add(&mut self, document: OpenDocument) {
+        if self.current().is_untouched() {
+            *self.current_mut() = document;
         } else {
-            bail!("No file path set - use Save As instead");
+            self.current_mut().end_gesture();
+            self.documents.push(document);
+            self.current = self.documents.len() - 1;
+        }
+    }
+
+    /// Returns the index of the open document stored at `path`, if any.
+    fn index_of(&self, path: &Path) -> Option<usize> {
+        self.documents
+            .iter()
+            .position(|d| d.file_path.as_deref() == Some(path))
+    }
+
+    /// Records the diagnostics carried by `error`, from a failed attempt to
+    /// open a document.
+    pub fn record_open_failure(&mut self, error: &anyhow::Error) {
+        self.open_diagnostics = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<InvalidSvg>())
+            .map(|invalid| invalid.diagnostics.clone())
+            .unwrap_or_default();
+    }
+
+    /// Offers to restore `path`, which failed to open, from its backups (if
+    /// it has any).
+    pub fn offer_backups(&mut self, path: &Path) {
+        let backups = backups_of(path);
+        self.restore_offer = (!backups.is_empty()).then(|| RestoreOffer {
+            path: path.to_path_buf(),
+            backups,
+        });
+    }
+
+    /// Opens the backup at `backup` in place of the file offered for restoring,
+    /// unsaved, so that saving replaces that file.
+    pub fn restore_backup(&mut self, backup: &Path) -> Result<()> {
+        let Some(offer) = self.restore_offer.take() else {
+            bail!("No file to restore");
+        };
+        match Document::new_from_file(backup) {
+            Ok(document) => {
+                self.open_diagnostics = document.diagnostics().to_vec();
+                self.add(OpenDocument::new_unsaved(
+                    document,
+                    Some(offer.path),
+                    self.undo_depth,
+                ));
+                Ok(())
+            }
+            Err(e) => {
+                self.restore_offer = Some(offer);
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns `true` iff `action` can go ahead; otherwise a document it
+    /// would close has unsaved changes, and is displayed for the user to be
+    /// asked whether to save or discard them first.
+    pub fn check_unsaved_changes(&mut self, action: DocumentAction) -> bool {
+        let dirty = match action {
+            DocumentAction::Close => self.current().is_dirty().then_some(self.current),
+            DocumentAction::Quit => self.documents.iter().position(OpenDocument::is_dirty),
+        };
+        match dirty {
+            Some(index) => {
+                self.select_document(index);
+                self.pending_action = Some(action);
+                false
+            }
+            None => true,
+        }
+    }
+
+    /// Returns `true` iff any document changed since it was last autosaved.
+    pub fn needs_autosave(&self) -> bool {
+        self.documents.iter().any(OpenDocument::needs_autosave)
+    }
+
+    /// Autosaves every document that changed since it was last autosaved,
+    /// for recovery.
+    pub fn autosave(&mut self) -> Result<()> {
+        for document in &mut self.documents {
+            if document.needs_autosave() {
+                document.autosave()?;
+            }
         }
+        Ok(())
+    }
+
+    /// Opens the recovered document `id`, unsaved.
+    pub fn restore_recovered(&mut self, id: &str) -> Result<()> {
+        let index = self
+            .recovered
+            .iter()
+            .position(|r| r.id == id)
+            .ok_or_else(|| anyhow!("No recovered document '{id}'"))?;
+        let recovered = self.recovered.remove(index);
+        self.open_diagnostics = recovered.document.diagnostics().to_vec();
+        self.restore_offer = None;
+        self.add(OpenDocument::new_unsaved(
+            recovered.document,
+            recovered.path,
+            self.undo_depth,
+        ));
+        // Keep the work in this session's entry before dropping the old one.
+        self.current_mut().autosave()?;
+        discard_recovery(id)
+    }
+
+    /// Discards the recovered document `id`.
+    pub fn discard_recovered(&mut self, id: &str) -> Result<()> {
+        self.recovered.retain(|r| r.id != id);
+        discard_recovery(id)
+    }
+
+    /// Returns the title to show for the current document.
+    pub fn document_title(&self) -> String {
+        self.current().title()
+    }
+
+    /// Returns the title of the window or tab showing the current document,
+    /// marked if it has unsaved changes.
+    pub fn window_title(&self) -> String {
+        self.current().marked_title()
+    }
+
+    /// Records the current document as saved by the platform, at its file
+    /// path (if any), as `saved`, from [`OpenDocument::stamped_for_saving`].
+    pub fn mark_saved(&mut self, saved: &Document) {
+        self.current_mut().mark_saved(saved);
+        self.finish_saving_current();
+    }
+
+    /// Saves the current document.
+    pub fn save_document(&mut self) -> Result<()> {
+        let generations = self.backup_generations;
+        self.current_mut().save(generations)?;
+        self.finish_saving_current();
+        Ok(())
     }
 
     /// Saves the current document in `path`.
     pub fn save_document_as(&mut self, path: &Path) -> Result<()> {
-        self.the_only_document.save_to_file(path)?;
-        self.current_file_path = Some(path.to_path_buf());
+        let generations = self.backup_generations;
+        self.current_mut().save_as(path, generations)?;
+        self.finish_saving_current();
         Ok(())
     }
 }
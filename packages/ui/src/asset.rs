@@ -0,0 +1,314 @@
+//! Binary assets, such as bitmaps, embedded in a document.
+//!
+//! Assets are stored once per document in an [`AssetTable`], keyed by the
+//! SHA-256 hash of their content, and referenced from `<image>` elements by
+//! `asset:<key>` URLs. The URLs are replaced by `data:` URLs for rendering.
+
+use crate::shape::{Node, Shape};
+use anyhow::{anyhow, ensure, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// The URL scheme referring to an entry of a document's asset table.
+pub const ASSET_SCHEME: &str = "asset:";
+
+/// The image types that may be embedded, with the signature their data starts with.
+const IMAGE_SIGNATURES: &[(&str, &[u8])] = &[
+    ("image/png", b"\x89PNG\r\n\x1a\n"),
+    ("image/jpeg", b"\xff\xd8\xff"),
+    ("image/gif", b"GIF8"),
+];
+
+/// A binary asset.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Asset {
+    /// The MIME type of the content, e.g. `image/png`.
+    pub mime_type: String,
+
+    /// The content, stored as base64 in JSON and as bytes in binary formats.
+    #[serde(with = "base64_data")]
+    pub data: Vec<u8>,
+}
+
+impl Asset {
+    /// Returns `self` as a `data:` URL.
+    pub fn data_url(&self) -> String {
+        format!(
+            "data:{};base64,{}",
+            self.mime_type,
+            STANDARD.encode(&self.data)
+        )
+    }
+}
+
+/// The assets of a document, keyed by the hash of their content.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(transparent)]
+pub struct AssetTable {
+    assets: BTreeMap<String, Asset>,
+}
+
+impl AssetTable {
+    /// Returns `true` iff `self` holds no assets.
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    /// Returns the number of assets in `self`.
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    /// Returns the asset stored under `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&Asset> {
+        self.assets.get(key)
+    }
+
+    /// Returns the assets of `self` with their keys, in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Asset)> {
+        self.assets.iter().map(|(key, asset)| (key.as_str(), asset))
+    }
+
+    /// Stores `data` of type `mime_type`, unless identical content is
+    /// already stored, and returns its key.
+    pub fn insert(&mut self, mime_type: &str, data: Vec<u8>) -> String {
+        let key = content_hash(&data);
+        self.assets.entry(key.clone()).or_insert_with(|| Asset {
+            mime_type: mime_type.to_string(),
+            data,
+        });
+        key
+    }
+
+    /// Stores the PNG, JPEG or GIF image `data` as by [`AssetTable::insert`].
+    pub fn insert_image(&mut self, data: Vec<u8>) -> Result<String> {
+        let mime_type = image_mime_type(&data)
+            .ok_or_else(|| anyhow!("Unsupported image format: expected PNG, JPEG or GIF"))?;
+        Ok(self.insert(mime_type, data))
+    }
+
+    /// Stores `asset`, loaded from a file under `key`, failing unless `key`
+    /// is the hash of its content and its type is that of its content.
+    pub(crate) fn insert_loaded(&mut self, key: String, asset: Asset) -> Result<()> {
+        check_asset(&key, &asset)?;
+        self.assets.insert(key, asset);
+        Ok(())
+    }
+
+    /// Fails unless every asset of `self` is stored under the hash of its
+    /// content with the type of its content.
+    ///
+    /// Types are written into the `data:` URLs pages are rendered with, so
+    /// those loaded from a file can't be trusted.
+    pub(crate) fn check(&self) -> Result<()> {
+        self.iter()
+            .try_for_each(|(key, asset)| check_asset(key, asset))
+    }
+
+    /// Returns the assets of `self` referenced from `shapes`, with their keys.
+    pub(crate) fn used_by<'s>(
+        &self,
+        shapes: impl IntoIterator<Item = &'s Shape>,
+    ) -> BTreeMap<&str, &Asset> {
+        let mut used = BTreeMap::new();
+        for shape in shapes {
+            for_each_image_href(shape, &mut |href| {
+                if let Some((key, asset)) =
+                    asset_key(href).and_then(|k| self.assets.get_key_value(k))
+                {
+                    used.insert(key.as_str(), asset);
+                }
+            });
+        }
+        used
+    }
+}
+
+/// Returns the key under which `data` is stored: its SHA-256 hash in hex.
+pub fn content_hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Fails unless `asset` is stored under the hash of its content, `key`,
+/// with the type of its content.
+fn check_asset(key: &str, asset: &Asset) -> Result<()> {
+    ensure!(
+        content_hash(&asset.data) == key,
+        "The content of asset {key} doesn't match its key"
+    );
+    ensure!(
+        image_mime_type(&asset.data) == Some(asset.mime_type.as_str()),
+        "Asset {key} isn't of its type, {:?}",
+        asset.mime_type
+    );
+    Ok(())
+}
+
+/// Returns the MIME type of the image `data`, if it is of a supported type.
+pub fn image_mime_type(data: &[u8]) -> Option<&'static str> {
+    IMAGE_SIGNATURES
+        .iter()
+        .find(|(_, signature)| data.starts_with(signature))
+        .map(|(mime_type, _)| *mime_type)
+}
+
+/// Returns the URL referring to the asset stored under `key`.
+pub fn asset_href(key: &str) -> String {
+    format!("{ASSET_SCHEME}{key}")
+}
+
+/// Returns the asset key `href` refers to, if it is an asset URL.
+pub fn asset_key(href: &str) -> Option<&str> {
+    href.strip_prefix(ASSET_SCHEME)
+}
+
+/// Returns the MIME type and content of the base64 `data:` URL `url`, if it
+/// holds a supported image.
+pub(crate) fn decode_image_data_url(url: &str) -> Option<(&'static str, Vec<u8>)> {
+    let (header, payload) = url.trim().strip_prefix("data:")?.split_once(',')?;
+    if !header.ends_with(";base64") {
+        return None;
+    }
+    let payload: String = payload.chars().filter(|c| !c.is_whitespace()).collect();
+    let data = STANDARD.decode(payload).ok()?;
+    Some((image_mime_type(&data)?, data))
+}
+
+/// Calls `f` with the link of each `<image>` element in `shape`.
+pub(crate) fn for_each_image_href(shape: &Shape, f: &mut impl FnMut(&str)) {
+    match shape {
+        Shape::Group(group) => {
+            for child in &group.children {
+                for_each_image_href(child, f);
+            }
+        }
+        Shape::Other(element) => {
+            if element.name == "image" {
+                for (_, href) in element.attributes.iter().filter(|(n, _)| is_link(n)) {
+                    f(href);
+                }
+            }
+            for node in &element.content {
+                if let Node::Shape(child) = node {
+                    for_each_image_href(child, f);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Calls `f` with the link of each `<image>` element in `shape`, for editing.
+pub(crate) fn for_each_image_href_mut(shape: &mut Shape, f: &mut impl FnMut(&mut String)) {
+    match shape {
+        Shape::Group(group) => {
+            for child in &mut group.children {
+                for_each_image_href_mut(child, f);
+            }
+        }
+        Shape::Other(element) => {
+            if element.name == "image" {
+                for (_, href) in element.attributes.iter_mut().filter(|(n, _)| is_link(n)) {
+                    f(href);
+                }
+            }
+            for node in &mut element.content {
+                if let Node::Shape(child) = node {
+                    for_each_image_href_mut(child, f);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns `true` iff `attribute` is the link of an `<image>`.
+fn is_link(attribute: &str) -> bool {
+    matches!(attribute, "href" | "xlink:href")
+}
+
+/// Serialization of binary data as base64 text, or as bytes in binary formats.
+mod base64_data {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&STANDARD.encode(data))
+        } else {
+            serializer.serialize_bytes(data)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        STANDARD.decode(text).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIXEL: &[u8] = include_bytes!("fixtures/pixel.png");
+
+    #[test]
+    fn keys_are_content_hashes() {
+        assert_eq!(
+            content_hash(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        let mut assets = AssetTable::default();
+        let key = assets.insert_image(PIXEL.to_vec()).unwrap();
+        assert_eq!(key, content_hash(PIXEL));
+        assert_eq!(assets.insert_image(PIXEL.to_vec()).unwrap(), key);
+        assert_eq!(assets.len(), 1);
+        assert_eq!(assets.get(&key).unwrap().mime_type, "image/png");
+        assert!(assets.insert_image(b"<svg/>".to_vec()).is_err());
+    }
+
+    #[test]
+    fn data_urls_round_trip() {
+        let asset = Asset {
+            mime_type: "image/png".into(),
+            data: PIXEL.to_vec(),
+        };
+        assert_eq!(
+            decode_image_data_url(&asset.data_url()),
+            Some(("image/png", PIXEL.to_vec()))
+        );
+        assert_eq!(decode_image_data_url("data:text/html,<script>"), None);
+    }
+
+    #[test]
+    fn loaded_assets_are_checked() {
+        let png = |mime_type: &str| Asset {
+            mime_type: mime_type.into(),
+            data: PIXEL.to_vec(),
+        };
+        let mut assets = AssetTable::default();
+        assert!(assets
+            .insert_loaded(content_hash(PIXEL), png("text/html"))
+            .is_err());
+        assert!(assets
+            .insert_loaded("0".repeat(64), png("image/png"))
+            .is_err());
+        assert!(assets.is_empty());
+        assets
+            .insert_loaded(content_hash(PIXEL), png("image/png"))
+            .unwrap();
+        assert!(assets.check().is_ok());
+
+        assets
+            .assets
+            .get_mut(&content_hash(PIXEL))
+            .unwrap()
+            .mime_type = "image/svg+xml".into();
+        assert!(assets.check().is_err());
+    }
+}
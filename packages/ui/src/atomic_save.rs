@@ -0,0 +1,172 @@
+//! Crash-safe writing of files, with rolling backups.
+//!
+//! A file is never written in place: the new content goes to a temporary
+//! file beside it, which is flushed to disk and then renamed over the
+//! target, so the target always holds either the old or the new content.
+//! Before that, the old content is copied to the first of a number of backup
+//! generations (`name.1.bak` is the newest, `name.2.bak` the one before, …).
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// The number of backup generations kept unless configured otherwise.
+pub const DEFAULT_BACKUP_GENERATIONS: usize = 3;
+
+/// A backup of a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backup {
+    /// Where the backup is stored.
+    pub path: PathBuf,
+
+    /// The generation of the backup (1 for the newest).
+    pub generation: usize,
+
+    /// When the backed-up content was written, if known.
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// Replaces the content of `path` with `content`, keeping the previous
+/// content (if any) as the newest of `generations` backups.
+pub fn write_atomically(path: &Path, content: &[u8], generations: usize) -> Result<()> {
+    let temporary = temporary_path(path);
+    let result = write_synced(&temporary, content)
+        .and_then(|()| rotate_backups(path, generations))
+        .and_then(|()| {
+            fs::rename(&temporary, path)
+                .with_context(|| format!("Failed to replace {path:?} with {temporary:?}"))
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    result?;
+
+    // Make the rename itself durable where the platform allows it.
+    if let Some(directory) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        let _ = File::open(directory).and_then(|d| d.sync_all());
+    }
+    Ok(())
+}
+
+/// Returns the path of backup `generation` of `path`.
+pub fn backup_path(path: &Path, generation: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{generation}.bak"));
+    path.with_file_name(name)
+}
+
+/// Returns the existing backups of `path`, newest first.
+pub fn backups_of(path: &Path) -> Vec<Backup> {
+    (1..)
+        .map(|generation| (generation, backup_path(path, generation)))
+        .take_while(|(_, backup)| backup.exists())
+        .map(|(generation, path)| Backup {
+            modified: fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .ok()
+                .map(DateTime::<Utc>::from),
+            path,
+            generation,
+        })
+        .collect()
+}
+
+/// Writes `content` to the new file `path` and flushes it to disk.
+fn write_synced(path: &Path, content: &[u8]) -> Result<()> {
+    let mut file = File::create(path).with_context(|| format!("Failed to create: {path:?}"))?;
+    file.write_all(content)
+        .and_then(|()| file.sync_all())
+        .with_context(|| format!("Failed to write: {path:?}"))
+}
+
+/// Shifts the backups of `path` by one generation, dropping the oldest, and
+/// copies the current content of `path` to the newest.
+fn rotate_backups(path: &Path, generations: usize) -> Result<()> {
+    // Drop generations no longer kept, e.g. after the number was lowered.
+    for stale in (generations + 1..).map(|generation| backup_path(path, generation)) {
+        if fs::remove_file(&stale).is_err() {
+            break;
+        }
+    }
+
+    if generations == 0 || !path.exists() {
+        return Ok(());
+    }
+    for generation in (1..generations).rev() {
+        let older = backup_path(path, generation);
+        if older.exists() {
+            let newer = backup_path(path, generation + 1);
+            fs::rename(&older, &newer)
+                .with_context(|| format!("Failed to move backup {older:?} to {newer:?}"))?;
+        }
+    }
+    let newest = backup_path(path, 1);
+    fs::copy(path, &newest).with_context(|| format!("Failed to back up {path:?}"))?;
+    Ok(())
+}
+
+/// Returns the path of the temporary file used while writing `path`.
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.tmp", std::process::id()));
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a new empty directory for the test `name`.
+    fn scratch_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("pcl-demo-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn writes_replace_content_without_leftovers() {
+        let directory = scratch_directory("atomic-write");
+        let path = directory.join("drawing.json");
+        write_atomically(&path, b"one", 0).unwrap();
+        write_atomically(&path, b"two", 0).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"two");
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 1);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn backups_rotate_and_are_trimmed() {
+        let directory = scratch_directory("backup-rotation");
+        let path = directory.join("drawing.json");
+        for content in ["one", "two", "three", "four"] {
+            write_atomically(&path, content.as_bytes(), 2).unwrap();
+        }
+
+        let backups = backups_of(&path);
+        let generations: Vec<_> = backups.iter().map(|b| b.generation).collect();
+        assert_eq!(generations, [1, 2]);
+        assert_eq!(fs::read(&backups[0].path).unwrap(), b"three");
+        assert_eq!(fs::read(&backups[1].path).unwrap(), b"two");
+        assert_eq!(backups[0].path, directory.join("drawing.json.1.bak"));
+
+        write_atomically(&path, b"five", 1).unwrap();
+        assert_eq!(backups_of(&path).len(), 1);
+        assert_eq!(fs::read(backup_path(&path, 1)).unwrap(), b"four");
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn failed_writes_leave_the_file_alone() {
+        let directory = scratch_directory("failed-write");
+        let path = directory.join("missing").join("drawing.json");
+        assert!(write_atomically(&path, b"one", 3).is_err());
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 0);
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,61 @@
+//! The compact binary encoding of documents (`.pclb` files).
+//!
+//! Documents are encoded as CBOR, starting with the CBOR "self-described"
+//! tag so that the encoding can be recognized from the file header. Asset
+//! data is stored as byte strings rather than base64 text.
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ciborium::Value as Cbor;
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+
+/// The bytes every binary document starts with: CBOR tag 55799.
+pub(crate) const BINARY_MAGIC: &[u8] = b"\xd9\xd9\xf7";
+
+/// Returns `content` in the binary encoding.
+pub(crate) fn encode(content: &impl Serialize) -> Result<Vec<u8>> {
+    let mut bytes = BINARY_MAGIC.to_vec();
+    ciborium::into_writer(content, &mut bytes).context("Failed to encode document")?;
+    Ok(bytes)
+}
+
+/// Returns the content of the binary document `bytes` as JSON, with byte
+/// strings as base64 text.
+pub(crate) fn decode(bytes: &[u8]) -> Result<Value> {
+    let cbor: Cbor = ciborium::from_reader(bytes).context("Invalid binary document")?;
+    to_json(cbor)
+}
+
+/// Returns `cbor` as JSON.
+fn to_json(cbor: Cbor) -> Result<Value> {
+    Ok(match cbor {
+        Cbor::Null => Value::Null,
+        Cbor::Bool(b) => Value::Bool(b),
+        Cbor::Integer(i) => {
+            let i = i128::from(i);
+            match (u64::try_from(i), i64::try_from(i)) {
+                (Ok(u), _) => Value::from(u),
+                (_, Ok(i)) => Value::from(i),
+                _ => bail!("Integer out of range: {i}"),
+            }
+        }
+        Cbor::Float(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+        Cbor::Text(text) => Value::String(text),
+        Cbor::Bytes(bytes) => Value::String(STANDARD.encode(bytes)),
+        Cbor::Array(items) => Value::Array(items.into_iter().map(to_json).collect::<Result<_>>()?),
+        Cbor::Map(entries) => {
+            let mut map = Map::new();
+            for (key, value) in entries {
+                let Cbor::Text(key) = key else {
+                    bail!("Invalid binary document: non-text map key");
+                };
+                map.insert(key, to_json(value)?);
+            }
+            Value::Object(map)
+        }
+        // The self-described tag, and any other.
+        Cbor::Tag(_, value) => to_json(*value)?,
+        _ => bail!("Invalid binary document: unsupported value"),
+    })
+}
@@ -0,0 +1,166 @@
+//! The zipped bundle format for documents (`.pcl` files).
+//!
+//! A bundle holds:
+//! - `manifest.json`, identifying the bundle and listing its entries;
+//! - `document.json`, the document as stored in JSON, without its assets;
+//! - `assets/<key>.<extension>`, the content of each asset;
+//! - `thumbnail.svg`, the first page, for previews.
+
+use crate::asset::Asset;
+use anyhow::{bail, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read, Write};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+/// The `format` recorded in the manifest of every bundle.
+const BUNDLE_FORMAT: &str = "pcl-bundle";
+
+/// The version of the bundle layout written by this application.
+const BUNDLE_VERSION: u64 = 1;
+
+/// The name of the manifest entry.
+const MANIFEST_ENTRY: &str = "manifest.json";
+
+/// The name of the document entry.
+const DOCUMENT_ENTRY: &str = "document.json";
+
+/// The name of the thumbnail entry.
+const THUMBNAIL_ENTRY: &str = "thumbnail.svg";
+
+/// The table of contents of a bundle.
+#[derive(Serialize, Deserialize, Debug)]
+struct Manifest {
+    format: String,
+    version: u64,
+    document: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thumbnail: Option<String>,
+    #[serde(default)]
+    assets: BTreeMap<String, ManifestAsset>,
+}
+
+/// The manifest entry for an asset.
+#[derive(Serialize, Deserialize, Debug)]
+struct ManifestAsset {
+    mime_type: String,
+    path: String,
+}
+
+/// The content of a bundle.
+pub(crate) struct Bundle {
+    /// The document as stored in JSON, without its assets.
+    pub document: Value,
+
+    /// The assets, keyed as in the document.
+    pub assets: BTreeMap<String, Asset>,
+}
+
+/// Returns a bundle holding the JSON `document`, `assets` and `thumbnail`.
+pub(crate) fn write_bundle(
+    document: &[u8],
+    assets: &BTreeMap<&str, &Asset>,
+    thumbnail: &str,
+) -> Result<Vec<u8>> {
+    let compressed = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    // Images are compressed already.
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    let manifest = Manifest {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        document: DOCUMENT_ENTRY.to_string(),
+        thumbnail: Some(THUMBNAIL_ENTRY.to_string()),
+        assets: assets
+            .iter()
+            .map(|(&key, asset)| {
+                let entry = ManifestAsset {
+                    mime_type: asset.mime_type.clone(),
+                    path: asset_path(key, asset),
+                };
+                (key.to_string(), entry)
+            })
+            .collect(),
+    };
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file(MANIFEST_ENTRY, compressed)?;
+    serde_json::to_writer_pretty(&mut zip, &manifest)?;
+    zip.start_file(DOCUMENT_ENTRY, compressed)?;
+    zip.write_all(document)?;
+    for (key, entry) in &manifest.assets {
+        zip.start_file(entry.path.as_str(), stored)?;
+        zip.write_all(&assets[key.as_str()].data)?;
+    }
+    zip.start_file(THUMBNAIL_ENTRY, compressed)?;
+    zip.write_all(thumbnail.as_bytes())?;
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Returns the content of the bundle `bytes`.
+pub(crate) fn read_bundle(bytes: &[u8]) -> Result<Bundle> {
+    let (mut zip, manifest) = open_bundle(bytes)?;
+    let document = serde_json::from_slice(&read_entry(&mut zip, &manifest.document)?)
+        .context("Invalid json")?;
+    let mut assets = BTreeMap::new();
+    for (key, entry) in manifest.assets {
+        let data = read_entry(&mut zip, &entry.path)?;
+        let asset = Asset {
+            mime_type: entry.mime_type,
+            data,
+        };
+        assets.insert(key, asset);
+    }
+    Ok(Bundle { document, assets })
+}
+
+/// Returns the document stored in the bundle `bytes` as JSON, without its assets.
+pub(crate) fn read_bundle_document(bytes: &[u8]) -> Result<Value> {
+    let (mut zip, manifest) = open_bundle(bytes)?;
+    serde_json::from_slice(&read_entry(&mut zip, &manifest.document)?).context("Invalid json")
+}
+
+/// Returns the path of the entry holding `asset`, stored under `key`.
+pub(crate) fn asset_path(key: &str, asset: &Asset) -> String {
+    format!("assets/{key}.{}", extension_for(&asset.mime_type))
+}
+
+/// Returns the archive of the bundle `bytes` and its manifest.
+fn open_bundle(bytes: &[u8]) -> Result<(ZipArchive<Cursor<&[u8]>>, Manifest)> {
+    let mut zip = ZipArchive::new(Cursor::new(bytes)).context("Invalid bundle")?;
+    let manifest: Manifest = serde_json::from_slice(&read_entry(&mut zip, MANIFEST_ENTRY)?)
+        .context("Invalid bundle manifest")?;
+    if manifest.format != BUNDLE_FORMAT {
+        bail!("Not a document bundle: {:?}", manifest.format);
+    }
+    ensure!(
+        manifest.version <= BUNDLE_VERSION,
+        "This bundle uses layout version {}, but this application only understands versions \
+         up to {BUNDLE_VERSION}. Please update the application.",
+        manifest.version
+    );
+    Ok((zip, manifest))
+}
+
+/// Returns the content of the entry `name` of `zip`.
+fn read_entry(zip: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> Result<Vec<u8>> {
+    let mut entry = zip
+        .by_name(name)
+        .with_context(|| format!("Missing bundle entry {name:?}"))?;
+    let mut data = Vec::new();
+    entry
+        .read_to_end(&mut data)
+        .with_context(|| format!("Failed to read bundle entry {name:?}"))?;
+    Ok(data)
+}
+
+/// Returns the file name extension for content of type `mime_type`.
+fn extension_for(mime_type: &str) -> &str {
+    match mime_type {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        _ => "bin",
+    }
+}
@@ -0,0 +1,224 @@
+//! Copying and pasting through the clipboard, as SVG markup and PNG images,
+//! which other applications exchange.
+//!
+//! How the clipboard is reached is up to the platform's
+//! [`ActionHost`](crate::ActionHost): the web and mobile apps use the
+//! webview's async Clipboard API, through the functions here, while the
+//! desktop app uses the system clipboard directly.
+
+use crate::application_state::ApplicationState;
+use crate::export::{encode_rgba_png, render_rgba, PngOptions, CSS_DPI};
+use crate::selection::shape_svg;
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use dioxus::prelude::*;
+
+/// What copying puts on the clipboard.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopiedContent {
+    /// The copied drawing as a standalone SVG file.
+    pub svg: String,
+
+    /// The copied drawing as a PNG image.
+    pub png: Vec<u8>,
+
+    /// The width of the image, in pixels.
+    pub width: u32,
+
+    /// The height of the image, in pixels.
+    pub height: u32,
+}
+
+impl CopiedContent {
+    /// Returns HTML showing the image, for clipboards taking formatted text
+    /// alongside the SVG markup as plain text.
+    pub fn html(&self) -> String {
+        format!(
+            r#"<img src="data:image/png;base64,{}" width="{}" height="{}" alt="">"#,
+            STANDARD.encode(&self.png),
+            self.width,
+            self.height
+        )
+    }
+}
+
+/// Returns what copying puts on the clipboard: the selected shape of the
+/// current document, or else its displayed page.
+pub fn copied_content(state: &ApplicationState) -> Result<CopiedContent> {
+    let open = state.current();
+    let svg = match open.selection() {
+        Some(shape) => shape_svg(&open.document, open.current_page, shape)?,
+        None => open.document.page_svg(open.current_page),
+    };
+    let (width, height, rgba) = render_rgba(&svg, &PngOptions::default())?;
+    Ok(CopiedContent {
+        png: encode_rgba_png(width, height, &rgba, CSS_DPI)?,
+        svg,
+        width,
+        height,
+    })
+}
+
+/// Content read from the clipboard, to paste.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PastedContent {
+    /// SVG markup.
+    Svg(String),
+
+    /// A PNG, JPEG or GIF image.
+    Image(Vec<u8>),
+}
+
+impl PastedContent {
+    /// Returns `text` as SVG markup to paste, if it's that.
+    pub fn from_text(text: &str) -> Option<Self> {
+        let text = text.trim_start_matches('\u{feff}').trim();
+        let markup =
+            text.starts_with("<svg") || (text.starts_with("<?xml") && text.contains("<svg"));
+        markup.then(|| PastedContent::Svg(text.to_string()))
+    }
+
+    /// Returns the `width` × `height` image whose pixels `rgba` holds, as
+    /// unpremultiplied RGBA rows, to paste.
+    pub fn from_rgba(width: u32, height: u32, rgba: &[u8]) -> Result<Self> {
+        encode_rgba_png(width, height, rgba, CSS_DPI).map(PastedContent::Image)
+    }
+}
+
+/// Pastes `content` into the displayed page of the current document.
+pub fn paste_content(state: &mut ApplicationState, content: PastedContent) -> Result<()> {
+    match content {
+        PastedContent::Svg(svg) => state.current_mut().insert_svg(&svg),
+        PastedContent::Image(data) => state.current_mut().insert_image(data),
+    }
+}
+
+/// Puts `content` on the clipboard through the async Clipboard API: the PNG
+/// image, the SVG markup as text, and the SVG markup as such where the
+/// browser supports it.
+pub async fn write_clipboard_api(content: CopiedContent) -> Result<()> {
+    let svg = serde_json::to_string(&content.svg)?;
+    let png = STANDARD.encode(&content.png);
+    let script = format!(
+        r#"
+        const svg = {svg};
+        const png = Uint8Array.from(atob("{png}"), (c) => c.charCodeAt(0));
+        const items = {{
+            "text/plain": new Blob([svg], {{ type: "text/plain" }}),
+            "image/png": new Blob([png], {{ type: "image/png" }}),
+        }};
+        if (typeof ClipboardItem !== "undefined" && ClipboardItem.supports?.("image/svg+xml")) {{
+            items["image/svg+xml"] = new Blob([svg], {{ type: "image/svg+xml" }});
+        }}
+        try {{
+            await navigator.clipboard.write([new ClipboardItem(items)]);
+            return null;
+        }} catch (error) {{
+            try {{
+                await navigator.clipboard.writeText(svg);
+                return null;
+            }} catch (textError) {{
+                return String(textError);
+            }}
+        }}
+        "#
+    );
+    let failure: Option<String> = document::eval(&script)
+        .join()
+        .await
+        .map_err(|e| anyhow!("{e}"))
+        .context("The clipboard is unavailable")?;
+    match failure {
+        Some(failure) => Err(anyhow!(failure).context("The clipboard refused the content")),
+        None => Ok(()),
+    }
+}
+
+/// Returns the content on the clipboard to paste, read through the async
+/// Clipboard API: SVG markup or an image, preferring the markup when the
+/// clipboard holds both, as it does after copying a page.
+pub async fn read_clipboard_api() -> Result<PastedContent> {
+    const SCRIPT: &str = r#"
+        const base64 = async (blob) => {
+            const bytes = new Uint8Array(await blob.arrayBuffer());
+            let binary = "";
+            for (let i = 0; i < bytes.length; i += 0x8000) {
+                binary += String.fromCharCode(...bytes.subarray(i, i + 0x8000));
+            }
+            return btoa(binary);
+        };
+        try {
+            const items = await navigator.clipboard.read();
+            for (const item of items) {
+                if (item.types.includes("image/svg+xml")) {
+                    return ["text", await (await item.getType("image/svg+xml")).text()];
+                }
+                const text = item.types.includes("text/plain")
+                    ? await (await item.getType("text/plain")).text()
+                    : "";
+                if (/<svg[\s>]/.test(text)) return ["text", text];
+                for (const type of ["image/png", "image/jpeg", "image/gif"]) {
+                    if (item.types.includes(type)) {
+                        return ["image", await base64(await item.getType(type))];
+                    }
+                }
+                if (text) return ["text", text];
+            }
+            return ["text", ""];
+        } catch (error) {
+            try {
+                return ["text", await navigator.clipboard.readText()];
+            } catch (textError) {
+                return ["error", String(textError)];
+            }
+        }
+    "#;
+    let (kind, data): (String, String) = document::eval(SCRIPT)
+        .join()
+        .await
+        .map_err(|e| anyhow!("{e}"))
+        .context("The clipboard is unavailable")?;
+    match kind.as_str() {
+        "image" => STANDARD
+            .decode(data)
+            .map(PastedContent::Image)
+            .context("The clipboard image couldn't be read"),
+        "text" => PastedContent::from_text(&data)
+            .ok_or_else(|| anyhow!("The clipboard holds no SVG markup or image")),
+        _ => Err(anyhow!(data).context("The clipboard couldn't be read")),
+    }
+}
+
+/// Returns the text selected in the text field with the keyboard focus, or
+/// `None` if no text field has it, so that copying can be left to the field.
+pub async fn selected_field_text() -> Option<String> {
+    const SCRIPT: &str = r#"
+        const field = document.activeElement;
+        if (!field) return null;
+        if (field.isContentEditable) return String(window.getSelection());
+        if (!["INPUT", "TEXTAREA"].includes(field.tagName)) return null;
+        return field.value.substring(field.selectionStart ?? 0, field.selectionEnd ?? 0);
+    "#;
+    document::eval(SCRIPT).join().await.ok().flatten()
+}
+
+/// Types `text` into the text field with the keyboard focus, returning
+/// `false` if no text field has it, so that pasting can be left to the field.
+pub async fn insert_into_field(text: &str) -> Result<bool> {
+    let text = serde_json::to_string(text)?;
+    let script = format!(
+        r#"
+        const field = document.activeElement;
+        const editable = field && (field.isContentEditable
+            || ["INPUT", "TEXTAREA"].includes(field.tagName));
+        if (!editable) return false;
+        document.execCommand("insertText", false, {text});
+        return true;
+        "#
+    );
+    document::eval(&script)
+        .join()
+        .await
+        .map_err(|e| anyhow!("{e}"))
+        .context("The text field couldn't be reached")
+}
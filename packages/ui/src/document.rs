@@ -1,12 +1,48 @@
-use anyhow::{Context, Result};
+use crate::asset::{
+    asset_href, asset_key, decode_image_data_url, for_each_image_href_mut, Asset, AssetTable,
+};
+use crate::atomic_save::{write_atomically, DEFAULT_BACKUP_GENERATIONS};
+use crate::binary;
+use crate::bundle::{asset_path, read_bundle, read_bundle_document, write_bundle};
+use crate::file_format::FileFormat;
+use crate::layer::Layer;
+use crate::metadata::Metadata;
+use crate::page::Page;
+use crate::sanitizer::{sanitize, sanitize_page, SanitizeReport};
+use crate::schema::{migrate, version_of, Versioned};
+use crate::shape::{Drawing, Element, Group, Shape, Style, ViewBox};
+use crate::svg_parser::parse_svg;
+use crate::validation::{check_svg, validate_svg, Diagnostic};
+use anyhow::{bail, ensure, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use serde::{Deserialize, Serialize};
-use std::{fs::File, path::Path};
+use serde_json::Value;
+use std::{collections::BTreeMap, fs, path::Path};
 
 /// In-memory representation of a pcl-demo document.
-#[derive(Serialize, Deserialize, Debug)]
+///
+/// Use [`Document::from_json`] and [`Document::to_json`] (or the file
+/// functions) rather than serde directly, so that the format version is
+/// written and older formats are upgraded.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Document {
-    /// The content in HTML form.
-    html: String,
+    /// Descriptive information about the document.
+    metadata: Metadata,
+
+    /// The content in structured form, in page order (never empty).
+    pages: Vec<Page>,
+
+    /// The binary assets referenced by the pages' images.
+    #[serde(default, skip_serializing_if = "AssetTable::is_empty")]
+    assets: AssetTable,
+
+    /// What was removed as unsafe when the document was loaded.
+    #[serde(skip)]
+    sanitize_report: SanitizeReport,
+
+    /// The problems found in the document's markup when it was loaded.
+    #[serde(skip)]
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl Default for Document {
@@ -18,31 +54,555 @@ impl Default for Document {
 impl Document {
     /// Returns a new empty document.
     pub fn new() -> Self {
-        Self {
-            html: "<svg viewBox=\"0 0 70 70\" xmlns=\"http://www.w3.org/2000/svg\">\n<text x=\"35\" y=\"35\" text-anchor=\"middle\" dominant-baseline=\"middle\" font-size=\"8\">New Document</text>\n</svg>".to_string()
+        Self::from_html("<svg viewBox=\"0 0 70 70\" xmlns=\"http://www.w3.org/2000/svg\">\n<text x=\"35\" y=\"35\" text-anchor=\"middle\" dominant-baseline=\"middle\" font-size=\"8\">New Document</text>\n</svg>")
+            .expect("The new document template is valid SVG")
+    }
+
+    /// Returns the document whose content is the SVG markup `html`.
+    ///
+    /// Fails with an [`InvalidSvg`](crate::InvalidSvg) error, carrying located
+    /// diagnostics, if `html` can't be loaded.
+    pub fn from_html(html: &str) -> Result<Self> {
+        let diagnostics = check_svg(html)?;
+        Ok(Self {
+            metadata: Metadata::new(),
+            pages: vec![Page::from_drawing(parse_svg(html)?)],
+            assets: AssetTable::default(),
+            sanitize_report: SanitizeReport::default(),
+            diagnostics,
+        }
+        .sanitized()
+        .with_embedded_images_extracted())
+    }
+
+    /// Returns the document stored as `value`, in any supported format version.
+    pub fn from_value(value: Value) -> Result<Self> {
+        // Version 1 embeds hand-written markup, so report problems against it.
+        let diagnostics = match value.get("html").and_then(Value::as_str) {
+            Some(html) if version_of(&value)? == 1 => Some(check_svg(html)?),
+            _ => None,
+        };
+
+        let mut document: Self =
+            serde_json::from_value(migrate(value)?).context("Invalid document")?;
+        ensure!(!document.pages.is_empty(), "Invalid document: no pages");
+        ensure!(
+            document.pages.iter().all(|page| !page.layers.is_empty()),
+            "Invalid document: a page has no layers"
+        );
+        document.assets.check().context("Invalid document")?;
+        document.diagnostics = diagnostics.unwrap_or_else(|| document.validate_pages());
+        Ok(document.sanitized().with_embedded_images_extracted())
+    }
+
+    /// Returns the problems found in the markup of each page of `self`.
+    ///
+    /// The markup is written from the model rather than by the user, so the
+    /// diagnostics aren't located in it.
+    fn validate_pages(&self) -> Vec<Diagnostic> {
+        let multiple_pages = self.pages.len() > 1;
+        let mut diagnostics = Vec::new();
+        for (index, page) in self.pages.iter().enumerate() {
+            for mut diagnostic in validate_svg(&page.to_drawing().to_svg()) {
+                diagnostic.position = None;
+                if multiple_pages {
+                    diagnostic.message = format!("{}: {}", page.label(index), diagnostic.message);
+                }
+                diagnostics.push(diagnostic);
+            }
+        }
+        diagnostics
+    }
+
+    /// Returns `self` with unsafe content removed and recorded in its report.
+    fn sanitized(mut self) -> Self {
+        self.sanitize_report = SanitizeReport::default();
+        for page in &mut self.pages {
+            let report = sanitize_page(page);
+            self.sanitize_report.removals.extend(report.removals);
         }
+        self
+    }
+
+    /// Returns `self` with the images embedded as `data:` URLs moved into
+    /// its asset table.
+    fn with_embedded_images_extracted(mut self) -> Self {
+        let assets = &mut self.assets;
+        for shape in self
+            .pages
+            .iter_mut()
+            .flat_map(|page| &mut page.layers)
+            .flat_map(|layer| &mut layer.shapes)
+        {
+            for_each_image_href_mut(shape, &mut |href| {
+                if let Some((mime_type, data)) = decode_image_data_url(href) {
+                    *href = asset_href(&assets.insert(mime_type, data));
+                }
+            });
+        }
+        self
+    }
+
+    /// Returns the assets referenced from the pages of `self`, with their keys.
+    fn used_assets(&self) -> BTreeMap<&str, &Asset> {
+        let shapes = self
+            .pages
+            .iter()
+            .flat_map(|page| &page.layers)
+            .flat_map(|layer| &layer.shapes);
+        self.assets.used_by(shapes)
+    }
+
+    /// Returns the form in which `self` is stored, with `assets`.
+    fn stored<'a>(&'a self, assets: BTreeMap<&'a str, &'a Asset>) -> Versioned<Stored<'a>> {
+        Versioned::new(Stored {
+            metadata: &self.metadata,
+            pages: &self.pages,
+            assets,
+        })
+    }
+
+    /// Returns the metadata of the document stored as the JSON text `json`,
+    /// without loading its content.
+    pub fn metadata_from_json(json: &str) -> Result<Metadata> {
+        Self::metadata_from_value(serde_json::from_str(json).context("Invalid json")?)
+    }
+
+    /// Returns the metadata of the document stored as `bytes`, in any file
+    /// format, without loading its content.
+    pub fn metadata_from_bytes(bytes: &[u8]) -> Result<Metadata> {
+        let value = match FileFormat::detect(bytes) {
+            FileFormat::Json => serde_json::from_slice(bytes).context("Invalid json")?,
+            FileFormat::Bundle => read_bundle_document(bytes)?,
+            FileFormat::Binary => binary::decode(bytes)?,
+        };
+        Self::metadata_from_value(value)
+    }
+
+    /// Returns the metadata of the document stored as `value`.
+    fn metadata_from_value(value: Value) -> Result<Metadata> {
+        let mut value = migrate(value)?;
+        serde_json::from_value(value["metadata"].take()).context("Invalid metadata")
+    }
+
+    /// Returns the document stored as the JSON text `json`.
+    pub fn from_json(json: &str) -> Result<Self> {
+        Self::from_value(serde_json::from_str(json).context("Invalid json")?)
     }
 
-    /// Returns the document at `p`.
+    /// Returns `self` as pretty-printed JSON in the current format version.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(&self.stored(self.used_assets()))
+            .context("Failed to serialize document")
+    }
+
+    /// Returns `self` as a JSON value in the current format version.
+    pub(crate) fn to_value(&self) -> Result<Value> {
+        serde_json::to_value(self.stored(self.used_assets()))
+            .context("Failed to serialize document")
+    }
+
+    /// Returns the document stored as `bytes`, in any file format.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        match FileFormat::detect(bytes) {
+            FileFormat::Json => {
+                Self::from_value(serde_json::from_slice(bytes).context("Invalid json")?)
+            }
+            FileFormat::Bundle => Self::from_bundle(bytes),
+            FileFormat::Binary => Self::from_value(binary::decode(bytes)?),
+        }
+    }
+
+    /// Returns the document stored as the bundle `bytes`.
+    fn from_bundle(bytes: &[u8]) -> Result<Self> {
+        let bundle = read_bundle(bytes)?;
+        let mut document = Self::from_value(bundle.document)?;
+        for (key, asset) in bundle.assets {
+            document
+                .assets
+                .insert_loaded(key, asset)
+                .context("Corrupt bundle")?;
+        }
+        Ok(document)
+    }
+
+    /// Returns `self` stored in `format`.
+    ///
+    /// Converting between formats is loading in one and storing in the other.
+    pub fn to_bytes(&self, format: FileFormat) -> Result<Vec<u8>> {
+        match format {
+            FileFormat::Json => Ok(self.to_json()?.into_bytes()),
+            FileFormat::Bundle => {
+                let document = serde_json::to_vec_pretty(&self.stored(BTreeMap::new()))
+                    .context("Failed to serialize document")?;
+                let thumbnail = self.render_page(0, asset_path);
+                write_bundle(&document, &self.used_assets(), &thumbnail)
+                    .context("Failed to write bundle")
+            }
+            FileFormat::Binary => binary::encode(&self.stored(self.used_assets())),
+        }
+    }
+
+    /// Returns the document at `p`, in any file format.
     pub fn new_from_file<P: AsRef<Path>>(p: P) -> Result<Self> {
         let p: &Path = p.as_ref();
 
-        let f = File::open(p).context(format!("Failed to open: {p:?}"))?;
+        let bytes = fs::read(p).context(format!("Failed to open: {p:?}"))?;
 
-        serde_json::from_reader(f).context(format!("Invalid json: {p:?}"))
+        Self::from_bytes(&bytes).context(format!("Failed to load: {p:?}"))
     }
 
-    /// Saves the document as `p`.
+    /// Saves the document as `p`, in the file format its extension names
+    /// (JSON if none), keeping the default number of backups.
     pub fn save_to_file<P: AsRef<Path>>(&self, p: P) -> Result<()> {
+        self.save_to_file_with_backups(p, DEFAULT_BACKUP_GENERATIONS)
+    }
+
+    /// Saves the document as `p`, like [`Document::save_to_file`], keeping
+    /// `generations` backups of the previous content.
+    ///
+    /// The file is replaced atomically, so a failed save leaves it intact.
+    pub fn save_to_file_with_backups<P: AsRef<Path>>(
+        &self,
+        p: P,
+        generations: usize,
+    ) -> Result<()> {
         let p: &Path = p.as_ref();
 
-        let f = File::create(p).context(format!("Failed to create: {p:?}"))?;
+        let bytes = self.to_bytes(FileFormat::from_path(p).unwrap_or_default())?;
+
+        write_atomically(p, &bytes, generations).context(format!("Failed to save: {p:?}"))
+    }
+
+    /// Returns the descriptive information about `self`.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Returns the descriptive information about `self` for editing.
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        &mut self.metadata
+    }
+
+    /// Records that `self` is being saved now.
+    pub fn touch(&mut self) {
+        self.metadata.touch();
+    }
+
+    /// Returns the binary assets of `self`.
+    pub fn assets(&self) -> &AssetTable {
+        &self.assets
+    }
+
+    /// Adds the PNG, JPEG or GIF image `data` to the topmost editable layer
+    /// of the page at `index`, scaled down to fit the page if needed.
+    ///
+    /// The image data is stored only once however often it is added.
+    pub fn insert_image(&mut self, index: usize, data: Vec<u8>) -> Result<()> {
+        let size = imagesize::blob_size(&data).context("Unreadable image")?;
+        let key = self.assets.insert_image(data)?;
+
+        let page = self.pages.get_mut(index).context("No such page")?;
+        let (x, y, scale) = match page.view_box {
+            Some(view_box) => {
+                let scale = (view_box.width / size.width as f64)
+                    .min(view_box.height / size.height as f64)
+                    .min(1.0);
+                (view_box.min_x, view_box.min_y, scale)
+            }
+            None => (0.0, 0.0, 1.0),
+        };
+        let layer = topmost_editable_layer(page)?;
+
+        let attribute = |name: &str, value: String| (name.to_string(), value);
+        layer.shapes.push(Shape::Other(Element {
+            name: "image".to_string(),
+            attributes: vec![
+                attribute("x", x.to_string()),
+                attribute("y", y.to_string()),
+                attribute("width", (size.width as f64 * scale).to_string()),
+                attribute("height", (size.height as f64 * scale).to_string()),
+                attribute("href", asset_href(&key)),
+            ],
+            content: Vec::new(),
+        }));
+        Ok(())
+    }
+
+    /// Adds the drawing in the SVG markup `svg` to the topmost editable layer
+    /// of the page at `index`, as a group scaled down to fit the page if
+    /// needed.
+    ///
+    /// The drawing is sanitized, and only its visible layers are added; the
+    /// images it embeds are stored with the document's other assets.
+    pub fn insert_svg(&mut self, index: usize, svg: &str) -> Result<()> {
+        let pasted = Self::from_html(svg)?;
+        let source = &pasted.pages[0];
+        ensure!(index < self.pages.len(), "No such page");
+        for (_, asset) in pasted.assets.iter() {
+            self.assets.insert(&asset.mime_type, asset.data.clone());
+        }
+
+        let page = &mut self.pages[index];
+        let source_box = source.view_box.or(match (source.width, source.height) {
+            (Some(width), Some(height)) => Some(ViewBox {
+                min_x: 0.0,
+                min_y: 0.0,
+                width,
+                height,
+            }),
+            _ => None,
+        });
+        let mut style = Style::default();
+        if let (Some(target), Some(source)) = (page.view_box, source_box) {
+            if source.width > 0.0 && source.height > 0.0 {
+                let scale = (target.width / source.width)
+                    .min(target.height / source.height)
+                    .min(1.0);
+                let x = target.min_x - source.min_x * scale;
+                let y = target.min_y - source.min_y * scale;
+                if scale != 1.0 || x != 0.0 || y != 0.0 {
+                    style.transform = Some(format!("translate({x} {y}) scale({scale})"));
+                }
+            }
+        }
+        let children = source
+            .layers
+            .iter()
+            .filter(|layer| layer.visible)
+            .flat_map(|layer| layer.shapes.iter().cloned())
+            .collect();
+
+        let layer = topmost_editable_layer(page)?;
+        layer.shapes.push(Shape::Group(Group { children, style }));
+        Ok(())
+    }
+
+    /// Returns the pages of `self`, in order.
+    pub fn pages(&self) -> &[Page] {
+        &self.pages
+    }
+
+    /// Returns the page at `index`, if any.
+    pub fn page(&self, index: usize) -> Option<&Page> {
+        self.pages.get(index)
+    }
+
+    /// Returns the page at `index` for editing, if any.
+    pub fn page_mut(&mut self, index: usize) -> Option<&mut Page> {
+        self.pages.get_mut(index)
+    }
+
+    /// Returns the number of pages in `self`.
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Inserts `page` so that it is at `index`.
+    pub fn insert_page(&mut self, index: usize, page: Page) -> Result<()> {
+        ensure!(index <= self.pages.len(), "No page position {index}");
+        self.pages.insert(index, page);
+        Ok(())
+    }
+
+    /// Removes and returns the page at `index`; the last page can't be removed.
+    pub fn remove_page(&mut self, index: usize) -> Result<Page> {
+        ensure!(index < self.pages.len(), "No page at position {index}");
+        if self.pages.len() == 1 {
+            bail!("A document must have at least one page");
+        }
+        Ok(self.pages.remove(index))
+    }
+
+    /// Inserts a copy of the page at `index` right after it, returning the
+    /// copy's index.
+    pub fn duplicate_page(&mut self, index: usize) -> Result<usize> {
+        let mut copy = self.page(index).context("No such page")?.clone();
+        if !copy.name.is_empty() {
+            copy.name = format!("{} copy", copy.name);
+        }
+        self.pages.insert(index + 1, copy);
+        Ok(index + 1)
+    }
+
+    /// Moves the page at `from` so that it is at `to`.
+    pub fn move_page(&mut self, from: usize, to: usize) -> Result<()> {
+        ensure!(
+            from < self.pages.len() && to < self.pages.len(),
+            "No page at position {}",
+            from.max(to)
+        );
+        let page = self.pages.remove(from);
+        self.pages.insert(to, page);
+        Ok(())
+    }
+
+    /// Returns what was removed as unsafe when `self` was loaded.
+    pub fn sanitize_report(&self) -> &SanitizeReport {
+        &self.sanitize_report
+    }
+
+    /// Returns the problems found in the markup of `self` when it was loaded.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Forgets what was removed when `self` was loaded, once the user has seen it.
+    pub fn dismiss_sanitize_report(&mut self) {
+        self.sanitize_report = SanitizeReport::default();
+    }
 
-        serde_json::to_writer_pretty(f, self).context(format!("Failed to write: {p:?}"))
+    /// Returns the HTML to render the page at `index` (empty if there is no
+    /// such page).
+    ///
+    /// This is also the page as plain SVG, with a `<g>` for each layer. The
+    /// drawing is sanitized again, since it may have been edited since loading.
+    pub fn page_html(&self, index: usize) -> String {
+        self.render_page(index, |_, asset| asset.data_url())
     }
 
-    /// Returns the HTML to render `self`.
-    pub fn to_html(&self) -> String {
-        self.html.clone()
+    /// Returns the page at `index` as the `data:` URL of an SVG image (empty
+    /// if there is no such page), for previews shown beside the page itself:
+    /// unlike its markup, an image keeps the ids of its elements to itself.
+    pub fn page_image_url(&self, index: usize) -> String {
+        match self.page(index) {
+            Some(_) => format!(
+                "data:image/svg+xml;base64,{}",
+                STANDARD.encode(self.page_html(index))
+            ),
+            None => String::new(),
+        }
+    }
+
+    /// Returns the page at `index` as a standalone SVG file for other
+    /// applications (empty if there is no such page).
+    ///
+    /// Its images are embedded, and its hidden layers and the attributes only
+    /// this application reads are left out.
+    pub fn page_svg(&self, index: usize) -> String {
+        let Some(page) = self.page(index) else {
+            return String::new();
+        };
+        let svg = self.render_drawing(page.to_plain_drawing(), |_, asset| asset.data_url());
+        format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{svg}\n")
+    }
+
+    /// Returns the sanitized SVG markup of the page at `index` (empty if
+    /// there is no such page), linking to each asset by `link(key, asset)`.
+    fn render_page(&self, index: usize, link: impl Fn(&str, &Asset) -> String) -> String {
+        match self.page(index) {
+            Some(page) => self.render_drawing(page.to_drawing(), link),
+            None => String::new(),
+        }
+    }
+
+    /// Returns the sanitized SVG markup of `drawing`, a page of `self`,
+    /// linking to each asset by `link(key, asset)`.
+    pub(crate) fn render_drawing(
+        &self,
+        mut drawing: Drawing,
+        link: impl Fn(&str, &Asset) -> String,
+    ) -> String {
+        sanitize(&mut drawing);
+        for shape in &mut drawing.shapes {
+            for_each_image_href_mut(shape, &mut |href| {
+                if let Some((key, asset)) =
+                    asset_key(href).and_then(|key| Some((key.to_string(), self.assets.get(key)?)))
+                {
+                    *href = link(&key, asset);
+                }
+            });
+        }
+        drawing.to_svg()
+    }
+}
+
+/// Returns the topmost layer of `page` that's visible and unlocked, which
+/// content added to the page goes in.
+fn topmost_editable_layer(page: &mut Page) -> Result<&mut Layer> {
+    page.layers
+        .iter_mut()
+        .rev()
+        .find(|layer| layer.visible && !layer.locked)
+        .context("Every layer of this page is hidden or locked")
+}
+
+/// The stored form of a [`Document`].
+#[derive(Serialize)]
+struct Stored<'a> {
+    metadata: &'a Metadata,
+    pages: &'a [Page],
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    assets: BTreeMap<&'a str, &'a Asset>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::content_hash;
+
+    #[test]
+    fn diagnostics_are_located_only_in_markup_the_user_wrote() {
+        let document = Document::from_html(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\">\n<rect x=\"one\"/>\n</svg>",
+        )
+        .unwrap();
+        let [diagnostic] = document.diagnostics() else {
+            panic!("Expected one diagnostic");
+        };
+        assert!(diagnostic.position.is_some());
+
+        let reloaded = Document::from_json(&document.to_json().unwrap()).unwrap();
+        let [diagnostic] = reloaded.diagnostics() else {
+            panic!("Expected one diagnostic");
+        };
+        assert_eq!(diagnostic.position, None);
+        assert!(diagnostic.to_string().starts_with("warning: "));
+    }
+
+    #[test]
+    fn comments_and_instructions_are_stored() {
+        let document = Document::from_html(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\"><!-- note --><?app-data x?></svg>",
+        )
+        .unwrap();
+        let reloaded = Document::from_json(&document.to_json().unwrap()).unwrap();
+        assert_eq!(reloaded.pages(), document.pages());
+    }
+
+    const PIXEL: &[u8] = include_bytes!("fixtures/pixel.png");
+
+    /// Returns a document embedding the image `PIXEL`.
+    fn document_with_image() -> Document {
+        let mut document = Document::new();
+        document.insert_image(0, PIXEL.to_vec()).unwrap();
+        document
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        let document = document_with_image();
+        for format in FileFormat::ALL {
+            let bytes = document.to_bytes(format).unwrap();
+            assert_eq!(FileFormat::detect(&bytes), format);
+            let loaded = Document::from_bytes(&bytes).unwrap();
+            assert_eq!(loaded.pages(), document.pages(), "{format:?}");
+            assert_eq!(loaded.assets(), document.assets(), "{format:?}");
+            assert_eq!(loaded.metadata(), document.metadata(), "{format:?}");
+        }
+    }
+
+    #[test]
+    fn assets_not_matching_their_content_are_rejected() {
+        let key = content_hash(PIXEL);
+        let mut value: Value =
+            serde_json::from_str(&document_with_image().to_json().unwrap()).unwrap();
+        value["assets"][&key]["mime_type"] = "text/html".into();
+        assert!(Document::from_value(value.clone()).is_err());
+
+        value["assets"][&key]["mime_type"] = "image/png".into();
+        let asset = value["assets"][&key].take();
+        value["assets"] = serde_json::json!({ "0".repeat(64): asset });
+        assert!(Document::from_value(value).is_err());
     }
 }
@@ -1,5 +1,17 @@
 use crate::application_state::ApplicationState;
-use crate::platform::FileMenu;
+use crate::file_drop::drop_files;
+use crate::keybindings::listen_for_shortcuts;
+use crate::layer_ui::LayerPanel;
+use crate::metadata::format_timestamp;
+use crate::notifications::report_failure;
+use crate::notifications_ui::NotificationCenter;
+use crate::page_ui::PageNavigator;
+use crate::platform::{sleep, FileMenu};
+use crate::properties_ui::PropertiesPanel;
+use crate::selection_ui::PageCanvas;
+use crate::tab_ui::DocumentTabs;
+use crate::validation::Severity;
+use dioxus::html::HasFileData;
 use dioxus::prelude::*;
 
 /// The stylesheet for document rendering.
@@ -8,18 +20,278 @@ const DOCUMENT_CSS: Asset = asset!("/assets/styling/document.css");
 /// The UI element that describes a document.
 #[component]
 pub fn DocumentUI(application_state: Signal<ApplicationState>) -> Element {
-    // Convert the document to something we can display.
-    let html = application_state.read().the_only_document.to_html();
+    // Autosave unsaved changes for recovery after a crash.
+    use_future(move || async move {
+        loop {
+            let interval = application_state.peek().autosave_interval;
+            sleep(interval).await;
+            if application_state.peek().needs_autosave() {
+                let autosaved = application_state.write().autosave();
+                if let Err(e) = autosaved {
+                    let notifications = &mut application_state.write().notifications;
+                    notifications.warning("Unsaved changes couldn't be autosaved", &e);
+                }
+            }
+        }
+    });
+
+    // Carry out the actions whose shortcuts are pressed.
+    use_future(move || listen_for_shortcuts(application_state));
+
+    // Whether files are being dragged over the document area.
+    let mut dragging = use_signal(|| false);
+
+    let title = application_state.read().window_title();
 
     rsx! {
         document::Link { rel: "stylesheet", href: DOCUMENT_CSS }
 
+        // The window title on desktop, the tab title on the web
+        document::Title { "{title}" }
+
         // Show appropriate file menu for each platform
         FileMenu { application_state }
 
+        DiagnosticsNotice { application_state }
+
+        RestoreNotice { application_state }
+
+        RecoveryNotice { application_state }
+
+        SanitizeNotice { application_state }
+
+        PropertiesPanel { application_state }
+
+        DocumentTabs { application_state }
+
+        NotificationCenter { application_state }
+
+        PageNavigator { application_state }
+
+        LayerPanel { application_state }
+
         div {
             id: "document",
-            dangerous_inner_html: html
+            class: if dragging() { "drop-target" },
+            // Documents dropped here are opened, and SVG files and images
+            // added to the page.
+            ondragover: move |e: DragEvent| {
+                e.prevent_default();
+                dragging.set(true);
+            },
+            ondragleave: move |_| dragging.set(false),
+            ondrop: move |e: DragEvent| {
+                e.prevent_default();
+                dragging.set(false);
+                if let Some(files) = e.files() {
+                    spawn(drop_files(application_state, files));
+                }
+            },
+            PageCanvas { application_state }
+        }
+    }
+}
+
+/// A notice listing the problems found in the markup of the document last
+/// opened, if any.
+#[component]
+fn DiagnosticsNotice(application_state: Signal<ApplicationState>) -> Element {
+    let diagnostics = application_state.read().open_diagnostics.clone();
+    if diagnostics.is_empty() {
+        return rsx! {};
+    }
+
+    let heading = if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        "This document couldn't be opened because its SVG is invalid:"
+    } else {
+        "Problems were found in this document's SVG:"
+    };
+    let dismiss = move |_| application_state.write().open_diagnostics.clear();
+
+    rsx! {
+        div {
+            class: "document-notice diagnostics-notice",
+            div {
+                class: "document-notice-header",
+                span { "{heading}" }
+                button { class: "document-notice-dismiss", title: "Dismiss", onclick: dismiss, "✕" }
+            }
+            ul {
+                for diagnostic in diagnostics {
+                    li { "{diagnostic}" }
+                }
+            }
+        }
+    }
+}
+
+/// A notice offering to restore the file that last failed to open from one
+/// of its backups, if it has any.
+#[component]
+fn RestoreNotice(application_state: Signal<ApplicationState>) -> Element {
+    let Some(offer) = application_state.read().restore_offer.clone() else {
+        return rsx! {};
+    };
+
+    let name = offer
+        .path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let dismiss = move |_| application_state.write().restore_offer = None;
+
+    rsx! {
+        div {
+            class: "document-notice",
+            div {
+                class: "document-notice-header",
+                span { "\"{name}\" couldn't be opened. Restore it from a backup?" }
+                button { class: "document-notice-dismiss", title: "Dismiss", onclick: dismiss, "✕" }
+            }
+            ul {
+                for backup in offer.backups {
+                    li {
+                        class: "restore-backup",
+                        match backup.modified {
+                            Some(time) => rsx! {
+                                "Backup {backup.generation} — saved {format_timestamp(&time)}"
+                            },
+                            None => rsx! { "Backup {backup.generation}" },
+                        }
+                        button {
+                            class: "restore-backup-button",
+                            onclick: move |_| {
+                                let result = application_state.write().restore_backup(&backup.path);
+                                report_failure(application_state, "Failed to restore backup", result);
+                            },
+                            "Restore"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A notice offering to restore the documents recovered from earlier
+/// sessions, if any, each with its autosave time and a preview.
+#[component]
+fn RecoveryNotice(application_state: Signal<ApplicationState>) -> Element {
+    let recovered: Vec<_> = application_state
+        .read()
+        .recovered
+        .iter()
+        .map(|r| {
+            let file_name = r
+                .path
+                .as_ref()
+                .and_then(|p| p.file_name())
+                .map(|n| n.to_string_lossy().into_owned());
+            let preview = r.document.page_image_url(0);
+            (
+                r.id.clone(),
+                r.title().to_string(),
+                file_name,
+                r.saved,
+                preview,
+            )
+        })
+        .collect();
+    if recovered.is_empty() {
+        return rsx! {};
+    }
+
+    // Dismissing keeps the documents for the next launch.
+    let dismiss = move |_| application_state.write().recovered.clear();
+
+    rsx! {
+        div {
+            class: "document-notice",
+            div {
+                class: "document-notice-header",
+                span { "Unsaved work from an earlier session was recovered:" }
+                button { class: "document-notice-dismiss", title: "Dismiss", onclick: dismiss, "✕" }
+            }
+            for (id, title, file_name, saved, preview) in recovered {
+                div {
+                    key: "{id}",
+                    class: "recovered-document",
+                    img { class: "recovered-preview", src: "{preview}", alt: "" }
+                    div {
+                        class: "recovered-info",
+                        div { class: "recovered-title", "{title}" }
+                        if let Some(file_name) = file_name {
+                            div { "{file_name}" }
+                        }
+                        div { "Autosaved {format_timestamp(&saved)}" }
+                    }
+                    button {
+                        class: "restore-backup-button",
+                        onclick: {
+                            let id = id.clone();
+                            move |_| {
+                                let result = application_state.write().restore_recovered(&id);
+                                report_failure(
+                                    application_state,
+                                    "Failed to restore recovered document",
+                                    result,
+                                );
+                            }
+                        },
+                        "Restore"
+                    }
+                    button {
+                        class: "restore-backup-button",
+                        onclick: move |_| {
+                            let result = application_state.write().discard_recovered(&id);
+                            report_failure(
+                                application_state,
+                                "Failed to discard recovered document",
+                                result,
+                            );
+                        },
+                        "Discard"
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A notice listing what was removed from the document as unsafe, if anything.
+#[component]
+fn SanitizeNotice(application_state: Signal<ApplicationState>) -> Element {
+    let lines = application_state
+        .read()
+        .current()
+        .document
+        .sanitize_report()
+        .lines();
+    if lines.is_empty() {
+        return rsx! {};
+    }
+
+    let dismiss = move |_| {
+        application_state
+            .write()
+            .current_mut()
+            .document
+            .dismiss_sanitize_report();
+    };
+
+    rsx! {
+        div {
+            class: "document-notice",
+            div {
+                class: "document-notice-header",
+                span { "Some content was removed from this document because it could run code:" }
+                button { class: "document-notice-dismiss", title: "Dismiss", onclick: dismiss, "✕" }
+            }
+            ul {
+                for line in lines {
+                    li { "{line}" }
+                }
+            }
         }
     }
 }
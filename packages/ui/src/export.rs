@@ -0,0 +1,282 @@
+//! Exporting the pages of a document in formats other applications read.
+//!
+//! Bitmaps are rendered on the CPU by resvg, so that exports look the same
+//! on every platform; PDFs are written by [`crate::pdf_export`].
+
+use crate::document::Document;
+use crate::open_document::OpenDocument;
+use crate::pdf_export::{export_pdf, PdfOptions};
+use anyhow::{anyhow, bail, Context, Result};
+use resvg::tiny_skia::{Color, Pixmap, Transform};
+use resvg::usvg::{self, fontdb, TreeParsing, TreePostProc};
+use std::sync::OnceLock;
+
+/// The resolution at which a page is its own size, in dots per inch: that of
+/// CSS pixels.
+pub const CSS_DPI: f32 = 96.0;
+
+/// The largest width or height of an exported bitmap, in pixels.
+pub const MAX_BITMAP_SIDE: u32 = 16384;
+
+/// A format pages can be exported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Portable Network Graphics, a bitmap.
+    Png,
+
+    /// Portable Document Format, with vector shapes and real text.
+    Pdf,
+
+    /// A standalone SVG file, as other vector graphics applications read it.
+    Svg,
+}
+
+impl ExportFormat {
+    /// All formats, in the order they're offered.
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Png, ExportFormat::Pdf, ExportFormat::Svg];
+
+    /// Returns the format with `extension`, if any.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extension().eq_ignore_ascii_case(extension))
+    }
+
+    /// Returns the extension of files in the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Png => "png",
+            ExportFormat::Pdf => "pdf",
+            ExportFormat::Svg => "svg",
+        }
+    }
+
+    /// Returns the name of the format for display.
+    pub fn description(self) -> &'static str {
+        match self {
+            ExportFormat::Png => "PNG Image",
+            ExportFormat::Pdf => "PDF Document",
+            ExportFormat::Svg => "SVG Image",
+        }
+    }
+
+    /// Returns `true` iff the format holds every page of a document, rather
+    /// than the current one.
+    pub fn holds_every_page(self) -> bool {
+        self == ExportFormat::Pdf
+    }
+
+    /// Returns the name to suggest for the export of `open`, e.g.
+    /// "report-page-2.png" for its current page.
+    pub fn file_name(self, open: &OpenDocument) -> String {
+        let stem = open
+            .file_path
+            .as_ref()
+            .and_then(|p| p.file_stem())
+            .and_then(|n| n.to_str())
+            .unwrap_or("document");
+        if open.document.page_count() > 1 && !self.holds_every_page() {
+            format!("{stem}-page-{}.{}", open.current_page + 1, self.extension())
+        } else {
+            format!("{stem}.{}", self.extension())
+        }
+    }
+}
+
+/// How pages are exported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportOptions {
+    /// The format to export in.
+    pub format: ExportFormat,
+
+    /// How pages are exported as PNG.
+    pub png: PngOptions,
+
+    /// How pages are exported as PDF.
+    pub pdf: PdfOptions,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::Png,
+            png: PngOptions::default(),
+            pdf: PdfOptions::default(),
+        }
+    }
+}
+
+/// How a page is exported as PNG.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PngOptions {
+    /// How many times its size the page is drawn, at [`CSS_DPI`].
+    pub scale: f32,
+
+    /// The resolution the page is drawn at, in dots per inch, recorded in
+    /// the PNG; on top of the scale, the page is drawn `dpi / CSS_DPI` times
+    /// its size.
+    pub dpi: f32,
+
+    /// The color drawn behind the page, as "#rrggbb", unless transparent.
+    pub background: String,
+
+    /// Whether the page is drawn with nothing behind it.
+    pub transparent: bool,
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            dpi: CSS_DPI,
+            background: "#ffffff".to_string(),
+            transparent: false,
+        }
+    }
+}
+
+/// Returns the page at `index` in `document` exported as `options` say, or
+/// every page for formats holding several.
+pub fn export(document: &Document, index: usize, options: &ExportOptions) -> Result<Vec<u8>> {
+    match options.format {
+        ExportFormat::Png => export_png(document, index, &options.png),
+        ExportFormat::Pdf => export_pdf(document, &options.pdf),
+        ExportFormat::Svg => export_svg(document, index),
+    }
+}
+
+/// Returns the page at `index` in `document` as a PNG.
+pub fn export_png(document: &Document, index: usize, options: &PngOptions) -> Result<Vec<u8>> {
+    if index >= document.page_count() {
+        bail!("No page {} to export", index + 1);
+    }
+    let (width, height, rgba) = render_rgba(&document.page_html(index), options)?;
+    encode_rgba_png(width, height, &rgba, options.dpi)
+}
+
+/// Returns the width, height and pixels of the SVG markup `svg` drawn as
+/// `options` say, as unpremultiplied RGBA rows.
+pub(crate) fn render_rgba(svg: &str, options: &PngOptions) -> Result<(u32, u32, Vec<u8>)> {
+    if !(options.scale > 0.0 && options.dpi > 0.0) {
+        bail!("The scale and resolution must be positive");
+    }
+    let tree = render_tree(svg)?;
+
+    let factor = options.scale * options.dpi / CSS_DPI;
+    let width = (tree.size.width() * factor).ceil();
+    let height = (tree.size.height() * factor).ceil();
+    if width > MAX_BITMAP_SIDE as f32 || height > MAX_BITMAP_SIDE as f32 {
+        bail!("The page would be {width}×{height} pixels, more than {MAX_BITMAP_SIDE} on a side");
+    }
+    let mut pixmap = Pixmap::new(width as u32, height as u32)
+        .ok_or_else(|| anyhow!("The page has no area to export"))?;
+    if !options.transparent {
+        pixmap.fill(parse_color(&options.background)?);
+    }
+    resvg::render(
+        &tree,
+        Transform::from_scale(factor, factor),
+        &mut pixmap.as_mut(),
+    );
+    let mut rgba = Vec::with_capacity(pixmap.data().len());
+    for pixel in pixmap.pixels() {
+        let color = pixel.demultiply();
+        rgba.extend_from_slice(&[color.red(), color.green(), color.blue(), color.alpha()]);
+    }
+    Ok((pixmap.width(), pixmap.height(), rgba))
+}
+
+/// Returns the page at `index` in `document` as a standalone SVG file.
+pub fn export_svg(document: &Document, index: usize) -> Result<Vec<u8>> {
+    if index >= document.page_count() {
+        bail!("No page {} to export", index + 1);
+    }
+    Ok(document.page_svg(index).into_bytes())
+}
+
+/// Returns `svg` parsed for rendering, with its text turned into paths in
+/// the fonts available.
+pub(crate) fn render_tree(svg: &str) -> Result<usvg::Tree> {
+    let mut tree =
+        usvg::Tree::from_str(svg, &usvg::Options::default()).context("Failed to parse the page")?;
+    tree.postprocess(
+        usvg::PostProcessingSteps {
+            convert_text_into_paths: true,
+        },
+        system_fonts(),
+    );
+    Ok(tree)
+}
+
+/// Returns the fonts installed on the system, loaded on first use since
+/// pages are also parsed for hit testing, on every click.
+pub(crate) fn system_fonts() -> &'static fontdb::Database {
+    static FONTS: OnceLock<fontdb::Database> = OnceLock::new();
+    FONTS.get_or_init(|| {
+        let mut fonts = fontdb::Database::new();
+        fonts.load_system_fonts();
+        fonts
+    })
+}
+
+/// Returns the color written as "#rrggbb" or "#rgb".
+fn parse_color(text: &str) -> Result<Color> {
+    let invalid = || anyhow!("Invalid color '{text}', expected one like #ffffff");
+    let hex = text.strip_prefix('#').ok_or_else(invalid)?;
+    let digits: Vec<u8> = hex
+        .chars()
+        .map(|c| c.to_digit(16).map(|d| d as u8))
+        .collect::<Option<_>>()
+        .ok_or_else(invalid)?;
+    let [r, g, b] = match digits[..] {
+        [r, g, b] => [r * 17, g * 17, b * 17],
+        [r1, r2, g1, g2, b1, b2] => [r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2],
+        _ => return Err(invalid()),
+    };
+    Ok(Color::from_rgba8(r, g, b, 255))
+}
+
+/// Returns the `width` × `height` image whose pixels `data` holds, as
+/// unpremultiplied RGBA rows, encoded as a PNG recording its resolution as
+/// `dpi`.
+pub(crate) fn encode_rgba_png(width: u32, height: u32, data: &[u8], dpi: f32) -> Result<Vec<u8>> {
+    const METERS_PER_INCH: f32 = 0.0254;
+
+    let mut png = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let pixels_per_meter = (dpi / METERS_PER_INCH).round() as u32;
+        encoder.set_pixel_dims(Some(png::PixelDimensions {
+            xppu: pixels_per_meter,
+            yppu: pixels_per_meter,
+            unit: png::Unit::Meter,
+        }));
+        let mut writer = encoder.write_header().context("Failed to encode PNG")?;
+        writer
+            .write_image_data(data)
+            .context("Failed to encode PNG")?;
+    }
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_are_parsed_from_hex() {
+        assert_eq!(
+            parse_color("#ff8000").unwrap(),
+            Color::from_rgba8(255, 128, 0, 255)
+        );
+        assert_eq!(
+            parse_color("#F80").unwrap(),
+            Color::from_rgba8(255, 136, 0, 255)
+        );
+        for invalid in ["ff8000", "#ff80", "#gg8000", "#", "#ff8000ff", "#ﬀ8"] {
+            assert!(parse_color(invalid).is_err(), "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::application_state::ApplicationState;
+use crate::export::{export, ExportFormat};
+use crate::pdf_export::{Orientation, PageSize};
+use dioxus::prelude::*;
+
+/// A dialog choosing how to export the displayed page, or the document in
+/// formats holding every page, while the user is exporting.
+///
+/// `on_export` saves the exported content in the platform's way, given the
+/// content and the file name to suggest.
+#[component]
+pub fn ExportDialog(
+    application_state: Signal<ApplicationState>,
+    on_export: EventHandler<(Vec<u8>, String)>,
+) -> Element {
+    let mut state = application_state;
+    if !state.read().show_export {
+        return rsx! {};
+    }
+    let options = state.read().export_options.clone();
+    let title = if options.format.holds_every_page() {
+        "Export Document"
+    } else {
+        "Export Page"
+    };
+
+    let cancel = move |_| state.write().show_export = false;
+    let confirm = move |_| {
+        let exported = {
+            let current_state = state.read();
+            let open = current_state.current();
+            let options = &current_state.export_options;
+            export(&open.document, open.current_page, options)
+                .map(|content| (content, options.format.file_name(open)))
+        };
+        match exported {
+            Ok(exported) => {
+                state.write().show_export = false;
+                on_export.call(exported);
+            }
+            Err(e) => state.write().notifications.error("Export failed", &e),
+        }
+    };
+
+    rsx! {
+        div {
+            class: "dialog-overlay",
+            div {
+                class: "dialog export-dialog",
+                h3 { "{title}" }
+                label { r#for: "export-format", "Format" }
+                select {
+                    id: "export-format",
+                    onchange: move |e: FormEvent| {
+                        if let Some(format) = ExportFormat::from_extension(&e.value()) {
+                            state.write().export_options.format = format;
+                        }
+                    },
+                    for format in ExportFormat::ALL {
+                        option {
+                            value: format.extension(),
+                            selected: format == options.format,
+                            "{format.description()}"
+                        }
+                    }
+                }
+                match options.format {
+                    ExportFormat::Png => rsx! {
+                        label { r#for: "export-scale", "Scale" }
+                        input {
+                            id: "export-scale",
+                            r#type: "number",
+                            min: "0.1",
+                            step: "0.5",
+                            value: "{options.png.scale}",
+                            onchange: move |e: FormEvent| {
+                                if let Ok(scale) = e.value().parse() {
+                                    state.write().export_options.png.scale = scale;
+                                }
+                            },
+                        }
+                        label { r#for: "export-dpi", "Resolution (DPI)" }
+                        input {
+                            id: "export-dpi",
+                            r#type: "number",
+                            min: "1",
+                            step: "1",
+                            value: "{options.png.dpi}",
+                            onchange: move |e: FormEvent| {
+                                if let Ok(dpi) = e.value().parse() {
+                                    state.write().export_options.png.dpi = dpi;
+                                }
+                            },
+                        }
+                        label { r#for: "export-background", "Background" }
+                        div {
+                            class: "export-background",
+                            input {
+                                id: "export-background",
+                                r#type: "color",
+                                disabled: options.png.transparent,
+                                value: "{options.png.background}",
+                                oninput: move |e: FormEvent| {
+                                    state.write().export_options.png.background = e.value();
+                                },
+                            }
+                            label {
+                                input {
+                                    r#type: "checkbox",
+                                    checked: options.png.transparent,
+                                    onchange: move |e: FormEvent| {
+                                        state.write().export_options.png.transparent = e.checked();
+                                    },
+                                }
+                                " Transparent"
+                            }
+                        }
+                    },
+                    ExportFormat::Pdf => rsx! {
+                        label { r#for: "export-page-size", "Page size" }
+                        select {
+                            id: "export-page-size",
+                            onchange: move |e: FormEvent| {
+                                let value = e.value();
+                                if let Some(size) = PageSize::ALL.into_iter().find(|s| s.description() == value) {
+                                    state.write().export_options.pdf.page_size = size;
+                                }
+                            },
+                            for size in PageSize::ALL {
+                                option {
+                                    value: size.description(),
+                                    selected: size == options.pdf.page_size,
+                                    "{size.description()}"
+                                }
+                            }
+                        }
+                        label { r#for: "export-orientation", "Orientation" }
+                        select {
+                            id: "export-orientation",
+                            disabled: options.pdf.page_size == PageSize::Fit,
+                            onchange: move |e: FormEvent| {
+                                state.write().export_options.pdf.orientation = if e.value() == "landscape" {
+                                    Orientation::Landscape
+                                } else {
+                                    Orientation::Portrait
+                                };
+                            },
+                            option {
+                                value: "portrait",
+                                selected: options.pdf.orientation == Orientation::Portrait,
+                                "Portrait"
+                            }
+                            option {
+                                value: "landscape",
+                                selected: options.pdf.orientation == Orientation::Landscape,
+                                "Landscape"
+                            }
+                        }
+                        label { r#for: "export-margin", "Margins (mm)" }
+                        input {
+                            id: "export-margin",
+                            r#type: "number",
+                            min: "0",
+                            step: "1",
+                            value: "{options.pdf.margin}",
+                            onchange: move |e: FormEvent| {
+                                if let Ok(margin) = e.value().parse() {
+                                    state.write().export_options.pdf.margin = margin;
+                                }
+                            },
+                        }
+                    },
+                    ExportFormat::Svg => rsx! {
+                        p { class: "export-note", "The visible layers of the page, with its images embedded." }
+                    },
+                }
+                div {
+                    class: "dialog-buttons",
+                    button { class: "dialog-button", onclick: cancel, "Cancel" }
+                    button {
+                        class: "dialog-button dialog-button-primary",
+                        onclick: confirm,
+                        "Export"
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! Opening and inserting the files dropped onto the document area.
+
+use crate::application_state::ApplicationState;
+use crate::asset::image_mime_type;
+use crate::file_format::{is_document_file_name, is_svg_file_name};
+use crate::notifications::report_failure;
+use anyhow::{anyhow, bail, Context, Result};
+use dioxus::html::FileEngine;
+use dioxus::prelude::*;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Opens the documents among `files`, dropped onto the document area, and
+/// adds the SVG files and images among them to the displayed page, telling
+/// the user about those that can't be.
+pub async fn drop_files(mut state: Signal<ApplicationState>, files: Arc<dyn FileEngine>) {
+    for name in files.files() {
+        let path = Path::new(&name);
+        // The desktop app is given the paths of the files, so documents are
+        // loaded from there, to be saved in place and listed as recent; the
+        // browser gives only their names.
+        let result = if is_document_file_name(&name) && path.is_absolute() {
+            state.write().load_document(path)
+        } else {
+            match files.read_file(&name).await {
+                Some(content) => drop_content(&mut state.write(), &name, content),
+                None => Err(anyhow!("The file couldn't be read")),
+            }
+        };
+        let file_name = path
+            .file_name()
+            .map_or(name.as_str(), |n| n.to_str().unwrap_or_default());
+        report_failure(state, &format!("Couldn't use \"{file_name}\""), result);
+    }
+}
+
+/// Opens or inserts `content`, that of the dropped file named `name`.
+fn drop_content(state: &mut ApplicationState, name: &str, content: Vec<u8>) -> Result<()> {
+    if is_document_file_name(name) {
+        state.open_file_content(name, &content)
+    } else if is_svg_file_name(name) {
+        let svg = String::from_utf8(content).context("The file isn't UTF-8 text")?;
+        state.current_mut().insert_svg(&svg)
+    } else if image_mime_type(&content).is_some() {
+        state.current_mut().insert_image(content)
+    } else {
+        bail!("Only documents, SVG files and PNG, JPEG or GIF images can be dropped")
+    }
+}
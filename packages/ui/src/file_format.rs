@@ -0,0 +1,106 @@
+//! The file formats a document can be stored in, and the plain SVG files
+//! that can be opened as new documents.
+
+use crate::binary::BINARY_MAGIC;
+use std::path::Path;
+
+/// The file name extension of plain SVG files, without the dot.
+pub const SVG_EXTENSION: &str = "svg";
+
+/// A file format for documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileFormat {
+    /// A single pretty-printed JSON file, with assets embedded as base64.
+    #[default]
+    Json,
+
+    /// A zip archive holding a manifest, the document as JSON, each asset as
+    /// a file of its own, and a thumbnail.
+    Bundle,
+
+    /// A compact binary (CBOR) encoding, faster to read and write than JSON.
+    Binary,
+}
+
+impl FileFormat {
+    /// Every format, in the order they are offered to the user.
+    pub const ALL: [FileFormat; 3] = [FileFormat::Json, FileFormat::Bundle, FileFormat::Binary];
+
+    /// Returns the file name extension of `self`, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            FileFormat::Json => "json",
+            FileFormat::Bundle => "pcl",
+            FileFormat::Binary => "pclb",
+        }
+    }
+
+    /// Returns a description of `self` for file dialogs.
+    pub fn description(self) -> &'static str {
+        match self {
+            FileFormat::Json => "JSON Documents",
+            FileFormat::Bundle => "PCL Bundles",
+            FileFormat::Binary => "Binary Documents",
+        }
+    }
+
+    /// Returns the format `path` is named for, if any.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::from_extension(path.extension()?.to_str()?)
+    }
+
+    /// Returns the format whose file name extension is `extension`, if any.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extension().eq_ignore_ascii_case(extension))
+    }
+
+    /// Returns the format of the file whose content starts with `header`.
+    pub fn detect(header: &[u8]) -> Self {
+        if header.starts_with(b"PK\x03\x04") {
+            FileFormat::Bundle
+        } else if header.starts_with(BINARY_MAGIC) {
+            FileFormat::Binary
+        } else {
+            FileFormat::Json
+        }
+    }
+
+    /// Returns `name` with the extension of `self`, replacing the extension
+    /// of any other format.
+    pub fn file_name(self, name: &str) -> String {
+        let path = Path::new(name);
+        match Self::from_path(path) {
+            Some(_) => path.with_extension(self.extension()).display().to_string(),
+            None => format!("{name}.{}", self.extension()),
+        }
+    }
+}
+
+/// Returns `true` iff `name` is named as a document in any format.
+pub fn is_document_file_name(name: &str) -> bool {
+    FileFormat::from_path(Path::new(name)).is_some()
+}
+
+/// Returns `true` iff `name` is named as a plain SVG file.
+pub fn is_svg_file_name(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case(SVG_EXTENSION))
+}
+
+/// Returns `true` iff `name` is named as a file that can be opened: a
+/// document in any format, or a plain SVG file.
+pub fn is_openable_file_name(name: &str) -> bool {
+    is_document_file_name(name) || is_svg_file_name(name)
+}
+
+/// Returns the file name extensions of the files that can be opened, without
+/// the dots: those of every format, then that of plain SVG files.
+pub fn openable_extensions() -> Vec<&'static str> {
+    let mut extensions = FileFormat::ALL.map(FileFormat::extension).to_vec();
+    extensions.push(SVG_EXTENSION);
+    extensions
+}
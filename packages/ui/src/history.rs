@@ -0,0 +1,373 @@
+//! Undo and redo of changes to a document.
+//!
+//! Every change to a document is made by applying a [`Command`], which can
+//! be reverted. The [`History`] records the commands applied as undoable
+//! steps; the commands of a continuous gesture, such as typing into a field
+//! or dragging a slider, form a single step.
+
+use crate::document::Document;
+use crate::metadata::Metadata;
+use crate::page::Page;
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+
+/// The number of steps that can be undone unless configured otherwise.
+pub const DEFAULT_UNDO_DEPTH: usize = 100;
+
+/// A reversible change to a document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Inserts `page` so that it is at `index`.
+    InsertPage { index: usize, page: Page },
+
+    /// Removes `page`, the page at `index`.
+    RemovePage { index: usize, page: Page },
+
+    /// Moves the page at `from` so that it is at `to`.
+    MovePage { from: usize, to: usize },
+
+    /// Replaces the page at `index`, `before`, with `after`.
+    EditPage {
+        index: usize,
+        before: Page,
+        after: Page,
+    },
+
+    /// Replaces the metadata, `before`, with `after`.
+    EditMetadata { before: Metadata, after: Metadata },
+}
+
+impl Command {
+    /// Applies `self` to `document`.
+    pub fn apply(&self, document: &mut Document) -> Result<()> {
+        match self {
+            Command::InsertPage { index, page } => document.insert_page(*index, page.clone()),
+            Command::RemovePage { index, .. } => document.remove_page(*index).map(drop),
+            Command::MovePage { from, to } => document.move_page(*from, *to),
+            Command::EditPage { index, after, .. } => {
+                *document.page_mut(*index).context("No such page")? = after.clone();
+                Ok(())
+            }
+            Command::EditMetadata { after, .. } => {
+                // When the document was saved is a fact about its file rather
+                // than an edit, so undoing and redoing leave it as it is.
+                let metadata = document.metadata_mut();
+                *metadata = Metadata {
+                    created: metadata.created,
+                    modified: metadata.modified,
+                    ..after.clone()
+                };
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the command that reverts `self`.
+    pub fn inverse(&self) -> Command {
+        match self.clone() {
+            Command::InsertPage { index, page } => Command::RemovePage { index, page },
+            Command::RemovePage { index, page } => Command::InsertPage { index, page },
+            Command::MovePage { from, to } => Command::MovePage { from: to, to: from },
+            Command::EditPage {
+                index,
+                before,
+                after,
+            } => Command::EditPage {
+                index,
+                before: after,
+                after: before,
+            },
+            Command::EditMetadata { before, after } => Command::EditMetadata {
+                before: after,
+                after: before,
+            },
+        }
+    }
+
+    /// Returns the index of the page to display once `self` is applied, if
+    /// it changes a page.
+    pub fn page(&self) -> Option<usize> {
+        match self {
+            Command::InsertPage { index, .. }
+            | Command::RemovePage { index, .. }
+            | Command::EditPage { index, .. } => Some(*index),
+            Command::MovePage { to, .. } => Some(*to),
+            Command::EditMetadata { .. } => None,
+        }
+    }
+
+    /// Combines `next`, applied right after `self`, into `self` if both
+    /// replace the same thing; returns `true` iff it did.
+    fn absorb(&mut self, next: &Command) -> bool {
+        match (self, next) {
+            (
+                Command::EditPage { index, after, .. },
+                Command::EditPage {
+                    index: next_index,
+                    after: next_after,
+                    ..
+                },
+            ) if index == next_index => {
+                *after = next_after.clone();
+                true
+            }
+            (
+                Command::EditMetadata { after, .. },
+                Command::EditMetadata {
+                    after: next_after, ..
+                },
+            ) => {
+                *after = next_after.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// An undoable step: the commands of one action or gesture, in the order
+/// they were applied.
+#[derive(Debug, Clone)]
+struct Step {
+    /// Identifies the step among all those recorded in a history.
+    id: u64,
+
+    commands: Vec<Command>,
+}
+
+/// The commands applied to a document, as steps that can be undone and
+/// redone.
+#[derive(Debug, Clone)]
+pub struct History {
+    /// The number of steps kept for undoing.
+    depth: usize,
+
+    /// The steps that can be undone, oldest first.
+    undo: VecDeque<Step>,
+
+    /// The steps that can be redone, most recently undone last.
+    redo: Vec<Step>,
+
+    /// The id of the last step recorded.
+    last_id: u64,
+
+    /// The id of the step whose state the oldest step to undo was applied
+    /// to (0 for the initial state).
+    base_id: u64,
+
+    /// Whether a gesture is in progress.
+    in_gesture: bool,
+
+    /// Whether the last undo step belongs to the gesture in progress.
+    gesture_recorded: bool,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_UNDO_DEPTH)
+    }
+}
+
+impl History {
+    /// Returns an empty history keeping at most `depth` steps for undoing.
+    pub fn new(depth: usize) -> Self {
+        Self {
+            depth,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            last_id: 0,
+            base_id: 0,
+            in_gesture: false,
+            gesture_recorded: false,
+        }
+    }
+
+    /// Keeps at most `depth` steps for undoing from now on, forgetting the
+    /// oldest steps beyond that.
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        self.trim();
+    }
+
+    /// Records `command`, just applied.
+    pub fn record(&mut self, command: Command) {
+        self.redo.clear();
+        match self.undo.back_mut() {
+            Some(step) if self.gesture_recorded => {
+                if !step
+                    .commands
+                    .last_mut()
+                    .is_some_and(|last| last.absorb(&command))
+                {
+                    step.commands.push(command);
+                }
+            }
+            _ => {
+                self.last_id += 1;
+                self.undo.push_back(Step {
+                    id: self.last_id,
+                    commands: vec![command],
+                });
+                self.gesture_recorded = self.in_gesture;
+            }
+        }
+        self.trim();
+    }
+
+    /// Starts a gesture: the commands recorded until it ends are undone and
+    /// redone together.
+    pub fn begin_gesture(&mut self) {
+        self.in_gesture = true;
+        self.gesture_recorded = false;
+    }
+
+    /// Ends the gesture in progress, if any.
+    pub fn end_gesture(&mut self) {
+        self.in_gesture = false;
+        self.gesture_recorded = false;
+    }
+
+    /// Returns a value identifying the state of the document reached by the
+    /// steps recorded so far, equal for equal states.
+    ///
+    /// The state is only identified while no further change is recorded
+    /// into the same step, so a gesture should end when the state is noted.
+    pub fn position(&self) -> u64 {
+        self.undo.back().map_or(self.base_id, |step| step.id)
+    }
+
+    /// Returns `true` iff there is a step to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Returns `true` iff there is a step to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Removes the step to undo, moving it to the redo steps, and returns
+    /// the commands reverting it in the order to apply them.
+    pub fn take_undo(&mut self) -> Option<Vec<Command>> {
+        self.end_gesture();
+        let step = self.undo.pop_back()?;
+        let inverse = step.commands.iter().rev().map(Command::inverse).collect();
+        self.redo.push(step);
+        Some(inverse)
+    }
+
+    /// Removes the step to redo, moving it back to the undo steps, and
+    /// returns its commands in the order to apply them.
+    pub fn take_redo(&mut self) -> Option<Vec<Command>> {
+        self.end_gesture();
+        let step = self.redo.pop()?;
+        let commands = step.commands.clone();
+        self.undo.push_back(step);
+        Some(commands)
+    }
+
+    /// Forgets every step.
+    pub fn clear(&mut self) {
+        *self = Self {
+            last_id: self.last_id,
+            base_id: self.last_id,
+            ..Self::new(self.depth)
+        };
+    }
+
+    /// Forgets the oldest steps beyond the depth.
+    fn trim(&mut self) {
+        while self.undo.len() > self.depth {
+            if let Some(step) = self.undo.pop_front() {
+                self.base_id = step.id;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a command retitling the document from `before` to `after`.
+    fn retitle(before: &str, after: &str) -> Command {
+        let metadata = |title: &str| Metadata {
+            title: title.into(),
+            ..Metadata::default()
+        };
+        Command::EditMetadata {
+            before: metadata(before),
+            after: metadata(after),
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_move_steps_between_stacks() {
+        let mut history = History::new(10);
+        assert!(!history.can_undo());
+        history.record(retitle("", "a"));
+        history.record(retitle("a", "b"));
+
+        assert_eq!(history.take_undo(), Some(vec![retitle("b", "a")]));
+        assert!(history.can_redo());
+        assert_eq!(history.take_redo(), Some(vec![retitle("a", "b")]));
+        assert!(!history.can_redo());
+
+        history.take_undo();
+        history.record(retitle("a", "c"));
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn gestures_are_undone_as_one_step() {
+        let mut history = History::new(10);
+        history.begin_gesture();
+        history.record(retitle("", "a"));
+        history.record(retitle("a", "ab"));
+        history.end_gesture();
+        history.record(retitle("ab", "abc"));
+
+        assert_eq!(history.take_undo(), Some(vec![retitle("abc", "ab")]));
+        assert_eq!(history.take_undo(), Some(vec![retitle("ab", "")]));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn positions_identify_states() {
+        let mut history = History::new(10);
+        let initial = history.position();
+        history.record(retitle("", "a"));
+        let edited = history.position();
+        assert_ne!(edited, initial);
+
+        history.take_undo();
+        assert_eq!(history.position(), initial);
+        history.take_redo();
+        assert_eq!(history.position(), edited);
+
+        history.take_undo();
+        history.record(retitle("", "b"));
+        assert_ne!(history.position(), edited);
+    }
+
+    #[test]
+    fn depth_limits_the_steps_kept() {
+        let mut history = History::new(2);
+        history.record(retitle("", "a"));
+        history.record(retitle("a", "b"));
+        history.record(retitle("b", "c"));
+        let position = history.position();
+
+        assert!(history.take_undo().is_some());
+        assert!(history.take_undo().is_some());
+        assert!(history.take_undo().is_none());
+        assert_ne!(history.position(), 0);
+
+        history.take_redo();
+        history.take_redo();
+        assert_eq!(history.position(), position);
+        history.set_depth(0);
+        assert!(!history.can_undo());
+        assert_eq!(history.position(), position);
+    }
+}
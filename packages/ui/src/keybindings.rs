@@ -0,0 +1,260 @@
+//! The keyboard shortcuts that carry out actions, remappable by the user.
+//!
+//! Each action is bound to its default shortcut unless the keybindings
+//! setting says otherwise. The setting is a JSON object mapping action ids
+//! to shortcuts, such as `{"redo": "Primary+Y", "quit": null}`, where
+//! "Primary" is Cmd on macOS and Ctrl elsewhere and `null` unbinds the
+//! action. In browsers, which keep some shortcuts for themselves, those
+//! actions default to others. The desktop app's native menus handle their
+//! shortcuts themselves; elsewhere [`listen_for_shortcuts`] watches the
+//! keyboard.
+
+use crate::actions::{action, perform, ActionHost, MenuName, Shortcut, ACTIONS};
+use crate::application_state::ApplicationState;
+use crate::notifications::Notifications;
+use crate::platform::{has_native_menus, in_browser, load_setting};
+use anyhow::{anyhow, bail, Context, Result};
+use dioxus::prelude::*;
+use std::collections::BTreeMap;
+
+/// The name of the setting remapping shortcuts.
+pub const SETTING_NAME: &str = "keybindings.json";
+
+/// The shortcuts browsers keep for themselves, opening a window and closing
+/// the tab, each with the one actions bound to it default to there instead.
+const BROWSER_SHORTCUTS: [(Shortcut, Shortcut); 2] = [
+    (Shortcut::primary("N", false), Shortcut::alt("N")),
+    (Shortcut::primary("W", false), Shortcut::alt("W")),
+];
+
+/// The shortcut bound to each action.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keybindings {
+    /// The bound actions' ids and their shortcuts.
+    bindings: Vec<(&'static str, Shortcut)>,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Self::defaults(in_browser())
+    }
+}
+
+impl Keybindings {
+    /// Returns the actions' default shortcuts, avoiding those browsers keep
+    /// iff `in_browser`.
+    fn defaults(in_browser: bool) -> Self {
+        let default = |shortcut: &Shortcut| {
+            BROWSER_SHORTCUTS
+                .iter()
+                .find(|(kept, _)| in_browser && kept == shortcut)
+                .map_or_else(|| shortcut.clone(), |(_, instead)| instead.clone())
+        };
+        Self {
+            bindings: ACTIONS
+                .iter()
+                .filter_map(|action| Some((action.id, default(action.shortcut.as_ref()?))))
+                .collect(),
+        }
+    }
+
+    /// Returns the default shortcuts, as remapped by the keybindings setting
+    /// (if it's valid); a setting that isn't is told in `notifications`.
+    pub fn load(notifications: &mut Notifications) -> Self {
+        let mut keybindings = Self::default();
+        if let Err(e) = keybindings.remap_from_setting() {
+            notifications.warning("Couldn't load the keybindings", &e);
+        }
+        keybindings
+    }
+
+    /// Returns the shortcut bound to the action `id`, if any.
+    pub fn shortcut(&self, id: &str) -> Option<&Shortcut> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == id)
+            .map(|(_, shortcut)| shortcut)
+    }
+
+    /// Returns the id of the action `shortcut` is bound to, if any.
+    pub fn action(&self, shortcut: &Shortcut) -> Option<&'static str> {
+        self.bindings
+            .iter()
+            .find(|(_, bound)| bound == shortcut)
+            .map(|(id, _)| *id)
+    }
+
+    /// Binds the action `id` to `shortcut`, or to nothing if `None`; any
+    /// other action bound to `shortcut` is unbound.
+    pub fn bind(&mut self, id: &'static str, shortcut: Option<Shortcut>) {
+        self.bindings.retain(|(bound, bound_shortcut)| {
+            *bound != id && Some(bound_shortcut) != shortcut.as_ref()
+        });
+        if let Some(shortcut) = shortcut {
+            self.bindings.push((id, shortcut));
+        }
+    }
+
+    /// Applies the remapping in the keybindings setting, if there is one.
+    fn remap_from_setting(&mut self) -> Result<()> {
+        match load_setting(SETTING_NAME)? {
+            Some(content) => self.remap(&content),
+            None => Ok(()),
+        }
+    }
+
+    /// Applies the remapping `content`, a JSON object mapping action ids to
+    /// shortcuts; nothing is remapped unless all of it is valid.
+    fn remap(&mut self, content: &str) -> Result<()> {
+        let remapped: BTreeMap<String, Option<String>> =
+            serde_json::from_str(content).context("Invalid keybindings")?;
+        let mut bindings: Vec<(&'static str, Option<Shortcut>)> = Vec::new();
+        for (id, notation) in remapped {
+            let id = action(&id)
+                .ok_or_else(|| anyhow!("No action '{id}' to bind"))?
+                .id;
+            let shortcut: Option<Shortcut> = notation.map(|n| n.parse()).transpose()?;
+            if let Some((other, _)) = bindings
+                .iter()
+                .find(|(_, bound)| shortcut.is_some() && *bound == shortcut)
+            {
+                let notation = shortcut
+                    .as_ref()
+                    .map(Shortcut::notation)
+                    .unwrap_or_default();
+                bail!("Both '{other}' and '{id}' are bound to {notation}");
+            }
+            bindings.push((id, shortcut));
+        }
+        for (id, shortcut) in bindings {
+            self.bind(id, shortcut);
+        }
+        Ok(())
+    }
+
+    /// Returns the script reporting the shortcuts bound in `self` as they're
+    /// pressed, by their notation.
+    fn listener_script(&self) -> String {
+        // Editing shortcuts, such as undo, are left to text fields.
+        let editing: BTreeMap<String, bool> = self
+            .bindings
+            .iter()
+            .map(|(id, shortcut)| {
+                let editing = action(id).is_some_and(|action| action.menu == MenuName::Edit);
+                (shortcut.notation(), editing)
+            })
+            .collect();
+        let editing = serde_json::to_string(&editing).unwrap_or_else(|_| "{}".to_string());
+        format!(
+            r#"
+            const editing = {editing};
+            const command = /^(Mac|iP)/.test(navigator.platform);
+            document.addEventListener("keydown", (event) => {{
+                // The key as typed, unless a modifier such as Option on macOS
+                // turned it into a character that isn't on the key.
+                const code = /^(?:Key|Digit)(.)$/.exec(event.code);
+                const key = /^[!-~]$/.test(event.key) ? event.key.toUpperCase()
+                    : code ? code[1] : event.key;
+                const parts = [];
+                if (command ? event.metaKey : event.ctrlKey) parts.push("Primary");
+                if (event.shiftKey) parts.push("Shift");
+                if (event.altKey) parts.push("Alt");
+                parts.push(key);
+                const notation = parts.join("+");
+                if (!(notation in editing)) return;
+                const target = event.target;
+                const inField = target && (target.isContentEditable
+                    || ["INPUT", "TEXTAREA", "SELECT"].includes(target.tagName));
+                if (editing[notation] && inField) return;
+                event.preventDefault();
+                dioxus.send(notation);
+            }});
+            await new Promise(() => {{}});
+            "#
+        )
+    }
+}
+
+/// Records the actions whose shortcuts are pressed in `state`, for the
+/// platform's menu to carry out, unless native menus handle shortcuts.
+pub async fn listen_for_shortcuts(mut state: Signal<ApplicationState>) {
+    if has_native_menus() {
+        return;
+    }
+    let script = state.peek().keybindings.listener_script();
+    let mut listener = document::eval(&script);
+    while let Ok(notation) = listener.recv::<String>().await {
+        let Ok(shortcut) = notation.parse::<Shortcut>() else {
+            continue;
+        };
+        let pressed = state.peek().keybindings.action(&shortcut);
+        if pressed.is_some() {
+            state.write().pressed_action = pressed;
+        }
+    }
+}
+
+/// Carries out through `host` the actions whose shortcuts are pressed.
+pub fn use_pressed_actions(host: impl ActionHost + Copy + 'static) {
+    use_effect(move || {
+        let pressed = host.state().read().pressed_action;
+        if let Some(id) = pressed {
+            host.state().write().pressed_action = None;
+            perform(&host, id);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn browsers_keep_their_own_shortcuts() {
+        let desktop = Keybindings::defaults(false);
+        let browser = Keybindings::defaults(true);
+        for (kept, instead) in &BROWSER_SHORTCUTS {
+            let id = desktop.action(kept).unwrap();
+            assert_eq!(browser.action(kept), None);
+            assert_eq!(browser.shortcut(id), Some(instead));
+        }
+        assert_eq!(
+            browser.shortcut("save"),
+            Some(&Shortcut::primary("S", false))
+        );
+    }
+
+    #[test]
+    fn remapping_binds_and_unbinds_actions() {
+        let mut keybindings = Keybindings::defaults(false);
+        keybindings
+            .remap(r#"{"redo": "Primary+Y", "quit": null, "save": "Ctrl+N"}"#)
+            .unwrap();
+        assert_eq!(
+            keybindings.shortcut("redo"),
+            Some(&Shortcut::primary("Y", false))
+        );
+        assert_eq!(keybindings.shortcut("quit"), None);
+        assert_eq!(
+            keybindings.action(&Shortcut::primary("N", false)),
+            Some("save")
+        );
+        // The shortcut taken from "new" leaves it unbound.
+        assert_eq!(keybindings.shortcut("new"), None);
+    }
+
+    #[test]
+    fn invalid_remapping_changes_nothing() {
+        let defaults = Keybindings::defaults(false);
+        for invalid in [
+            r#"{"redo": "Primary+Y", "nothing": "Primary+J"}"#,
+            r#"{"redo": "Primary+Y", "undo": "Hyper+Z"}"#,
+            r#"{"redo": "Primary+Y", "undo": "Primary+Y"}"#,
+            r#"["redo"]"#,
+        ] {
+            let mut keybindings = defaults.clone();
+            assert!(keybindings.remap(invalid).is_err(), "{invalid}");
+            assert_eq!(keybindings, defaults, "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! The layers of a page.
+
+use crate::shape::{Group, Shape, Style};
+use serde::{Deserialize, Serialize};
+
+/// The attribute naming the layer a top-level `<g>` represents in SVG markup.
+const LAYER_ATTRIBUTE: &str = "data-layer";
+
+/// The attribute marking the layer a `<g>` represents as locked.
+const LOCKED_ATTRIBUTE: &str = "data-locked";
+
+/// A named stack of shapes within a page, shown and edited as a unit.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Layer {
+    /// The layer's name, as shown to the user.
+    pub name: String,
+
+    /// Whether the layer is rendered.
+    #[serde(default = "default_visible")]
+    pub visible: bool,
+
+    /// Whether the layer's content is protected from editing.
+    #[serde(default)]
+    pub locked: bool,
+
+    /// The opacity the layer is rendered with, from 0 (transparent) to 1 (opaque).
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+
+    /// The layer's shapes, back to front.
+    #[serde(default)]
+    pub shapes: Vec<Shape>,
+}
+
+impl Layer {
+    /// Returns an empty, visible, unlocked and opaque layer called `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            visible: true,
+            locked: false,
+            opacity: 1.0,
+            shapes: Vec::new(),
+        }
+    }
+
+    /// Returns the layer represented by `shape`, if it is a group marked as a
+    /// layer by [`Layer::to_group`].
+    pub(crate) fn from_group(shape: &Shape) -> Option<Self> {
+        let Shape::Group(group) = shape else {
+            return None;
+        };
+        let mut style = group.style.clone();
+        let name = take_attribute(&mut style, LAYER_ATTRIBUTE)?;
+
+        let mut layer = Self::new(name);
+        layer.locked = take_attribute(&mut style, LOCKED_ATTRIBUTE).is_some_and(|v| v == "true");
+        layer.visible = take_attribute(&mut style, "display").is_none_or(|v| v.trim() != "none");
+        layer.opacity = style.opacity.take().unwrap_or(1.0).clamp(0.0, 1.0);
+        // The id is derived from the name whenever the layer is written.
+        take_attribute(&mut style, "id");
+        // The layer's attributes are written in its own order.
+        style.attribute_order.clear();
+
+        // Keep any other styling of the group by grouping the content with it.
+        layer.shapes = if style.is_empty() {
+            group.children.clone()
+        } else {
+            vec![Shape::Group(Group {
+                children: group.children.clone(),
+                style,
+            })]
+        };
+        Some(layer)
+    }
+
+    /// Returns the `<g>` representing `self` in SVG markup, identified by `id`.
+    pub(crate) fn to_group(&self, id: &str) -> Shape {
+        let mut style = Style::default();
+        if self.opacity < 1.0 {
+            style.opacity = Some(self.opacity.max(0.0));
+        }
+        style.attributes.push(("id".to_string(), id.to_string()));
+        style
+            .attributes
+            .push((LAYER_ATTRIBUTE.to_string(), self.name.clone()));
+        if !self.visible {
+            style
+                .attributes
+                .push(("display".to_string(), "none".to_string()));
+        }
+        if self.locked {
+            style
+                .attributes
+                .push((LOCKED_ATTRIBUTE.to_string(), "true".to_string()));
+        }
+        Shape::Group(Group {
+            children: self.shapes.clone(),
+            style,
+        })
+    }
+
+    /// Returns the `<g>` representing `self` in a standalone SVG file, for
+    /// other applications, identified by `id`: without the attributes only
+    /// this application reads.
+    pub(crate) fn to_plain_group(&self, id: &str) -> Shape {
+        let mut group = self.to_group(id);
+        if let Shape::Group(Group { style, .. }) = &mut group {
+            style
+                .attributes
+                .retain(|(name, _)| name != LAYER_ATTRIBUTE && name != LOCKED_ATTRIBUTE);
+        }
+        group
+    }
+}
+
+/// Returns a readable element id for the layer called `name`, e.g.
+/// `layer-background` for "Background".
+pub(crate) fn layer_id(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    match slug.trim_end_matches('-') {
+        "" => "layer".to_string(),
+        // Don't repeat the prefix of default names like "Layer 1".
+        slug if slug == "layer" || slug.starts_with("layer-") => slug.to_string(),
+        slug => format!("layer-{slug}"),
+    }
+}
+
+/// Removes the attribute `name` from `style`, returning its value.
+fn take_attribute(style: &mut Style, name: &str) -> Option<String> {
+    let index = style.attributes.iter().position(|(n, _)| n == name)?;
+    Some(style.attributes.remove(index).1)
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+fn default_opacity() -> f64 {
+    1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_are_slugs_of_names() {
+        assert_eq!(layer_id("Background"), "layer-background");
+        assert_eq!(layer_id("  Sky & Clouds! "), "layer-sky-clouds");
+        assert_eq!(layer_id("Layer 1"), "layer-1");
+        assert_eq!(layer_id("layer"), "layer");
+        assert_eq!(layer_id("Layered"), "layer-layered");
+        assert_eq!(layer_id("Étage"), "layer-étage");
+        assert_eq!(layer_id(" -- "), "layer");
+    }
+
+    #[test]
+    fn groups_round_trip() {
+        let mut layer = Layer::new("Notes");
+        layer.visible = false;
+        layer.locked = true;
+        layer.opacity = 0.5;
+        let group = layer.to_group("layer-notes");
+        assert_eq!(Layer::from_group(&group), Some(layer.clone()));
+
+        let Shape::Group(plain) = layer.to_plain_group("layer-notes") else {
+            panic!("Expected a group");
+        };
+        assert!(plain
+            .style
+            .attributes
+            .iter()
+            .all(|(name, _)| name != LAYER_ATTRIBUTE && name != LOCKED_ATTRIBUTE));
+    }
+}
@@ -0,0 +1,111 @@
+use crate::application_state::ApplicationState;
+use crate::notifications::report_failure;
+use dioxus::prelude::*;
+
+/// The layers of the displayed page, topmost first, with controls for their
+/// visibility, lock state, opacity and stacking order.
+#[component]
+pub fn LayerPanel(application_state: Signal<ApplicationState>) -> Element {
+    let mut state = application_state;
+    let Some(layers) = state
+        .read()
+        .current()
+        .current_page()
+        .map(|page| page.layers.clone())
+    else {
+        return rsx! {};
+    };
+    let count = layers.len();
+
+    let report = move |result| report_failure(state, "Layer operation failed", result);
+
+    rsx! {
+        div {
+            class: "layer-panel",
+            div {
+                class: "layer-header",
+                span { "Layers" }
+                button {
+                    class: "page-button",
+                    title: "Add layer",
+                    onclick: move |_| report(state.write().current_mut().add_layer()),
+                    "＋"
+                }
+            }
+            for (index, layer) in layers.into_iter().enumerate().rev() {
+                div {
+                    key: "{index}",
+                    class: if layer.visible { "layer-row" } else { "layer-row layer-hidden" },
+                    button {
+                        class: "layer-toggle",
+                        title: if layer.visible { "Hide layer" } else { "Show layer" },
+                        onclick: move |_| {
+                            report(state.write().current_mut().edit_layer(index, |l| l.visible = !l.visible))
+                        },
+                        if layer.visible { "👁" } else { "⊘" }
+                    }
+                    button {
+                        class: "layer-toggle",
+                        title: if layer.locked { "Unlock layer" } else { "Lock layer" },
+                        onclick: move |_| {
+                            report(state.write().current_mut().edit_layer(index, |l| l.locked = !l.locked))
+                        },
+                        if layer.locked { "🔒" } else { "🔓" }
+                    }
+                    input {
+                        class: "layer-name",
+                        r#type: "text",
+                        value: "{layer.name}",
+                        disabled: layer.locked,
+                        onfocus: move |_| state.write().current_mut().begin_gesture(),
+                        onblur: move |_| state.write().current_mut().end_gesture(),
+                        oninput: move |e: FormEvent| {
+                            report(state.write().current_mut().edit_layer(index, |l| l.name = e.value()))
+                        },
+                    }
+                    input {
+                        class: "layer-opacity",
+                        r#type: "range",
+                        min: "0",
+                        max: "100",
+                        title: "Opacity",
+                        value: "{(layer.opacity * 100.0).round()}",
+                        // A drag of the slider is undone as one change.
+                        onpointerdown: move |_| state.write().current_mut().begin_gesture(),
+                        onchange: move |_| state.write().current_mut().end_gesture(),
+                        oninput: move |e: FormEvent| {
+                            let Ok(percent) = e.value().parse::<f64>() else {
+                                return;
+                            };
+                            let opacity = (percent / 100.0).clamp(0.0, 1.0);
+                            report(state.write().current_mut().edit_layer(index, |l| l.opacity = opacity))
+                        },
+                    }
+                    button {
+                        class: "page-button",
+                        title: "Move layer up",
+                        disabled: index + 1 >= count,
+                        onclick: move |_| report(state.write().current_mut().move_layer(index, index + 1)),
+                        "▲"
+                    }
+                    button {
+                        class: "page-button",
+                        title: "Move layer down",
+                        disabled: index == 0,
+                        onclick: move |_| {
+                            report(state.write().current_mut().move_layer(index, index.saturating_sub(1)))
+                        },
+                        "▼"
+                    }
+                    button {
+                        class: "page-button",
+                        title: "Delete layer",
+                        disabled: count <= 1 || layer.locked,
+                        onclick: move |_| report(state.write().current_mut().delete_layer(index)),
+                        "🗑"
+                    }
+                }
+            }
+        }
+    }
+}
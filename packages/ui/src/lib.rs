@@ -3,6 +3,30 @@
 mod document_ui;
 pub use document_ui::DocumentUI;
 
+mod export_ui;
+pub use export_ui::ExportDialog;
+
+mod layer_ui;
+pub use layer_ui::LayerPanel;
+
+mod notifications_ui;
+pub use notifications_ui::NotificationCenter;
+
+mod page_ui;
+pub use page_ui::PageNavigator;
+
+mod properties_ui;
+pub use properties_ui::PropertiesPanel;
+
+mod selection_ui;
+pub use selection_ui::PageCanvas;
+
+mod tab_ui;
+pub use tab_ui::DocumentTabs;
+
+mod unsaved_changes_ui;
+pub use unsaved_changes_ui::UnsavedChangesPrompt;
+
 mod platform;
 pub use platform::{
     delete_document, file_size, load_document, save_document, saved_files, share_document_mobile,
@@ -13,8 +37,100 @@ pub use platform::{
 mod mobile_file_menu;
 mod web_file_menu;
 
+mod actions;
+pub use actions::{
+    action, menu_actions, perform, proceed, request, Action, ActionHost, MenuName, Shortcut,
+    ACTIONS,
+};
+
+mod keybindings;
+pub use keybindings::{listen_for_shortcuts, use_pressed_actions, Keybindings};
+
 mod application_state;
-pub use application_state::ApplicationState;
+pub use application_state::{ApplicationState, DocumentAction, RestoreOffer};
+
+mod asset;
+pub use asset::{content_hash, image_mime_type, Asset, AssetTable};
+
+mod atomic_save;
+pub use atomic_save::{
+    backup_path, backups_of, write_atomically, Backup, DEFAULT_BACKUP_GENERATIONS,
+};
+
+mod binary;
+
+mod bundle;
+
+mod clipboard;
+pub use clipboard::{
+    copied_content, insert_into_field, paste_content, read_clipboard_api, selected_field_text,
+    write_clipboard_api, CopiedContent, PastedContent,
+};
 
 mod document;
 pub use document::Document;
+
+mod export;
+pub use export::{
+    export, export_png, export_svg, ExportFormat, ExportOptions, PngOptions, CSS_DPI,
+    MAX_BITMAP_SIDE,
+};
+
+mod pdf_export;
+pub use pdf_export::{export_pdf, Orientation, PageSize, PdfOptions};
+
+mod file_drop;
+
+mod file_format;
+pub use file_format::{
+    is_document_file_name, is_openable_file_name, is_svg_file_name, openable_extensions,
+    FileFormat, SVG_EXTENSION,
+};
+
+mod history;
+pub use history::{Command, History, DEFAULT_UNDO_DEPTH};
+
+mod layer;
+pub use layer::Layer;
+
+mod notifications;
+pub use notifications::{
+    report_failure, report_warning, Notification, NotificationKind, Notifications,
+    NOTIFICATION_HISTORY_LIMIT, TOAST_DURATION,
+};
+
+mod open_document;
+pub use open_document::OpenDocument;
+
+mod metadata;
+pub use metadata::{format_timestamp, Metadata};
+
+mod page;
+pub use page::Page;
+
+mod recent_files;
+pub use recent_files::{RecentFiles, DEFAULT_RECENT_FILES_LIMIT};
+
+mod recovery;
+pub use recovery::{recovered_documents, RecoveredDocument, DEFAULT_AUTOSAVE_INTERVAL};
+
+mod sanitizer;
+pub use sanitizer::{sanitize, sanitize_page, Removal, SanitizeReport};
+
+mod schema;
+pub use schema::CURRENT_VERSION;
+
+mod selection;
+pub use selection::{shape_at, shape_bounds, Bounds, ShapeRef};
+
+mod shape;
+pub use shape::{
+    Attributes, Circle, Drawing, Element, Ellipse, Group, Line, Node, Path, Rect, Shape, Style,
+    Text, ViewBox,
+};
+
+mod svg_parser;
+pub use svg_parser::parse_svg;
+
+mod validation;
+pub use validation::{check_svg, validate_svg, Diagnostic, InvalidSvg, Position, Severity};
@@ -0,0 +1,91 @@
+//! Descriptive information about a document.
+
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Descriptive information stored alongside a document's content.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Metadata {
+    /// The document's title (empty if untitled).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub title: String,
+
+    /// Who wrote the document (empty if unknown).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub author: String,
+
+    /// When the document was created, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<DateTime<Utc>>,
+
+    /// When the document was last saved, if ever.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<DateTime<Utc>>,
+
+    /// Free-text notes about the document.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+
+    /// Keywords for finding the document.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl Metadata {
+    /// Returns the metadata for a document created now by the current user.
+    pub fn new() -> Self {
+        let now = Utc::now();
+        Self {
+            author: current_user().unwrap_or_default(),
+            created: Some(now),
+            modified: Some(now),
+            ..Self::default()
+        }
+    }
+
+    /// Records that the document is being saved now.
+    pub fn touch(&mut self) {
+        let now = Utc::now();
+        self.created.get_or_insert(now);
+        self.modified = Some(now);
+    }
+
+    /// Returns the title, or `fallback` if the document is untitled.
+    pub fn title_or<'a>(&'a self, fallback: &'a str) -> &'a str {
+        if self.title.trim().is_empty() {
+            fallback
+        } else {
+            &self.title
+        }
+    }
+
+    /// Returns the tags as a comma-separated list.
+    pub fn tags_text(&self) -> String {
+        self.tags.join(", ")
+    }
+
+    /// Replaces the tags with those in the comma-separated list `text`.
+    pub fn set_tags_text(&mut self, text: &str) {
+        self.tags = text
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect();
+    }
+}
+
+/// Returns `time` formatted for display in the local time zone.
+pub fn format_timestamp(time: &DateTime<Utc>) -> String {
+    time.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+/// Returns the name of the user running the application, if known.
+fn current_user() -> Option<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+        .filter(|name| !name.is_empty())
+}
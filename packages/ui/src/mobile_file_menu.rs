@@ -1,13 +1,21 @@
+use crate::actions::{action, menu_actions, perform, proceed, ActionHost, MenuName};
 use crate::application_state::ApplicationState;
+use crate::export_ui::ExportDialog;
+use crate::file_format::{is_svg_file_name, FileFormat};
+use crate::keybindings::use_pressed_actions;
+use crate::metadata::format_timestamp;
+use crate::notifications::{report_failure, report_warning};
 use crate::platform::{
     delete_document, file_size, load_document, save_document, saved_files, share_document_mobile,
+    storage_path,
 };
+use crate::unsaved_changes_ui::UnsavedChangesPrompt;
 use crate::Document;
+use anyhow::{Context, Result};
 use dioxus::prelude::*;
 
 // Mobile-specific imports
-use serde_json::{from_str, to_string_pretty};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Individual menu item in the bottom sheet.
 #[component]
@@ -31,167 +39,127 @@ fn MenuItem(
     }
 }
 
-/// Menu item for creating a new document.
-#[component]
-fn NewMenuItem(mut state: Signal<ApplicationState>, mut menu_open: Signal<bool>) -> Element {
-    let handle_click = move |_| {
-        state.write().new_document();
-        menu_open.set(false);
-    };
-
-    rsx! {
-        MenuItem {
-            icon: "📄",
-            title: "New",
-            subtitle: "Create a new document",
-            onclick: handle_click,
-        }
-    }
+/// The mobile app's side of carrying out actions.
+#[derive(Clone, Copy, PartialEq)]
+struct MobileHost {
+    state: Signal<ApplicationState>,
+    file_list_open: Signal<bool>,
+    filename_prompt_open: Signal<bool>,
+    filename_input: Signal<String>,
+    saved_files_list: Signal<Vec<String>>,
 }
 
-/// Menu item for opening a saved document.
-#[component]
-fn OpenMenuItem(
-    mut menu_open: Signal<bool>,
-    mut file_list_open: Signal<bool>,
-    mut saved_files_list: Signal<Vec<String>>,
-    mut error_message: Signal<Option<String>>,
-) -> Element {
-    let handle_click = move |_| match saved_files() {
-        Ok(files) => {
-            saved_files_list.set(files);
-            error_message.set(None);
-            file_list_open.set(true);
-            menu_open.set(false);
-        }
-        Err(e) => {
-            error_message.set(Some(format!("Failed to load saved files: {e}")));
-            saved_files_list.set(vec![]);
-            file_list_open.set(true);
-            menu_open.set(false);
-        }
-    };
+impl ActionHost for MobileHost {
+    fn state(&self) -> Signal<ApplicationState> {
+        self.state
+    }
 
-    rsx! {
-        MenuItem {
-            icon: "📂",
-            title: "Open",
-            subtitle: "Browse saved documents ({saved_files_list.read().len()} files)",
-            onclick: handle_click,
-        }
+    fn offers(&self, id: &str) -> bool {
+        // Quitting is up to the operating system, and there is no image
+        // picker yet.
+        !matches!(id, "insert_image" | "quit")
     }
-}
 
-/// Menu item for saving the current document.
-#[component]
-fn SaveMenuItem(
-    mut state: Signal<ApplicationState>,
-    mut menu_open: Signal<bool>,
-    mut saved_files_list: Signal<Vec<String>>,
-    mut error_message: Signal<Option<String>>,
-) -> Element {
-    let handle_click = move |_| {
-        let current_state = state.read();
-        match to_string_pretty(&current_state.the_only_document) {
-            Ok(json_content) => {
-                let filename = current_state
-                    .current_file_path
-                    .as_ref()
-                    .and_then(|p| p.file_name())
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("document.json");
-
-                match save_document(&json_content, filename) {
-                    Ok(_) => match saved_files() {
-                        Ok(files) => {
-                            saved_files_list.set(files);
-                            error_message.set(None);
-                        }
-                        Err(e) => error_message
-                            .set(Some(format!("Failed to refresh file list after save: {e}"))),
-                    },
-                    Err(e) => error_message.set(Some(format!("Failed to save document: {e}"))),
-                }
-            }
-            Err(e) => {
-                error_message.set(Some(format!("Failed to serialize document for save: {e}")));
-            }
-        }
-        menu_open.set(false);
-    };
+    fn open(&self) -> Result<()> {
+        show_file_list(self.state, self.file_list_open, self.saved_files_list);
+        Ok(())
+    }
 
-    rsx! {
-        MenuItem {
-            icon: "💾",
-            title: "Save",
-            subtitle: "Save current document",
-            onclick: handle_click,
-        }
+    fn save(&self) -> Result<bool> {
+        save(self.state, self.saved_files_list).map(|()| true)
     }
-}
 
-/// Menu item for saving the document with a new name.
-#[component]
-fn SaveAsMenuItem(
-    mut state: Signal<ApplicationState>,
-    mut menu_open: Signal<bool>,
-    mut filename_prompt_open: Signal<bool>,
-    mut filename_input: Signal<String>,
-) -> Element {
-    let handle_click = move |_| {
+    fn save_as(&self) -> Result<()> {
+        let mut filename_input = self.filename_input;
+        let mut filename_prompt_open = self.filename_prompt_open;
         filename_input.set({
-            let current_state = state.read();
+            let current_state = self.state.read();
+            // The extension follows the format chosen in the prompt.
             current_state
-                .current_file_path
+                .current()
+                .file_path
                 .as_ref()
-                .and_then(|p| p.file_name())
+                .filter(|p| FileFormat::from_path(p).is_some())
+                .and_then(|p| p.file_stem())
                 .and_then(|n| n.to_str())
                 .unwrap_or("document")
-                .replace(".json", "")
+                .to_string()
         });
         filename_prompt_open.set(true);
-        menu_open.set(false);
-    };
+        Ok(())
+    }
+
+    fn share(&self) -> Result<()> {
+        let json_content = self.state.read().current().document.to_json()?;
+        share_document_mobile(json_content.as_bytes());
+        Ok(())
+    }
+}
+
+/// Returns the icon of the menu item for the action `id`.
+fn icon(id: &str) -> &'static str {
+    match id {
+        "new" => "📄",
+        "open" => "📂",
+        "close" => "✖️",
+        "save" => "💾",
+        "save_as" => "📋",
+        "export" => "🖼️",
+        "properties" => "ℹ️",
+        "share" => "📤",
+        "copy" => "📑",
+        "paste" => "📥",
+        _ => "•",
+    }
+}
+
+/// Floating buttons for undoing and redoing changes.
+#[component]
+fn HistoryButtons(host: MobileHost) -> Element {
+    let state = host.state.read();
 
     rsx! {
-        MenuItem {
-            icon: "📋",
-            title: "Save As",
-            subtitle: "Save with new name",
-            onclick: handle_click,
+        div {
+            class: "fab-history",
+            for id in ["undo", "redo"] {
+                if let Some(action) = action(id) {
+                    button {
+                        class: "fab-history-button",
+                        title: action.label,
+                        disabled: !(action.enabled)(&state),
+                        onclick: move |_| perform(&host, id),
+                        if id == "undo" { "↶" } else { "↷" }
+                    }
+                }
+            }
         }
     }
 }
 
-/// Menu item for sharing the document.
+/// Menu item in the bottom sheet carrying out the action `id`.
 #[component]
-fn ShareMenuItem(
-    mut state: Signal<ApplicationState>,
-    mut menu_open: Signal<bool>,
-    mut error_message: Signal<Option<String>>,
-) -> Element {
+fn ActionMenuItem(host: MobileHost, id: &'static str, mut menu_open: Signal<bool>) -> Element {
+    let Some(action) = action(id) else {
+        return rsx! {};
+    };
     let handle_click = move |_| {
-        let current_state = state.read();
-        match to_string_pretty(&current_state.the_only_document) {
-            Ok(json_content) => {
-                share_document_mobile(&json_content);
-            }
-            Err(e) => {
-                error_message.set(Some(format!("Failed to serialize document for share: {e}")));
-            }
-        }
         menu_open.set(false);
+        perform(&host, id);
     };
 
     rsx! {
         button {
-            class: "mobile-menu-item mobile-menu-item-share",
+            class: if id == "share" {
+                "mobile-menu-item mobile-menu-item-share"
+            } else {
+                "mobile-menu-item"
+            },
             onclick: handle_click,
-            div { class: "menu-item-icon", "📤" }
+            div { class: "menu-item-icon", "{icon(id)}" }
             div {
                 class: "menu-item-content",
-                div { class: "menu-item-title", "Share" }
-                div { class: "menu-item-subtitle", "Share document with other apps" }
+                div { class: "menu-item-title", "{action.label.trim_end_matches(\"...\")}" }
+                div { class: "menu-item-subtitle", "{action.description}" }
             }
         }
     }
@@ -199,16 +167,9 @@ fn ShareMenuItem(
 
 /// Bottom sheet menu with file actions.
 #[component]
-fn MenuBottomSheet(
-    state: Signal<ApplicationState>,
-    mut menu_open: Signal<bool>,
-    file_list_open: Signal<bool>,
-    filename_prompt_open: Signal<bool>,
-    saved_files_list: Signal<Vec<String>>,
-    error_message: Signal<Option<String>>,
-    filename_input: Signal<String>,
-) -> Element {
+fn MenuBottomSheet(host: MobileHost, mut menu_open: Signal<bool>) -> Element {
     let close_menu = move |_| menu_open.set(false);
+    let title = host.state.read().document_title();
 
     rsx! {
         div { class: "menu-overlay", onclick: close_menu }
@@ -216,16 +177,63 @@ fn MenuBottomSheet(
             class: "bottom-sheet",
             div {
                 class: "bottom-sheet-header",
-                h3 { "File Menu" }
+                div {
+                    h3 { "File Menu" }
+                    div { class: "bottom-sheet-subtitle", "{title}" }
+                }
                 button { class: "close-button", onclick: close_menu, "✕" }
             }
             div {
                 class: "menu-actions",
-                NewMenuItem { state, menu_open }
-                OpenMenuItem { menu_open, file_list_open, saved_files_list, error_message }
-                SaveMenuItem { state, menu_open, saved_files_list, error_message }
-                SaveAsMenuItem { state, menu_open, filename_prompt_open, filename_input }
-                ShareMenuItem { state, menu_open, error_message }
+                for action in menu_actions(&host, MenuName::File) {
+                    ActionMenuItem { key: "{action.id}", host, id: action.id, menu_open }
+                }
+            }
+            div {
+                class: "menu-actions menu-section",
+                h4 { class: "menu-section-title", "Edit" }
+                // Undo and redo have buttons of their own.
+                for action in menu_actions(&host, MenuName::Edit).into_iter().filter(|a| a.group > 0) {
+                    ActionMenuItem { key: "{action.id}", host, id: action.id, menu_open }
+                }
+            }
+            RecentSection { state: host.state, menu_open }
+        }
+    }
+}
+
+/// The recent documents, to reopen one, in the bottom sheet.
+#[component]
+fn RecentSection(mut state: Signal<ApplicationState>, mut menu_open: Signal<bool>) -> Element {
+    let recent: Vec<PathBuf> = state.read().recent_files.paths().to_vec();
+    if recent.is_empty() {
+        return rsx! {};
+    }
+
+    let mut open_recent = move |path: PathBuf| {
+        let result = state.write().open_recent(&path);
+        report_failure(state, "Failed to open recent document", result);
+        menu_open.set(false);
+    };
+
+    rsx! {
+        div {
+            class: "menu-actions menu-section",
+            h4 { class: "menu-section-title", "Recent" }
+            for path in recent {
+                MenuItem {
+                    key: "{path.display()}",
+                    icon: "🕘",
+                    title: path.file_name().map_or_else(
+                        || path.display().to_string(),
+                        |name| name.to_string_lossy().into_owned(),
+                    ),
+                    subtitle: "Open recent document",
+                    onclick: {
+                        let path = path.clone();
+                        move |_| open_recent(path.clone())
+                    },
+                }
             }
         }
     }
@@ -240,6 +248,23 @@ fn FileItem(
 ) -> Element {
     let filename_for_open = filename.clone();
     let filename_for_delete = filename.clone();
+    let metadata = load_document(&filename)
+        .and_then(|content| Document::metadata_from_bytes(&content))
+        .unwrap_or_default();
+    let title = metadata.title_or(&filename).to_string();
+    let details = [
+        metadata.author.clone(),
+        metadata
+            .modified
+            .as_ref()
+            .map(format_timestamp)
+            .unwrap_or_default(),
+    ]
+    .into_iter()
+    .filter(|detail| !detail.is_empty())
+    .collect::<Vec<_>>()
+    .join(" · ");
+    let tags = metadata.tags_text();
     if let Ok(n) = file_size(&filename) {
         rsx! {
             div {
@@ -250,7 +275,19 @@ fn FileItem(
                     div { class: "file-item-icon", "📄" }
                     div {
                         class: "file-item-info",
-                        div { class: "file-item-name", "{filename}" }
+                        div { class: "file-item-name", "{title}" }
+                        if title != filename {
+                            div { class: "file-item-detail", "{filename}" }
+                        }
+                        if !details.is_empty() {
+                            div { class: "file-item-detail", "{details}" }
+                        }
+                        if !tags.is_empty() {
+                            div { class: "file-item-tags", "{tags}" }
+                        }
+                        if !metadata.description.is_empty() {
+                            div { class: "file-item-detail", "{metadata.description}" }
+                        }
                         div { class: "file-item-size", "{n} bytes" }
                     }
                 }
@@ -272,43 +309,51 @@ fn FileItem(
 fn FileListModal(
     mut state: Signal<ApplicationState>,
     mut file_list_open: Signal<bool>,
-    mut saved_files_list: Signal<Vec<String>>,
-    mut error_message: Signal<Option<String>>,
+    saved_files_list: Signal<Vec<String>>,
 ) -> Element {
     let close_file_list = move |_| file_list_open.set(false);
 
     let handle_file_open = move |filename: String| {
-        match load_document(&filename) {
-            Ok(content) => match from_str::<Document>(&content) {
-                Ok(document) => {
-                    state.write().the_only_document = document;
-                    state.write().current_file_path = Some(PathBuf::from(&filename));
-                    error_message.set(None);
-                }
-                Err(e) => {
-                    error_message.set(Some(format!(
-                        "Failed to parse document from file {filename}: {e}"
-                    )));
-                }
-            },
+        file_list_open.set(false);
+        if is_svg_file_name(&filename) {
+            let result = load_document(&filename)
+                .and_then(|content| state.write().import_svg(&filename, &content));
+            report_failure(state, &format!("Couldn't open \"{filename}\""), result);
+            return;
+        }
+        let opened = load_document(&filename)
+            .with_context(|| format!("Failed to load document {filename}"))
+            .and_then(|content| {
+                Document::from_bytes(&content)
+                    .with_context(|| format!("Failed to parse document from file {filename}"))
+            });
+        match opened {
+            Ok(document) => {
+                state
+                    .write()
+                    .open_document(document, Some(PathBuf::from(&filename)));
+            }
             Err(e) => {
-                error_message.set(Some(format!("Failed to load document {filename}: {e}")));
+                let mut app_state = state.write();
+                app_state.record_open_failure(&e);
+                app_state.offer_backups(&storage_path(&filename));
+                app_state
+                    .notifications
+                    .error(format!("Couldn't open \"{filename}\""), &e);
             }
         }
-        file_list_open.set(false);
     };
 
     let handle_file_delete = move |filename: String| match delete_document(&filename) {
-        Ok(_) => match saved_files() {
-            Ok(files) => {
-                saved_files_list.set(files);
-                error_message.set(None);
-            }
-            Err(e) => error_message.set(Some(format!(
-                "Failed to refresh file list after delete: {e}"
-            ))),
-        },
-        Err(e) => error_message.set(Some(format!("Failed to delete document: {e}"))),
+        Ok(_) => {
+            let removed = state.write().recent_files.remove(Path::new(&filename));
+            report_warning(state, "Couldn't update the recent files", removed);
+            refresh_file_list(state, saved_files_list);
+        }
+        Err(e) => state
+            .write()
+            .notifications
+            .error(format!("Couldn't delete \"{filename}\""), &e),
     };
 
     rsx! {
@@ -347,56 +392,28 @@ fn FileListModal(
 /// Modal for entering a filename when saving.
 #[component]
 fn FilenamePromptModal(
-    mut state: Signal<ApplicationState>,
+    state: Signal<ApplicationState>,
     mut filename_prompt_open: Signal<bool>,
     mut filename_input: Signal<String>,
-    mut saved_files_list: Signal<Vec<String>>,
-    mut error_message: Signal<Option<String>>,
+    saved_files_list: Signal<Vec<String>>,
 ) -> Element {
     let close_prompt = move |_| filename_prompt_open.set(false);
-
-    let save_with_filename = move |_| {
+    let mut format = use_signal(|| {
+        state
+            .read()
+            .current()
+            .file_path
+            .as_deref()
+            .and_then(FileFormat::from_path)
+            .unwrap_or_default()
+    });
+
+    let mut save_with_filename = move || {
         let filename = filename_input.read().clone();
         if !filename.trim().is_empty() {
-            let json_content = {
-                let current_state = state.read();
-                to_string_pretty(&current_state.the_only_document)
-            };
-
-            match json_content {
-                Ok(json_content) => {
-                    let filename = if filename.ends_with(".json") {
-                        filename
-                    } else {
-                        format!("{filename}.json")
-                    };
-
-                    match save_document(&json_content, &filename) {
-                        Ok(_) => {
-                            {
-                                let mut app_state = state.write();
-                                app_state.current_file_path = Some(PathBuf::from(&filename));
-                            }
-                            match saved_files() {
-                                Ok(files) => {
-                                    saved_files_list.set(files);
-                                    error_message.set(None);
-                                }
-                                Err(e) => error_message
-                                    .set(Some(format!("Failed to refresh file list: {e}"))),
-                            }
-                        }
-                        Err(e) => {
-                            error_message.set(Some(format!("Failed to save document: {e}")));
-                        }
-                    }
-                }
-                Err(e) => {
-                    error_message.set(Some(format!(
-                        "Failed to serialize document for save with filename: {e}"
-                    )));
-                }
-            }
+            let filename = format().file_name(&filename);
+            let result = save_as(state, &filename, saved_files_list);
+            report_failure(state, "Failed to save document", result);
         }
         filename_prompt_open.set(false);
     };
@@ -405,49 +422,15 @@ fn FilenamePromptModal(
         filename_input.set(event.value());
     };
 
+    let handle_format_change = move |event: FormEvent| {
+        if let Some(chosen) = FileFormat::from_extension(&event.value()) {
+            format.set(chosen);
+        }
+    };
+
     let handle_filename_keypress = move |event: KeyboardEvent| {
         if event.key() == Key::Enter {
-            let filename = filename_input.read().clone();
-            if !filename.trim().is_empty() {
-                let json_content = {
-                    let current_state = state.read();
-                    to_string_pretty(&current_state.the_only_document)
-                };
-
-                match json_content {
-                    Ok(json_content) => {
-                        let filename = if filename.ends_with(".json") {
-                            filename
-                        } else {
-                            format!("{filename}.json")
-                        };
-
-                        match save_document(&json_content, &filename) {
-                            Ok(_) => {
-                                {
-                                    let mut app_state = state.write();
-                                    app_state.current_file_path = Some(PathBuf::from(&filename));
-                                }
-                                match saved_files() {
-                                    Ok(files) => saved_files_list.set(files),
-                                    Err(e) => error_message.set(Some(format!(
-                                        "Failed to refresh file list after save: {e}"
-                                    ))),
-                                }
-                            }
-                            Err(e) => {
-                                error_message.set(Some(format!("Failed to save document: {e}")))
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        error_message.set(Some(format!(
-                            "Failed to serialize document for keypress save: {e}"
-                        )));
-                    }
-                }
-            }
-            filename_prompt_open.set(false);
+            save_with_filename();
         }
     };
 
@@ -474,12 +457,31 @@ fn FilenamePromptModal(
                         oninput: handle_filename_input,
                         onkeypress: handle_filename_keypress,
                     }
-                    div { class: "filename-hint", ".json extension will be added automatically" }
+                    div {
+                        class: "filename-hint",
+                        "The extension of the chosen format will be added automatically"
+                    }
+                }
+                div {
+                    class: "filename-prompt-field",
+                    label { r#for: "format-input", "Format:" }
+                    select {
+                        id: "format-input",
+                        class: "filename-input",
+                        onchange: handle_format_change,
+                        for option_format in FileFormat::ALL {
+                            option {
+                                value: option_format.extension(),
+                                selected: option_format == format(),
+                                "{option_format.description()} (.{option_format.extension()})"
+                            }
+                        }
+                    }
                 }
                 div {
                     class: "filename-prompt-buttons",
                     button { class: "filename-button filename-cancel", onclick: close_prompt, "Cancel" }
-                    button { class: "filename-button filename-save", onclick: save_with_filename, "Save" }
+                    button { class: "filename-button filename-save", onclick: move |_| save_with_filename(), "Save" }
                 }
             }
         }
@@ -495,7 +497,14 @@ pub fn MobileFileMenu(application_state: Signal<ApplicationState>) -> Element {
     let filename_prompt_open = use_signal(|| false);
     let filename_input = use_signal(String::new);
     let saved_files_list = use_signal(|| saved_files().unwrap_or_default());
-    let error_message = use_signal(|| None::<String>);
+    let host = MobileHost {
+        state,
+        file_list_open,
+        filename_prompt_open,
+        filename_input,
+        saved_files_list,
+    };
+    use_pressed_actions(host);
 
     let toggle_menu = move |_| {
         let current = menu_open();
@@ -517,17 +526,34 @@ pub fn MobileFileMenu(application_state: Signal<ApplicationState>) -> Element {
                 "⋮" // Three dots menu icon
             }
 
+            // Undo and redo, always at hand
+            HistoryButtons { host }
+
+            UnsavedChangesPrompt {
+                application_state: state,
+                on_save: move |_| match host.save() {
+                    Ok(saved) => saved,
+                    Err(e) => {
+                        host.report("Save", e);
+                        false
+                    }
+                },
+                // Closes the document, then quits too if quitting asked.
+                on_proceed: move |action| proceed(&host, action),
+            }
+
+            ExportDialog {
+                application_state: state,
+                on_export: move |(content, file_name): (Vec<u8>, String)| {
+                    if let Err(e) = host.export(&content, &file_name) {
+                        host.report("Export", e);
+                    }
+                },
+            }
+
             // Bottom sheet menu
             if *menu_open.read() {
-                MenuBottomSheet {
-                    state,
-                    menu_open,
-                    file_list_open,
-                    filename_prompt_open,
-                    saved_files_list,
-                    error_message,
-                    filename_input,
-                }
+                MenuBottomSheet { host, menu_open }
             }
 
             // File list modal
@@ -536,7 +562,6 @@ pub fn MobileFileMenu(application_state: Signal<ApplicationState>) -> Element {
                     state,
                     file_list_open,
                     saved_files_list,
-                    error_message,
                 }
             }
 
@@ -547,9 +572,87 @@ pub fn MobileFileMenu(application_state: Signal<ApplicationState>) -> Element {
                     filename_prompt_open,
                     filename_input,
                     saved_files_list,
-                    error_message,
                 }
             }
         }
     }
 }
+
+/// Saves the current document to storage under its name ("document.json" if
+/// it has none).
+fn save(mut state: Signal<ApplicationState>, saved_files_list: Signal<Vec<String>>) -> Result<()> {
+    let filename = state
+        .read()
+        .current()
+        .file_path
+        .as_ref()
+        .and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+        .unwrap_or("document.json")
+        .to_string();
+    let saved = state.read().current().stamped_for_saving();
+    write_document(state, &saved, &filename)?;
+    state.write().mark_saved(&saved);
+    refresh_file_list(state, saved_files_list);
+    Ok(())
+}
+
+/// Saves the current document to storage as `filename`, in the format its
+/// extension names.
+fn save_as(
+    mut state: Signal<ApplicationState>,
+    filename: &str,
+    saved_files_list: Signal<Vec<String>>,
+) -> Result<()> {
+    let saved = state.read().current().stamped_for_saving();
+    write_document(state, &saved, filename)?;
+    {
+        let mut app_state = state.write();
+        app_state.current_mut().file_path = Some(PathBuf::from(filename));
+        app_state.mark_saved(&saved);
+    }
+    refresh_file_list(state, saved_files_list);
+    Ok(())
+}
+
+/// Writes `document`, the current document as saved, to storage as
+/// `filename`, in the format its extension names.
+fn write_document(
+    state: Signal<ApplicationState>,
+    document: &Document,
+    filename: &str,
+) -> Result<()> {
+    let format = FileFormat::from_path(Path::new(filename)).unwrap_or_default();
+    let content = document
+        .to_bytes(format)
+        .context("Failed to serialize document for save")?;
+    save_document(&content, filename, state.read().backup_generations)
+        .context("Failed to save document")
+}
+
+/// Lists the saved documents anew, warning the user if they can't be.
+fn refresh_file_list(
+    mut state: Signal<ApplicationState>,
+    mut saved_files_list: Signal<Vec<String>>,
+) {
+    match saved_files() {
+        Ok(files) => saved_files_list.set(files),
+        Err(e) => {
+            saved_files_list.set(vec![]);
+            state
+                .write()
+                .notifications
+                .warning("Couldn't list the saved documents", &e);
+        }
+    }
+}
+
+/// Shows the list of saved documents, to open one.
+fn show_file_list(
+    state: Signal<ApplicationState>,
+    mut file_list_open: Signal<bool>,
+    saved_files_list: Signal<Vec<String>>,
+) {
+    refresh_file_list(state, saved_files_list);
+    file_list_open.set(true);
+}
@@ -0,0 +1,243 @@
+//! Messages for the user: toasts that go away by themselves, dialogs
+//! explaining failures with the causes anyhow recorded for them, and a
+//! history of the recent messages.
+
+use crate::application_state::ApplicationState;
+use chrono::{DateTime, Utc};
+use dioxus::prelude::*;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// How many messages the history keeps.
+pub const NOTIFICATION_HISTORY_LIMIT: usize = 50;
+
+/// How long a toast is shown.
+pub const TOAST_DURATION: Duration = Duration::from_secs(5);
+
+/// How serious a message is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    /// Something the user may want to know, shown as a toast.
+    Info,
+
+    /// A failure the user can carry on after, shown as a toast.
+    Warning,
+
+    /// A failure of what the user asked for, shown in a dialog.
+    Error,
+}
+
+/// A message for the user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    /// Identifies the message among those of the session.
+    pub id: u64,
+
+    /// How serious the message is.
+    pub kind: NotificationKind,
+
+    /// What happened.
+    pub message: String,
+
+    /// Why it failed, from the outermost cause in, for failures.
+    pub causes: Vec<String>,
+
+    /// When it happened.
+    pub time: DateTime<Utc>,
+}
+
+/// The messages for the user.
+#[derive(Debug, Default)]
+pub struct Notifications {
+    /// The recent messages, newest first.
+    history: VecDeque<Notification>,
+
+    /// The ids of the messages shown as toasts, oldest first.
+    toasts: Vec<u64>,
+
+    /// The id of the message shown in a dialog, if any.
+    dialog: Option<u64>,
+
+    /// Whether the history is shown.
+    pub show_history: bool,
+
+    /// The id of the next message.
+    next_id: u64,
+}
+
+impl Notifications {
+    /// Tells the user `message` in a toast.
+    pub fn info(&mut self, message: impl Into<String>) {
+        let id = self.push(NotificationKind::Info, message.into(), Vec::new());
+        self.toasts.push(id);
+    }
+
+    /// Tells the user in a toast that `message`, because of `error`.
+    pub fn warning(&mut self, message: impl Into<String>, error: &anyhow::Error) {
+        let id = self.push(NotificationKind::Warning, message.into(), causes(error));
+        self.toasts.push(id);
+    }
+
+    /// Tells the user in a dialog that `message`, because of `error`.
+    pub fn error(&mut self, message: impl Into<String>, error: &anyhow::Error) {
+        let id = self.push(NotificationKind::Error, message.into(), causes(error));
+        self.dialog = Some(id);
+    }
+
+    /// Returns the recent messages, newest first.
+    pub fn history(&self) -> impl Iterator<Item = &Notification> {
+        self.history.iter()
+    }
+
+    /// Forgets the recent messages, hiding any toast or dialog.
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+        self.toasts.clear();
+        self.dialog = None;
+    }
+
+    /// Returns the messages shown as toasts, oldest first.
+    pub fn toasts(&self) -> impl Iterator<Item = &Notification> {
+        self.toasts.iter().filter_map(|id| self.find(*id))
+    }
+
+    /// Stops showing the message `id` as a toast.
+    pub fn dismiss_toast(&mut self, id: u64) {
+        self.toasts.retain(|shown| *shown != id);
+    }
+
+    /// Returns the message shown in a dialog, if any.
+    pub fn dialog(&self) -> Option<&Notification> {
+        self.dialog.and_then(|id| self.find(id))
+    }
+
+    /// Stops showing the message in a dialog.
+    pub fn dismiss_dialog(&mut self) {
+        self.dialog = None;
+    }
+
+    /// Adds a message to the history, returning its id.
+    fn push(&mut self, kind: NotificationKind, message: String, causes: Vec<String>) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.history.push_front(Notification {
+            id,
+            kind,
+            message,
+            causes,
+            time: Utc::now(),
+        });
+        self.history.truncate(NOTIFICATION_HISTORY_LIMIT);
+        id
+    }
+
+    /// Returns the message `id`, if it's still in the history.
+    fn find(&self, id: u64) -> Option<&Notification> {
+        self.history.iter().find(|n| n.id == id)
+    }
+}
+
+/// Returns the causes of `error`, from the outermost in.
+fn causes(error: &anyhow::Error) -> Vec<String> {
+    error.chain().map(ToString::to_string).collect()
+}
+
+/// Tells the user in a toast that `message`, if `result` is a failure that
+/// doesn't keep them from carrying on.
+pub fn report_warning(
+    mut state: Signal<ApplicationState>,
+    message: &str,
+    result: anyhow::Result<()>,
+) {
+    if let Err(e) = result {
+        state.write().notifications.warning(message, &e);
+    }
+}
+
+/// Tells the user in a dialog that `message`, if `result` is a failure.
+pub fn report_failure(
+    mut state: Signal<ApplicationState>,
+    message: &str,
+    result: anyhow::Result<()>,
+) {
+    if let Err(e) = result {
+        state.write().notifications.error(message, &e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn history_keeps_the_newest_messages() {
+        let mut notifications = Notifications::default();
+        for n in 0..NOTIFICATION_HISTORY_LIMIT + 5 {
+            notifications.info(format!("Message {n}"));
+        }
+        let messages: Vec<&str> = notifications
+            .history()
+            .map(|n| n.message.as_str())
+            .collect();
+        assert_eq!(messages.len(), NOTIFICATION_HISTORY_LIMIT);
+        assert_eq!(
+            messages[0],
+            format!("Message {}", NOTIFICATION_HISTORY_LIMIT + 4)
+        );
+        assert_eq!(messages[messages.len() - 1], "Message 5");
+        // Toasts for forgotten messages aren't shown anymore.
+        assert_eq!(notifications.toasts().count(), NOTIFICATION_HISTORY_LIMIT);
+        assert_eq!(notifications.toasts().next().unwrap().message, "Message 5");
+    }
+
+    #[test]
+    fn dismissed_toasts_stay_in_the_history() {
+        let mut notifications = Notifications::default();
+        notifications.info("Saved");
+        notifications.warning("Couldn't update the recent files", &anyhow!("Disk full"));
+        let saved = notifications.toasts().next().unwrap().id;
+        notifications.dismiss_toast(saved);
+
+        let shown: Vec<&str> = notifications.toasts().map(|n| n.message.as_str()).collect();
+        assert_eq!(shown, ["Couldn't update the recent files"]);
+        assert_eq!(notifications.history().count(), 2);
+        assert_eq!(notifications.dialog(), None);
+    }
+
+    #[test]
+    fn dismissed_dialogs_stay_in_the_history() {
+        let mut notifications = Notifications::default();
+        let error = anyhow!("Permission denied").context("Failed to write drawing.json");
+        notifications.error("Couldn't save", &error);
+        let dialog = notifications.dialog().unwrap();
+        assert_eq!(dialog.kind, NotificationKind::Error);
+        assert_eq!(
+            dialog.causes,
+            ["Failed to write drawing.json", "Permission denied"]
+        );
+        assert_eq!(notifications.toasts().count(), 0);
+
+        notifications.dismiss_dialog();
+        assert_eq!(notifications.dialog(), None);
+        assert_eq!(
+            notifications.history().next().unwrap().message,
+            "Couldn't save"
+        );
+    }
+
+    #[test]
+    fn clearing_the_history_hides_everything() {
+        let mut notifications = Notifications::default();
+        notifications.info("Saved");
+        notifications.error("Couldn't open", &anyhow!("Not a document"));
+        notifications.clear_history();
+        assert_eq!(notifications.history().count(), 0);
+        assert_eq!(notifications.toasts().count(), 0);
+        assert_eq!(notifications.dialog(), None);
+
+        // Messages after clearing still get ids of their own.
+        notifications.info("Exported");
+        assert_eq!(notifications.history().next().unwrap().id, 2);
+    }
+}
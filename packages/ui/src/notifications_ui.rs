@@ -0,0 +1,138 @@
+use crate::application_state::ApplicationState;
+use crate::metadata::format_timestamp;
+use crate::notifications::{Notification, NotificationKind, TOAST_DURATION};
+use crate::platform::sleep;
+use dioxus::prelude::*;
+
+/// The messages for the user: toasts, the dialog explaining a failure, and
+/// the history when it's shown.
+#[component]
+pub fn NotificationCenter(application_state: Signal<ApplicationState>) -> Element {
+    let (toasts, dialog, show_history) = {
+        let state = application_state.read();
+        let notifications = &state.notifications;
+        (
+            notifications.toasts().cloned().collect::<Vec<_>>(),
+            notifications.dialog().cloned(),
+            notifications.show_history,
+        )
+    };
+
+    rsx! {
+        div {
+            class: "toasts",
+            for notification in toasts {
+                Toast { key: "{notification.id}", application_state, notification }
+            }
+        }
+        if let Some(notification) = dialog {
+            ErrorDialog { application_state, notification }
+        }
+        if show_history {
+            NotificationHistory { application_state }
+        }
+    }
+}
+
+/// Returns the class of the element showing a message of `kind`.
+fn kind_class(kind: NotificationKind) -> &'static str {
+    match kind {
+        NotificationKind::Info => "notification-info",
+        NotificationKind::Warning => "notification-warning",
+        NotificationKind::Error => "notification-error",
+    }
+}
+
+/// A message shown for a while.
+#[component]
+fn Toast(application_state: Signal<ApplicationState>, notification: Notification) -> Element {
+    let id = notification.id;
+    use_future(move || async move {
+        sleep(TOAST_DURATION).await;
+        application_state.write().notifications.dismiss_toast(id);
+    });
+    let dismiss = move |_| application_state.write().notifications.dismiss_toast(id);
+    let cause = notification.causes.first();
+
+    rsx! {
+        div {
+            class: "toast {kind_class(notification.kind)}",
+            div {
+                class: "toast-text",
+                div { "{notification.message}" }
+                if let Some(cause) = cause {
+                    div { class: "toast-cause", "{cause}" }
+                }
+            }
+            button { class: "document-notice-dismiss", title: "Dismiss", onclick: dismiss, "✕" }
+        }
+    }
+}
+
+/// A dialog explaining a failure, with its causes.
+#[component]
+fn ErrorDialog(application_state: Signal<ApplicationState>, notification: Notification) -> Element {
+    let dismiss = move |_| application_state.write().notifications.dismiss_dialog();
+
+    rsx! {
+        div {
+            class: "dialog-overlay",
+            div {
+                class: "dialog",
+                h3 { "{notification.message}" }
+                if !notification.causes.is_empty() {
+                    ul {
+                        class: "error-causes",
+                        for cause in notification.causes {
+                            li { "{cause}" }
+                        }
+                    }
+                }
+                div {
+                    class: "dialog-buttons",
+                    button { class: "dialog-button dialog-button-primary", onclick: dismiss, "OK" }
+                }
+            }
+        }
+    }
+}
+
+/// The recent messages, newest first.
+#[component]
+fn NotificationHistory(application_state: Signal<ApplicationState>) -> Element {
+    let history: Vec<Notification> = application_state
+        .read()
+        .notifications
+        .history()
+        .cloned()
+        .collect();
+    let close = move |_| application_state.write().notifications.show_history = false;
+    let clear = move |_| application_state.write().notifications.clear_history();
+
+    rsx! {
+        div {
+            class: "notification-history",
+            div {
+                class: "properties-header",
+                h3 { "Messages" }
+                button { class: "close-button", title: "Close", onclick: close, "✕" }
+            }
+            if history.is_empty() {
+                div { class: "notification-empty", "No messages" }
+            } else {
+                for notification in history {
+                    div {
+                        key: "{notification.id}",
+                        class: "notification-entry {kind_class(notification.kind)}",
+                        div { class: "notification-time", "{format_timestamp(&notification.time)}" }
+                        div { "{notification.message}" }
+                        for cause in notification.causes {
+                            div { class: "toast-cause", "{cause}" }
+                        }
+                    }
+                }
+                button { class: "dialog-button", onclick: clear, "Clear" }
+            }
+        }
+    }
+}
@@ -0,0 +1,531 @@
+use crate::asset::content_hash;
+use crate::document::Document;
+use crate::history::{Command, History};
+use crate::layer::Layer;
+use crate::metadata::Metadata;
+use crate::page::Page;
+use crate::recovery::{discard_recovery, new_recovery_id, write_recovery};
+use crate::selection::ShapeRef;
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A document open for editing, with the state of its editing.
+pub struct OpenDocument {
+    /// The document.
+    pub document: Document,
+
+    /// Where the document will be saved (`None` for new unsaved documents).
+    pub file_path: Option<PathBuf>,
+
+    /// The index of the page being displayed.
+    pub current_page: usize,
+
+    /// The shape selected on the page being displayed, if any.
+    selection: Option<ShapeRef>,
+
+    /// The changes made to the document, for undo and redo.
+    history: History,
+
+    /// The position in the history at which the document was last opened or
+    /// saved (`None` if its content has never been saved).
+    saved_position: Option<u64>,
+
+    /// The id of the document's recovery entry.
+    recovery_id: String,
+
+    /// The content hash of the document as last opened or saved.
+    saved_hash: String,
+
+    /// Identifies the content of the document among that of every document
+    /// open: a new revision is taken whenever a change is applied, undone
+    /// or redone.
+    revision: u64,
+
+    /// The revision of the document when it was last autosaved or saved.
+    autosaved_revision: u64,
+}
+
+impl OpenDocument {
+    /// Returns `document`, stored at `path` (if any), opened with no changes
+    /// and keeping at most `undo_depth` changes for undoing.
+    pub fn new(document: Document, path: Option<PathBuf>, undo_depth: usize) -> Self {
+        let saved_hash = document_hash(&document);
+        let revision = next_revision();
+        Self {
+            document,
+            file_path: path,
+            current_page: 0,
+            selection: None,
+            history: History::new(undo_depth),
+            saved_position: Some(0),
+            recovery_id: new_recovery_id(),
+            saved_hash,
+            revision,
+            autosaved_revision: revision,
+        }
+    }
+
+    /// Returns `document`, to be saved at `path` (if anywhere), opened with
+    /// all of its content unsaved.
+    pub(crate) fn new_unsaved(
+        document: Document,
+        path: Option<PathBuf>,
+        undo_depth: usize,
+    ) -> Self {
+        Self {
+            saved_position: None,
+            saved_hash: String::new(),
+            ..Self::new(document, path, undo_depth)
+        }
+    }
+
+    /// Returns `true` iff the document is a new one that was never changed,
+    /// and so can be replaced by the next document opened.
+    pub fn is_untouched(&self) -> bool {
+        self.file_path.is_none() && self.saved_position == Some(0) && !self.is_dirty()
+    }
+
+    /// Keeps at most `depth` changes for undoing.
+    pub fn set_undo_depth(&mut self, depth: usize) {
+        self.history.set_depth(depth);
+    }
+
+    /// Returns a copy of the document stamped as saved now, to be written.
+    ///
+    /// Once it is, [`OpenDocument::mark_saved`] records the stamp in the
+    /// document, so that a failed save leaves the document unchanged.
+    pub fn stamped_for_saving(&self) -> Document {
+        let mut document = self.document.clone();
+        document.touch();
+        document
+    }
+
+    /// Records `saved`, the document as written by a save, as saved; its
+    /// recovery entry is left for [`OpenDocument::discard_recovery`].
+    ///
+    /// The time of the save is recorded in the document's metadata, outside
+    /// of its history: it is a fact about the file rather than an edit.
+    pub fn mark_saved(&mut self, saved: &Document) {
+        let (created, modified) = (saved.metadata().created, saved.metadata().modified);
+        let metadata = self.document.metadata_mut();
+        metadata.created = created;
+        metadata.modified = modified;
+        self.history.end_gesture();
+        self.saved_position = Some(self.history.position());
+        self.saved_hash = document_hash(&self.document);
+        self.autosaved_revision = self.revision;
+    }
+
+    /// Discards the document's recovery entry, if it has one.
+    pub(crate) fn discard_recovery(&self) -> Result<()> {
+        discard_recovery(&self.recovery_id)
+    }
+
+    /// Returns `true` iff the document has changes that haven't been saved.
+    pub fn is_dirty(&self) -> bool {
+        self.saved_position != Some(self.history.position())
+    }
+
+    /// Returns `true` iff the document changed since it was last autosaved.
+    pub fn needs_autosave(&self) -> bool {
+        self.revision != self.autosaved_revision
+    }
+
+    /// Returns the revision of the document's content, which differs from
+    /// that of any other content of any document open.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Autosaves the document for recovery if it has unsaved changes, or
+    /// discards its recovery entry if it has none.
+    pub fn autosave(&mut self) -> Result<()> {
+        let document = self.document.to_value()?;
+        let hash = value_hash(&document);
+        if hash == self.saved_hash {
+            discard_recovery(&self.recovery_id)?;
+        } else {
+            write_recovery(&self.recovery_id, self.file_path.as_deref(), document)?;
+        }
+        self.autosaved_revision = self.revision;
+        Ok(())
+    }
+
+    /// Displays the page at `index`, if there is one.
+    pub fn go_to_page(&mut self, index: usize) {
+        if index < self.document.page_count() && index != self.current_page {
+            self.current_page = index;
+            self.selection = None;
+        }
+    }
+
+    /// Displays the page after the current one, if any.
+    pub fn next_page(&mut self) {
+        self.go_to_page(self.current_page + 1);
+    }
+
+    /// Displays the page before the current one, if any.
+    pub fn previous_page(&mut self) {
+        if let Some(index) = self.current_page.checked_sub(1) {
+            self.go_to_page(index);
+        }
+    }
+
+    /// Adds an empty page after the current one and displays it.
+    pub fn add_page(&mut self) -> Result<()> {
+        let page = match self.current_page() {
+            Some(current) => current.blank_like(),
+            None => bail!("No current page"),
+        };
+        self.execute(Command::InsertPage {
+            index: self.current_page + 1,
+            page,
+        })
+    }
+
+    /// Deletes the current page, displaying the one that takes its place.
+    pub fn delete_current_page(&mut self) -> Result<()> {
+        let Some(page) = self.current_page().cloned() else {
+            bail!("No current page");
+        };
+        self.execute(Command::RemovePage {
+            index: self.current_page,
+            page,
+        })
+    }
+
+    /// Adds a copy of the current page after it and displays the copy.
+    pub fn duplicate_current_page(&mut self) -> Result<()> {
+        let Some(mut copy) = self.current_page().cloned() else {
+            bail!("No current page");
+        };
+        if !copy.name.is_empty() {
+            copy.name = format!("{} copy", copy.name);
+        }
+        self.execute(Command::InsertPage {
+            index: self.current_page + 1,
+            page: copy,
+        })
+    }
+
+    /// Moves the current page to `index`, keeping it displayed.
+    pub fn move_current_page(&mut self, index: usize) -> Result<()> {
+        self.execute(Command::MovePage {
+            from: self.current_page,
+            to: index,
+        })
+    }
+
+    /// Returns the page being displayed.
+    pub fn current_page(&self) -> Option<&Page> {
+        self.document.page(self.current_page)
+    }
+
+    /// Changes the layer at `index` of the page being displayed with `edit`.
+    pub fn edit_layer(&mut self, index: usize, edit: impl FnOnce(&mut Layer)) -> Result<()> {
+        self.edit_current_page(|page| match page.layers.get_mut(index) {
+            Some(layer) => {
+                edit(layer);
+                Ok(())
+            }
+            None => bail!("No layer at position {index}"),
+        })
+    }
+
+    /// Adds an empty layer on top of the displayed page's other layers.
+    pub fn add_layer(&mut self) -> Result<()> {
+        self.edit_current_page(|page| {
+            page.add_layer();
+            Ok(())
+        })
+    }
+
+    /// Deletes the layer at `index` of the displayed page.
+    pub fn delete_layer(&mut self, index: usize) -> Result<()> {
+        self.edit_current_page(|page| page.remove_layer(index).map(drop))?;
+        self.selection = None;
+        Ok(())
+    }
+
+    /// Moves the layer at `from` of the displayed page to `to` in the
+    /// stacking order.
+    pub fn move_layer(&mut self, from: usize, to: usize) -> Result<()> {
+        self.edit_current_page(|page| page.move_layer(from, to))?;
+        self.selection = None;
+        Ok(())
+    }
+
+    /// Returns the selected shape of the displayed page, if there is one
+    /// that can still be moved: its layer may have been hidden or locked, or
+    /// the shape removed, since it was selected.
+    pub fn selection(&self) -> Option<ShapeRef> {
+        let shape = self.selection?;
+        let layer = self.current_page()?.layers.get(shape.layer)?;
+        (layer.visible && !layer.locked && shape.index < layer.shapes.len()).then_some(shape)
+    }
+
+    /// Selects `shape` of the displayed page, or nothing.
+    pub fn select(&mut self, shape: Option<ShapeRef>) {
+        self.selection = shape;
+    }
+
+    /// Moves the selected shape by (`dx`, `dy`) in the displayed page's user
+    /// coordinates.
+    pub fn move_selection(&mut self, dx: f64, dy: f64) -> Result<()> {
+        let Some(shape) = self.selection() else {
+            bail!("No shape is selected");
+        };
+        self.edit_current_page(|page| page.translate_shape(shape.layer, shape.index, dx, dy))
+    }
+
+    /// Adds the image `data` to the displayed page.
+    pub fn insert_image(&mut self, data: Vec<u8>) -> Result<()> {
+        self.insert_into_page(|document, index| document.insert_image(index, data))
+    }
+
+    /// Adds the drawing in the SVG markup `svg` to the displayed page.
+    pub fn insert_svg(&mut self, svg: &str) -> Result<()> {
+        self.insert_into_page(|document, index| document.insert_svg(index, svg))
+    }
+
+    /// Adds content to the displayed page with `insert`, given the document
+    /// and the page's index.
+    fn insert_into_page(
+        &mut self,
+        insert: impl FnOnce(&mut Document, usize) -> Result<()>,
+    ) -> Result<()> {
+        let index = self.current_page;
+        let before = self.current_page().cloned().context("No current page")?;
+        // Images are stored in the document's assets, which only hold what
+        // pages refer to once saved, so undoing needs only the page.
+        insert(&mut self.document, index)?;
+        self.revision = next_revision();
+        let after = self.current_page().cloned().context("No current page")?;
+        self.history.record(Command::EditPage {
+            index,
+            before,
+            after,
+        });
+        Ok(())
+    }
+
+    /// Changes the metadata of the document with `edit`.
+    pub fn edit_metadata(&mut self, edit: impl FnOnce(&mut Metadata)) -> Result<()> {
+        let before = self.document.metadata().clone();
+        let mut after = before.clone();
+        edit(&mut after);
+        if after == before {
+            return Ok(());
+        }
+        self.execute(Command::EditMetadata { before, after })
+    }
+
+    /// Starts a continuous gesture, such as typing into a field or dragging
+    /// a slider: the changes made until it ends are undone as one.
+    pub fn begin_gesture(&mut self) {
+        self.history.begin_gesture();
+    }
+
+    /// Ends the gesture in progress, if any.
+    pub fn end_gesture(&mut self) {
+        self.history.end_gesture();
+    }
+
+    /// Returns `true` iff there is a change to undo.
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    /// Returns `true` iff there is a change to redo.
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    /// Reverts the last change not undone yet, if any.
+    pub fn undo(&mut self) -> Result<()> {
+        match self.history.take_undo() {
+            Some(commands) => self.replay(&commands),
+            None => Ok(()),
+        }
+    }
+
+    /// Makes the last change undone again, if any.
+    pub fn redo(&mut self) -> Result<()> {
+        match self.history.take_redo() {
+            Some(commands) => self.replay(&commands),
+            None => Ok(()),
+        }
+    }
+
+    /// Applies `command` to the document, recording it for undo, and
+    /// displays the page it changes.
+    fn execute(&mut self, command: Command) -> Result<()> {
+        command.apply(&mut self.document)?;
+        self.revision = next_revision();
+        self.show_page_changed_by(&command);
+        self.history.record(command);
+        Ok(())
+    }
+
+    /// Changes the page being displayed with `edit`, recording the change
+    /// for undo.
+    fn edit_current_page(&mut self, edit: impl FnOnce(&mut Page) -> Result<()>) -> Result<()> {
+        let Some(before) = self.current_page().cloned() else {
+            bail!("No current page");
+        };
+        let mut after = before.clone();
+        edit(&mut after)?;
+        if after == before {
+            return Ok(());
+        }
+        self.execute(Command::EditPage {
+            index: self.current_page,
+            before,
+            after,
+        })
+    }
+
+    /// Applies `commands`, from the history, to the document.
+    fn replay(&mut self, commands: &[Command]) -> Result<()> {
+        for command in commands {
+            command.apply(&mut self.document)?;
+            self.revision = next_revision();
+            self.show_page_changed_by(command);
+        }
+        Ok(())
+    }
+
+    /// Displays the page `command` changed, if any.
+    fn show_page_changed_by(&mut self, command: &Command) {
+        if let Some(index) = command.page() {
+            let index = index.min(self.document.page_count() - 1);
+            if index != self.current_page {
+                self.current_page = index;
+                self.selection = None;
+            }
+        }
+    }
+
+    /// Returns the title to show for the document.
+    pub fn title(&self) -> String {
+        let file_name = self
+            .file_path
+            .as_ref()
+            .and_then(|p| p.file_stem())
+            .and_then(|n| n.to_str())
+            .unwrap_or("Untitled");
+        self.document.metadata().title_or(file_name).to_string()
+    }
+
+    /// Returns the title of the document, marked if it has unsaved changes.
+    pub fn marked_title(&self) -> String {
+        let marker = if self.is_dirty() { "• " } else { "" };
+        format!("{marker}{}", self.title())
+    }
+
+    /// Saves the document, keeping `backup_generations` backups of the
+    /// file's previous content.
+    pub fn save(&mut self, backup_generations: usize) -> Result<()> {
+        let Some(path) = self.file_path.clone() else {
+            bail!("No file path set - use Save As instead");
+        };
+        self.save_as(&path, backup_generations)
+    }
+
+    /// Saves the document in `path`, keeping `backup_generations` backups of
+    /// the file's previous content.
+    ///
+    /// Its recovery entry is left as by [`OpenDocument::mark_saved`].
+    pub fn save_as(&mut self, path: &Path, backup_generations: usize) -> Result<()> {
+        let saved = self.stamped_for_saving();
+        saved.save_to_file_with_backups(path, backup_generations)?;
+        self.file_path = Some(path.to_path_buf());
+        self.mark_saved(&saved);
+        Ok(())
+    }
+}
+
+/// Returns a revision number never returned before.
+fn next_revision() -> u64 {
+    static LAST_REVISION: AtomicU64 = AtomicU64::new(0);
+    LAST_REVISION.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Returns the hash of the stored form of `document`, to detect changes.
+fn document_hash(document: &Document) -> String {
+    document
+        .to_value()
+        .map(|value| value_hash(&value))
+        .unwrap_or_default()
+}
+
+/// Returns the hash of the JSON `value`.
+fn value_hash(value: &Value) -> String {
+    content_hash(value.to_string().as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::file_operations::user_data_directory;
+
+    #[test]
+    fn failed_save_leaves_the_document_unchanged() {
+        let mut open = OpenDocument::new(Document::new(), None, 10);
+        open.edit_metadata(|metadata| metadata.title = "Plans".into())
+            .unwrap();
+        let before = open.document.metadata().clone();
+
+        let path = std::env::temp_dir().join("pcl-demo-missing-directory/plans.json");
+        assert!(open.save_as(&path, 0).is_err());
+        assert_eq!(open.document.metadata(), &before);
+        assert!(open.is_dirty());
+        assert!(open.file_path.is_none());
+    }
+
+    #[test]
+    fn undo_keeps_the_time_of_the_last_save() {
+        let directory = std::env::temp_dir().join(format!("pcl-demo-{}", new_recovery_id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("plans.json");
+
+        let mut open = OpenDocument::new(Document::new(), None, 10);
+        open.edit_metadata(|metadata| metadata.title = "Plans".into())
+            .unwrap();
+        open.save_as(&path, 0).unwrap();
+        let saved = open.document.metadata().modified;
+        assert!(!open.is_dirty());
+
+        open.undo().unwrap();
+        assert_eq!(open.document.metadata().title, "");
+        assert_eq!(open.document.metadata().modified, saved);
+        open.redo().unwrap();
+        assert!(!open.is_dirty());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn changes_need_autosaving_until_autosaved() {
+        let mut open = OpenDocument::new(Document::new(), None, 10);
+        assert!(!open.needs_autosave());
+        open.begin_gesture();
+        open.edit_metadata(|metadata| metadata.title = "P".into())
+            .unwrap();
+        open.autosave().unwrap();
+        assert!(!open.needs_autosave());
+        open.edit_metadata(|metadata| metadata.title = "Plans".into())
+            .unwrap();
+        assert!(open.needs_autosave());
+        open.undo().unwrap();
+        assert!(open.needs_autosave());
+        open.autosave().unwrap();
+        assert!(!open.needs_autosave());
+
+        let directory = user_data_directory();
+        assert!(directory.join("recovery").is_dir());
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+//! The pages of a document.
+
+use crate::layer::{layer_id, Layer};
+use crate::shape::{Attributes, Drawing, Shape, ViewBox};
+use anyhow::{bail, ensure, Result};
+use serde::{Deserialize, Serialize};
+
+/// One page of a document, with its own coordinate system, size and layers.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Page {
+    /// The page's name (empty to be labeled by its position).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+
+    /// The user coordinate system, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub view_box: Option<ViewBox>,
+
+    /// The rendered width, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<f64>,
+
+    /// The rendered height, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<f64>,
+
+    /// Attributes of the page's `<svg>` element the model doesn't interpret.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Attributes,
+
+    /// The order the attributes of the page's `<svg>` element were written in
+    /// (see [`Drawing::attribute_order`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attribute_order: Vec<String>,
+
+    /// The comments and processing instructions before the page's `<svg>` element.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prolog: Vec<Shape>,
+
+    /// The page's layers, back to front (never empty).
+    pub layers: Vec<Layer>,
+}
+
+impl Page {
+    /// Returns an unnamed page showing `drawing`.
+    ///
+    /// Top-level groups marked as layers (as written by [`Page::to_drawing`])
+    /// become layers; any other shapes are collected into unnamed layers.
+    pub fn from_drawing(drawing: Drawing) -> Self {
+        let mut layers: Vec<Layer> = Vec::new();
+        let mut loose: Option<Layer> = None;
+        for shape in drawing.shapes {
+            if let Some(layer) = Layer::from_group(&shape) {
+                layers.extend(loose.take());
+                layers.push(layer);
+            } else {
+                loose
+                    .get_or_insert_with(|| Layer::new(String::new()))
+                    .shapes
+                    .push(shape);
+            }
+        }
+        layers.extend(loose);
+
+        let mut page = Self {
+            name: String::new(),
+            view_box: drawing.view_box,
+            width: drawing.width,
+            height: drawing.height,
+            attributes: drawing.attributes,
+            attribute_order: drawing.attribute_order,
+            prolog: drawing.prolog,
+            layers: Vec::new(),
+        };
+        for mut layer in layers {
+            if layer.name.trim().is_empty() {
+                layer.name = page.new_layer_name();
+            }
+            page.layers.push(layer);
+        }
+        if page.layers.is_empty() {
+            page.layers.push(Layer::new(page.new_layer_name()));
+        }
+        page
+    }
+
+    /// Returns the plain SVG drawing of `self`, where each layer is a `<g>`
+    /// with a readable id.
+    pub fn to_drawing(&self) -> Drawing {
+        self.drawing_of(self.layers.iter(), Layer::to_group)
+    }
+
+    /// Returns `self` as a drawing for a standalone SVG file: its visible
+    /// layers only, without the attributes only this application reads.
+    pub fn to_plain_drawing(&self) -> Drawing {
+        self.drawing_of(
+            self.layers.iter().filter(|layer| layer.visible),
+            Layer::to_plain_group,
+        )
+    }
+
+    /// Returns a drawing of `layers` in the coordinate system of `self`, each
+    /// a `<g>` made by `to_group` with an id unique among them.
+    fn drawing_of<'a>(
+        &self,
+        layers: impl Iterator<Item = &'a Layer>,
+        to_group: impl Fn(&Layer, &str) -> Shape,
+    ) -> Drawing {
+        let mut ids: Vec<String> = Vec::new();
+        let shapes: Vec<Shape> = layers
+            .map(|layer| {
+                let base = layer_id(&layer.name);
+                let mut id = base.clone();
+                let mut suffix = 2;
+                while ids.contains(&id) {
+                    id = format!("{base}-{suffix}");
+                    suffix += 1;
+                }
+                let group = to_group(layer, &id);
+                ids.push(id);
+                group
+            })
+            .collect();
+
+        Drawing {
+            view_box: self.view_box,
+            width: self.width,
+            height: self.height,
+            attributes: self.attributes.clone(),
+            attribute_order: self.attribute_order.clone(),
+            prolog: self.prolog.clone(),
+            shapes,
+        }
+    }
+
+    /// Returns an empty page with the same coordinate system and size as `self`.
+    pub fn blank_like(&self) -> Self {
+        Self::from_drawing(Drawing {
+            view_box: self.view_box,
+            width: self.width,
+            height: self.height,
+            ..Drawing::default()
+        })
+    }
+
+    /// Returns the label for `self` when it is at `index` in its document.
+    pub fn label(&self, index: usize) -> String {
+        if self.name.trim().is_empty() {
+            format!("Page {}", index + 1)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// Returns a name for a new layer that no layer of `self` has yet.
+    fn new_layer_name(&self) -> String {
+        (1..)
+            .map(|n| format!("Layer {n}"))
+            .find(|name| self.layers.iter().all(|layer| layer.name != *name))
+            .expect("Some layer number is unused")
+    }
+
+    /// Adds an empty layer on top of the others, returning its index.
+    pub fn add_layer(&mut self) -> usize {
+        self.layers.push(Layer::new(self.new_layer_name()));
+        self.layers.len() - 1
+    }
+
+    /// Removes and returns the layer at `index`; the last layer and locked
+    /// layers can't be removed.
+    pub fn remove_layer(&mut self, index: usize) -> Result<Layer> {
+        let Some(layer) = self.layers.get(index) else {
+            bail!("No layer at position {index}");
+        };
+        ensure!(!layer.locked, "Layer \"{}\" is locked", layer.name);
+        if self.layers.len() == 1 {
+            bail!("A page must have at least one layer");
+        }
+        Ok(self.layers.remove(index))
+    }
+
+    /// Moves the layer at `from` so that it is at `to` in the stacking order.
+    pub fn move_layer(&mut self, from: usize, to: usize) -> Result<()> {
+        ensure!(
+            from < self.layers.len() && to < self.layers.len(),
+            "No layer at position {}",
+            from.max(to)
+        );
+        let layer = self.layers.remove(from);
+        self.layers.insert(to, layer);
+        Ok(())
+    }
+
+    /// Moves the shape at `index` in the layer at `layer` by (`dx`, `dy`) in
+    /// the page's user coordinates; shapes of locked layers can't be moved.
+    pub fn translate_shape(&mut self, layer: usize, index: usize, dx: f64, dy: f64) -> Result<()> {
+        let Some(layer) = self.layers.get_mut(layer) else {
+            bail!("No layer at position {layer}");
+        };
+        ensure!(!layer.locked, "Layer \"{}\" is locked", layer.name);
+        let Some(shape) = layer.shapes.get_mut(index) else {
+            bail!("No shape at position {index} in layer \"{}\"", layer.name);
+        };
+        shape.translate(dx, dy);
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use crate::application_state::ApplicationState;
+use crate::notifications::report_failure;
+use dioxus::prelude::*;
+
+/// Navigation between the pages of the current document: a thumbnail strip
+/// with previous/next controls and page operations.
+#[component]
+pub fn PageNavigator(application_state: Signal<ApplicationState>) -> Element {
+    let mut state = application_state;
+    let (thumbnails, current, count) = {
+        let current_state = state.read();
+        let open = current_state.current();
+        let document = &open.document;
+        let thumbnails: Vec<(String, String)> = document
+            .pages()
+            .iter()
+            .enumerate()
+            .map(|(index, page)| (page.label(index), document.page_image_url(index)))
+            .collect();
+        (thumbnails, open.current_page, document.page_count())
+    };
+
+    let report = move |result| report_failure(state, "Page operation failed", result);
+
+    rsx! {
+        div {
+            class: "page-navigator",
+            div {
+                class: "page-controls",
+                button {
+                    class: "page-button",
+                    title: "Previous page",
+                    disabled: current == 0,
+                    onclick: move |_| state.write().current_mut().previous_page(),
+                    "◀"
+                }
+                span { class: "page-position", "Page {current + 1} of {count}" }
+                button {
+                    class: "page-button",
+                    title: "Next page",
+                    disabled: current + 1 >= count,
+                    onclick: move |_| state.write().current_mut().next_page(),
+                    "▶"
+                }
+                span { class: "page-separator" }
+                button {
+                    class: "page-button",
+                    title: "Add page",
+                    onclick: move |_| report(state.write().current_mut().add_page()),
+                    "＋"
+                }
+                button {
+                    class: "page-button",
+                    title: "Duplicate page",
+                    onclick: move |_| report(state.write().current_mut().duplicate_current_page()),
+                    "⧉"
+                }
+                button {
+                    class: "page-button",
+                    title: "Delete page",
+                    disabled: count <= 1,
+                    onclick: move |_| report(state.write().current_mut().delete_current_page()),
+                    "🗑"
+                }
+                button {
+                    class: "page-button",
+                    title: "Move page earlier",
+                    disabled: current == 0,
+                    onclick: move |_| {
+                        report(state.write().current_mut().move_current_page(current.saturating_sub(1)))
+                    },
+                    "⇤"
+                }
+                button {
+                    class: "page-button",
+                    title: "Move page later",
+                    disabled: current + 1 >= count,
+                    onclick: move |_| report(state.write().current_mut().move_current_page(current + 1)),
+                    "⇥"
+                }
+            }
+            div {
+                class: "page-thumbnails",
+                for (index, (label, url)) in thumbnails.into_iter().enumerate() {
+                    button {
+                        key: "{index}",
+                        class: if index == current {
+                            "page-thumbnail page-thumbnail-current"
+                        } else {
+                            "page-thumbnail"
+                        },
+                        title: "{label}",
+                        onclick: move |_| state.write().current_mut().go_to_page(index),
+                        img { class: "page-thumbnail-image", src: "{url}", alt: "" }
+                        div { class: "page-thumbnail-label", "{label}" }
+                    }
+                }
+            }
+        }
+    }
+}
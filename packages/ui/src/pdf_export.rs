@@ -0,0 +1,764 @@
+//! Exporting a document as a vector PDF, with a PDF page for each page.
+//!
+//! svg2pdf draws the shapes and images of a page as PDF vectors, and its
+//! text is written as real PDF text in between, in the order the page draws
+//! them, so that it can be selected and searched. The system fonts the text
+//! is set in are embedded, along with fonts having the characters they lack.
+
+use crate::document::Document;
+use crate::export::system_fonts;
+use anyhow::{anyhow, bail, Context, Result};
+use pdf_writer::types::{CidFontType, FontFlags, SystemInfo, TextRenderingMode, UnicodeCmap};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use resvg::tiny_skia::Transform;
+use resvg::usvg::{
+    self, fontdb, utils::view_box_to_transform, FontStyle, Group, Node, Paint, TextAnchor,
+    TreeParsing, TreePostProc,
+};
+use std::collections::BTreeMap;
+
+/// Points per CSS pixel.
+const POINTS_PER_PIXEL: f32 = 0.75;
+
+/// Points per millimeter.
+const POINTS_PER_MILLIMETER: f32 = 72.0 / 25.4;
+
+/// The size of a PDF page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageSize {
+    /// ISO A4, 210 × 297 mm.
+    A4,
+
+    /// US Letter, 8.5 × 11 in.
+    Letter,
+
+    /// The size of the content, plus the margins.
+    Fit,
+}
+
+impl PageSize {
+    /// All page sizes, in the order they're offered.
+    pub const ALL: [PageSize; 3] = [PageSize::A4, PageSize::Letter, PageSize::Fit];
+
+    /// Returns the name of the page size for display.
+    pub fn description(self) -> &'static str {
+        match self {
+            PageSize::A4 => "A4",
+            PageSize::Letter => "Letter",
+            PageSize::Fit => "Fit to content",
+        }
+    }
+
+    /// Returns the width and height of a portrait page of this size in
+    /// points, unless it fits the content.
+    fn portrait_points(self) -> Option<(f32, f32)> {
+        match self {
+            PageSize::A4 => Some((210.0 * POINTS_PER_MILLIMETER, 297.0 * POINTS_PER_MILLIMETER)),
+            PageSize::Letter => Some((612.0, 792.0)),
+            PageSize::Fit => None,
+        }
+    }
+}
+
+/// Which way up a PDF page is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    /// Taller than wide.
+    Portrait,
+
+    /// Wider than tall.
+    Landscape,
+}
+
+/// How a page is exported as PDF.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfOptions {
+    /// The size of the PDF page.
+    pub page_size: PageSize,
+
+    /// Which way up the PDF page is, unless it fits the content.
+    pub orientation: Orientation,
+
+    /// The space left blank around the content, in millimeters.
+    pub margin: f32,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::A4,
+            orientation: Orientation::Portrait,
+            margin: 10.0,
+        }
+    }
+}
+
+/// Returns `document` as a PDF, with a PDF page for each of its pages.
+///
+/// The content of each page is centered on its PDF page, shrunk if needed to
+/// fit inside the margins.
+pub fn export_pdf(document: &Document, options: &PdfOptions) -> Result<Vec<u8>> {
+    if options.margin.is_nan() || options.margin < 0.0 {
+        bail!("The margins can't be negative");
+    }
+
+    let mut pdf = Pdf::new();
+    let mut next_id = Ref::new(1);
+    let catalog_id = next_id.bump();
+    let page_tree_id = next_id.bump();
+    let info_id = next_id.bump();
+    let mut fonts = Fonts::new(system_fonts());
+    let mut page_ids = Vec::new();
+    for index in 0..document.page_count() {
+        let page = PdfPage {
+            html: document.page_html(index),
+            parent: page_tree_id,
+        };
+        let page_id = page
+            .write(&mut pdf, &mut next_id, &mut fonts, options)
+            .with_context(|| format!("Failed to export page {}", index + 1))?;
+        page_ids.push(page_id);
+    }
+    fonts.write(&mut pdf, &mut next_id)?;
+
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id)
+        .count(page_ids.len() as i32)
+        .kids(page_ids);
+
+    let metadata = document.metadata();
+    let mut info = pdf.document_info(info_id);
+    if !metadata.title.is_empty() {
+        info.title(TextStr(&metadata.title));
+    }
+    if !metadata.author.is_empty() {
+        info.author(TextStr(&metadata.author));
+    }
+    info.finish();
+
+    Ok(pdf.finish())
+}
+
+/// A page of the document, to write as a PDF page.
+struct PdfPage {
+    /// The markup of the page.
+    html: String,
+
+    /// The id of the page tree the PDF page belongs to.
+    parent: Ref,
+}
+
+impl PdfPage {
+    /// Writes the page to `pdf`, numbering objects from `next_id`, and
+    /// returns the id of the PDF page.
+    fn write(
+        &self,
+        pdf: &mut Pdf,
+        next_id: &mut Ref,
+        fonts: &mut Fonts,
+        options: &PdfOptions,
+    ) -> Result<Ref> {
+        let mut tree = usvg::Tree::from_str(&self.html, &usvg::Options::default())
+            .context("Failed to parse the page")?;
+        // What's drawn before, between and after the texts, each drawn by
+        // svg2pdf as a form of its own so that the texts go in between.
+        let mut parts = Vec::new();
+        for part in 0..=count_text(&tree.root) {
+            let mut part_tree = tree.clone();
+            keep_part(&mut part_tree.root, part, &mut 0);
+            part_tree.postprocess(usvg::PostProcessingSteps::default(), fonts.database);
+            parts.push(part_tree);
+        }
+        tree.postprocess(
+            usvg::PostProcessingSteps {
+                convert_text_into_paths: false,
+            },
+            fonts.database,
+        );
+
+        // Lay the content out on the page.
+        let margin = options.margin * POINTS_PER_MILLIMETER;
+        let content_width = tree.size.width() * POINTS_PER_PIXEL;
+        let content_height = tree.size.height() * POINTS_PER_PIXEL;
+        let (page_width, page_height) = match options.page_size.portrait_points() {
+            None => (content_width + 2.0 * margin, content_height + 2.0 * margin),
+            Some((width, height)) => match options.orientation {
+                Orientation::Portrait => (width, height),
+                Orientation::Landscape => (height, width),
+            },
+        };
+        let available_width = page_width - 2.0 * margin;
+        let available_height = page_height - 2.0 * margin;
+        if available_width <= 0.0 || available_height <= 0.0 {
+            bail!("The margins leave no room on the page");
+        }
+        let fit = (available_width / content_width)
+            .min(available_height / content_height)
+            .min(1.0);
+        let (width, height) = (content_width * fit, content_height * fit);
+        let left = margin + (available_width - width) / 2.0;
+        let bottom = margin + (available_height - height) / 2.0;
+
+        // The text, from pixels with y down to points with y up.
+        let to_page = Transform::from_row(
+            POINTS_PER_PIXEL * fit,
+            0.0,
+            0.0,
+            -POINTS_PER_PIXEL * fit,
+            left,
+            bottom + height,
+        )
+        .pre_concat(view_box_to_transform(
+            tree.view_box.rect,
+            tree.view_box.aspect,
+            tree.size,
+        ));
+        let mut texts = Vec::new();
+        collect_text(&tree.root, &mut texts);
+
+        let page_id = next_id.bump();
+        let content_id = next_id.bump();
+        let mut content = Content::new();
+        let mut forms = Vec::new();
+        for (number, part) in parts.iter().enumerate() {
+            if part.root.has_children() {
+                // The shapes and images, as a form scaled to the unit square.
+                let form_id = next_id.bump();
+                *next_id =
+                    svg2pdf::convert_tree_into(part, svg2pdf::Options::default(), pdf, form_id);
+                let name = format!("S{}", number + 1);
+                content
+                    .save_state()
+                    .transform([width, 0.0, 0.0, height, left, bottom])
+                    .x_object(Name(name.as_bytes()))
+                    .restore_state();
+                forms.push((name, form_id));
+            }
+            if let Some(text) = texts.get(number) {
+                write_text(text, to_page, &mut content, fonts, next_id)?;
+            }
+        }
+        pdf.stream(content_id, &content.finish());
+
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, page_width, page_height))
+            .parent(self.parent)
+            .contents(content_id);
+        let mut resources = page.resources();
+        let mut x_objects = resources.x_objects();
+        for (name, form_id) in &forms {
+            x_objects.pair(Name(name.as_bytes()), *form_id);
+        }
+        x_objects.finish();
+        let mut font_dict = resources.fonts();
+        for font in &fonts.embedded {
+            font_dict.pair(Name(font.name.as_bytes()), font.id);
+        }
+        Ok(page_id)
+    }
+}
+
+/// Returns how many texts `group` draws.
+fn count_text(group: &Group) -> usize {
+    group
+        .children
+        .iter()
+        .map(|node| match node {
+            Node::Group(group) => count_text(group),
+            Node::Text(_) => 1,
+            Node::Path(_) | Node::Image(_) => 0,
+        })
+        .sum()
+}
+
+/// Removes the texts from `group`, along with the shapes and images not
+/// drawn between text `part - 1` and text `part`, where `seen` counts the
+/// texts passed.
+fn keep_part(group: &mut Group, part: usize, seen: &mut usize) {
+    group.children.retain_mut(|node| match node {
+        Node::Group(group) => {
+            keep_part(group, part, seen);
+            group.has_children()
+        }
+        Node::Text(_) => {
+            *seen += 1;
+            false
+        }
+        Node::Path(_) | Node::Image(_) => *seen == part,
+    });
+}
+
+/// Adds the texts `group` draws to `texts`, in the order it draws them.
+fn collect_text<'a>(group: &'a Group, texts: &mut Vec<&'a usvg::Text>) {
+    for node in &group.children {
+        match node {
+            Node::Group(group) => collect_text(group, texts),
+            Node::Text(text) => texts.push(text),
+            Node::Path(_) | Node::Image(_) => {}
+        }
+    }
+}
+
+/// Characters of a text chunk set in the same span and font.
+struct Run<'a> {
+    /// The span of the characters.
+    span: &'a usvg::TextSpan,
+
+    /// The index of the font in [`Fonts::embedded`].
+    font: usize,
+
+    /// The glyph of each character, with the space added after it in pixels.
+    glyphs: Vec<(u16, f32)>,
+
+    /// How far the characters advance, in pixels.
+    advance: f32,
+}
+
+/// Writes `text` to `content`, placed on the PDF page by `to_page`, in the
+/// fonts `fonts` embeds.
+fn write_text(
+    text: &usvg::Text,
+    to_page: Transform,
+    content: &mut Content,
+    fonts: &mut Fonts,
+    next_id: &mut Ref,
+) -> Result<()> {
+    let transform = to_page.pre_concat(text.abs_transform);
+    let (mut x, mut y) = (0.0, 0.0);
+    for chunk in &text.chunks {
+        x = chunk.x.unwrap_or(x);
+        y = chunk.y.unwrap_or(y);
+
+        let mut runs: Vec<Run> = Vec::new();
+        for span in &chunk.spans {
+            let Some(text) = chunk.text.get(span.start..span.end) else {
+                continue;
+            };
+            let size = span.font_size.get();
+            let primary = fonts.primary(&span.font, text, next_id)?;
+            for c in text.chars() {
+                let (font, glyph) = fonts.glyph(primary, c, next_id)?;
+                let spacing = span.letter_spacing + if c == ' ' { span.word_spacing } else { 0.0 };
+                let advance = fonts.embedded[font].width(glyph) * size / 1000.0 + spacing;
+                match runs.last_mut() {
+                    Some(run) if run.font == font && std::ptr::eq(run.span, span) => {
+                        run.glyphs.push((glyph, spacing));
+                        run.advance += advance;
+                    }
+                    _ => runs.push(Run {
+                        span,
+                        font,
+                        glyphs: vec![(glyph, spacing)],
+                        advance,
+                    }),
+                }
+            }
+        }
+
+        let advance: f32 = runs.iter().map(|run| run.advance).sum();
+        x -= match chunk.anchor {
+            TextAnchor::Start => 0.0,
+            TextAnchor::Middle => advance / 2.0,
+            TextAnchor::End => advance,
+        };
+        for run in runs {
+            // Glyphs are drawn with y up, so flip them back.
+            let matrix = transform.pre_translate(x, y).pre_scale(1.0, -1.0);
+            let size = run.span.font_size.get();
+            content.begin_text();
+            match run.span.fill.as_ref().map(|fill| &fill.paint) {
+                Some(Paint::Color(color)) => {
+                    content.set_fill_rgb(
+                        f32::from(color.red) / 255.0,
+                        f32::from(color.green) / 255.0,
+                        f32::from(color.blue) / 255.0,
+                    );
+                }
+                Some(_) => {
+                    content.set_fill_gray(0.0);
+                }
+                // Unfilled text is still there to select and search.
+                None => {
+                    content.set_text_rendering_mode(TextRenderingMode::Invisible);
+                }
+            }
+            content
+                .set_font(Name(fonts.embedded[run.font].name.as_bytes()), size)
+                .set_text_matrix([
+                    matrix.sx, matrix.ky, matrix.kx, matrix.sy, matrix.tx, matrix.ty,
+                ]);
+            let mut shown = content.show_positioned();
+            let mut items = shown.items();
+            for (glyph, spacing) in run.glyphs {
+                items.show(Str(&glyph.to_be_bytes()));
+                if spacing != 0.0 {
+                    items.adjust(-spacing * 1000.0 / size);
+                }
+            }
+            items.finish();
+            shown.finish();
+            content.end_text();
+            x += run.advance;
+        }
+    }
+    Ok(())
+}
+
+/// The fonts text is set in, embedded in the PDF.
+struct Fonts<'a> {
+    /// The fonts to choose from.
+    database: &'a fontdb::Database,
+
+    /// The fonts used so far.
+    embedded: Vec<EmbeddedFont>,
+
+    /// The font and glyph found for each character lacking from the font
+    /// chosen for it, unless no font has it.
+    fallbacks: BTreeMap<char, Option<(usize, u16)>>,
+}
+
+impl<'a> Fonts<'a> {
+    /// Returns an empty set of fonts chosen from `database`.
+    fn new(database: &'a fontdb::Database) -> Self {
+        Self {
+            database,
+            embedded: Vec::new(),
+            fallbacks: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the index of the font to set `text` in, given its `font`,
+    /// embedding it with the ids from `next_id` if it isn't yet.
+    fn primary(&mut self, font: &usvg::Font, text: &str, next_id: &mut Ref) -> Result<usize> {
+        let families: Vec<fontdb::Family> = font
+            .families
+            .iter()
+            .map(|family| match family.as_str() {
+                "serif" => fontdb::Family::Serif,
+                "sans-serif" => fontdb::Family::SansSerif,
+                "monospace" => fontdb::Family::Monospace,
+                "cursive" => fontdb::Family::Cursive,
+                "fantasy" => fontdb::Family::Fantasy,
+                name => fontdb::Family::Name(name),
+            })
+            .chain([fontdb::Family::SansSerif, fontdb::Family::Serif])
+            .collect();
+        let query = fontdb::Query {
+            families: &families,
+            weight: fontdb::Weight(font.weight),
+            stretch: fontdb::Stretch::Normal,
+            style: match font.style {
+                FontStyle::Normal => fontdb::Style::Normal,
+                FontStyle::Italic => fontdb::Style::Italic,
+                FontStyle::Oblique => fontdb::Style::Oblique,
+            },
+        };
+        // Failing that, a font with the first character, or any font.
+        let first = text.chars().next().unwrap_or(' ');
+        let id = self
+            .database
+            .query(&query)
+            .or_else(|| self.with_glyph(first))
+            .or_else(|| self.database.faces().next().map(|face| face.id))
+            .ok_or_else(|| anyhow!("No fonts are available to set the text \"{text}\" in"))?;
+        self.embed(id, next_id)
+    }
+
+    /// Returns the index of the font to draw `c` in, preferring `primary`,
+    /// and its glyph, noting that it's drawn; `primary` and its glyph for
+    /// missing characters if no font has `c`.
+    fn glyph(&mut self, primary: usize, c: char, next_id: &mut Ref) -> Result<(usize, u16)> {
+        let found = match self.embedded[primary].glyph(c) {
+            Some(glyph) => Some((primary, glyph)),
+            None => match self.fallbacks.get(&c) {
+                Some(&found) => found,
+                None => {
+                    let found = match self.with_glyph(c) {
+                        Some(id) => {
+                            let font = self.embed(id, next_id)?;
+                            self.embedded[font].glyph(c).map(|glyph| (font, glyph))
+                        }
+                        None => None,
+                    };
+                    self.fallbacks.insert(c, found);
+                    found
+                }
+            },
+        };
+        let (font, glyph) = found.unwrap_or((primary, 0));
+        self.embedded[font].used.insert(glyph, c);
+        Ok((font, glyph))
+    }
+
+    /// Returns the first font having `c`, among those embedded and then all.
+    fn with_glyph(&self, c: char) -> Option<fontdb::ID> {
+        let has_glyph = |id| {
+            self.database
+                .with_face_data(id, |data, index| {
+                    ttf_parser::Face::parse(data, index)
+                        .is_ok_and(|face| face.glyph_index(c).is_some())
+                })
+                .unwrap_or(false)
+        };
+        self.embedded
+            .iter()
+            .map(|font| font.face)
+            .chain(self.database.faces().map(|face| face.id))
+            .find(|&id| has_glyph(id))
+    }
+
+    /// Returns the index of the font `id`, embedding it with the ids from
+    /// `next_id` if it isn't yet.
+    fn embed(&mut self, id: fontdb::ID, next_id: &mut Ref) -> Result<usize> {
+        if let Some(index) = self.embedded.iter().position(|font| font.face == id) {
+            return Ok(index);
+        }
+        let data = self
+            .database
+            .with_face_data(id, single_font)
+            .ok_or_else(|| anyhow!("A font couldn't be read"))?
+            .context("A font couldn't be read")?;
+        ttf_parser::Face::parse(&data, 0).context("A font couldn't be read")?;
+        self.embedded.push(EmbeddedFont {
+            face: id,
+            name: format!("F{}", self.embedded.len() + 1),
+            id: next_id.bump(),
+            data,
+            glyphs: BTreeMap::new(),
+            used: BTreeMap::new(),
+        });
+        Ok(self.embedded.len() - 1)
+    }
+
+    /// Writes the fonts to `pdf`, numbering objects from `next_id`.
+    fn write(&self, pdf: &mut Pdf, next_id: &mut Ref) -> Result<()> {
+        for font in &self.embedded {
+            font.write(pdf, next_id)?;
+        }
+        Ok(())
+    }
+}
+
+/// A font embedded in the PDF.
+struct EmbeddedFont {
+    /// The font in the database.
+    face: fontdb::ID,
+
+    /// The name pages refer to the font by.
+    name: String,
+
+    /// The id of the font's object.
+    id: Ref,
+
+    /// The font file, holding this font alone.
+    data: Vec<u8>,
+
+    /// The glyph of each character looked up, if the font has it.
+    glyphs: BTreeMap<char, Option<u16>>,
+
+    /// The character each glyph drawn stands for.
+    used: BTreeMap<u16, char>,
+}
+
+impl EmbeddedFont {
+    /// Returns the font parsed.
+    fn face(&self) -> Option<ttf_parser::Face<'_>> {
+        ttf_parser::Face::parse(&self.data, 0).ok()
+    }
+
+    /// Returns the glyph of `c`, if the font has it.
+    fn glyph(&mut self, c: char) -> Option<u16> {
+        if let Some(&glyph) = self.glyphs.get(&c) {
+            return glyph;
+        }
+        let glyph = self.face()?.glyph_index(c).map(|glyph| glyph.0);
+        self.glyphs.insert(c, glyph);
+        glyph
+    }
+
+    /// Returns how far `glyph` advances, in thousandths of the font size.
+    fn width(&self, glyph: u16) -> f32 {
+        self.face().map_or(0.0, |face| {
+            let advance = face.glyph_hor_advance(ttf_parser::GlyphId(glyph));
+            f32::from(advance.unwrap_or(0)) * 1000.0 / f32::from(face.units_per_em())
+        })
+    }
+
+    /// Writes the font to `pdf` as a CID font addressed by glyph, numbering
+    /// the objects besides [`EmbeddedFont::id`] from `next_id`.
+    fn write(&self, pdf: &mut Pdf, next_id: &mut Ref) -> Result<()> {
+        let face = self.face().context("A font couldn't be read")?;
+        let scale = 1000.0 / f32::from(face.units_per_em());
+        let thousandths = |value: i16| f32::from(value) * scale;
+        let base_font: String = face
+            .names()
+            .into_iter()
+            .filter(|name| name.name_id == ttf_parser::name_id::POST_SCRIPT_NAME)
+            .find_map(|name| name.to_string())
+            .unwrap_or_else(|| self.name.clone())
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+            .collect();
+        let base_font = Name(base_font.as_bytes());
+        let system_info = || SystemInfo {
+            registry: Str(b"Adobe"),
+            ordering: Str(b"Identity"),
+            supplement: 0,
+        };
+        let cff = face.tables().cff.is_some();
+        let cid_font_id = next_id.bump();
+        let descriptor_id = next_id.bump();
+        let file_id = next_id.bump();
+        let cmap_id = next_id.bump();
+
+        pdf.type0_font(self.id)
+            .base_font(base_font)
+            .encoding_predefined(Name(b"Identity-H"))
+            .descendant_font(cid_font_id)
+            .to_unicode(cmap_id);
+
+        let mut cid_font = pdf.cid_font(cid_font_id);
+        cid_font
+            .subtype(if cff {
+                CidFontType::Type0
+            } else {
+                CidFontType::Type2
+            })
+            .base_font(base_font)
+            .system_info(system_info())
+            .font_descriptor(descriptor_id)
+            .default_width(0.0);
+        if !cff {
+            cid_font.cid_to_gid_map_predefined(Name(b"Identity"));
+        }
+        let mut widths = cid_font.widths();
+        for &glyph in self.used.keys() {
+            widths.consecutive(glyph, [self.width(glyph)]);
+        }
+        widths.finish();
+        cid_font.finish();
+
+        let mut flags = FontFlags::SYMBOLIC;
+        flags.set(FontFlags::FIXED_PITCH, face.is_monospaced());
+        flags.set(FontFlags::ITALIC, face.is_italic());
+        let bbox = face.global_bounding_box();
+        let mut descriptor = pdf.font_descriptor(descriptor_id);
+        descriptor
+            .name(base_font)
+            .flags(flags)
+            .bbox(Rect::new(
+                thousandths(bbox.x_min),
+                thousandths(bbox.y_min),
+                thousandths(bbox.x_max),
+                thousandths(bbox.y_max),
+            ))
+            .italic_angle(face.italic_angle().unwrap_or(0.0))
+            .ascent(thousandths(face.ascender()))
+            .descent(thousandths(face.descender()))
+            .cap_height(thousandths(
+                face.capital_height().unwrap_or(face.ascender()),
+            ))
+            .stem_v(10.0 + 0.244 * (f32::from(face.weight().to_number()) - 50.0));
+        if cff {
+            descriptor.font_file3(file_id);
+        } else {
+            descriptor.font_file2(file_id);
+        }
+        descriptor.finish();
+
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&self.data, 6);
+        let mut file = pdf.stream(file_id, &compressed);
+        file.filter(Filter::FlateDecode);
+        if cff {
+            file.pair(Name(b"Subtype"), Name(b"OpenType"));
+        } else {
+            file.pair(Name(b"Length1"), self.data.len() as i32);
+        }
+        file.finish();
+
+        let mut cmap = UnicodeCmap::new(Name(b"Custom"), system_info());
+        for (&glyph, &c) in &self.used {
+            cmap.pair(glyph, c);
+        }
+        pdf.cmap(cmap_id, &cmap.finish());
+        Ok(())
+    }
+}
+
+/// Returns the font at `index` in the font file `data`, taken out of the
+/// collection if `data` is one, since a PDF embeds fonts one by one.
+fn single_font(data: &[u8], index: u32) -> Result<Vec<u8>> {
+    if !data.starts_with(b"ttcf") {
+        return Ok(data.to_vec());
+    }
+    let bytes = |at: usize, length: usize| {
+        data.get(at..at + length)
+            .ok_or_else(|| anyhow!("The font collection is cut short"))
+    };
+    let number = |at: usize| -> Result<usize> {
+        let b = bytes(at, 4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+    };
+
+    let offset = number(12 + 4 * index as usize)?;
+    let header = bytes(offset, 12)?;
+    let table_count = usize::from(u16::from_be_bytes([header[4], header[5]]));
+    let tables_start = 12 + 16 * table_count;
+    let mut font = header.to_vec();
+    let mut tables = Vec::new();
+    for table in 0..table_count {
+        let record = offset + 12 + 16 * table;
+        let (table_offset, length) = (number(record + 8)?, number(record + 12)?);
+        // The tag and checksum, then where the table is now.
+        font.extend_from_slice(bytes(record, 8)?);
+        font.extend_from_slice(&((tables_start + tables.len()) as u32).to_be_bytes());
+        font.extend_from_slice(&(length as u32).to_be_bytes());
+        tables.extend_from_slice(bytes(table_offset, length)?);
+        tables.resize(tables.len().next_multiple_of(4), 0);
+    }
+    font.extend(tables);
+    Ok(font)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the number of times `part` occurs in `pdf`.
+    fn occurrences(pdf: &[u8], part: &[u8]) -> usize {
+        pdf.windows(part.len())
+            .filter(|window| *window == part)
+            .count()
+    }
+
+    #[test]
+    fn every_page_is_exported_with_its_text() {
+        let mut document = Document::from_html(
+            r#"<svg viewBox="0 0 100 50" xmlns="http://www.w3.org/2000/svg">
+<text x="10" y="20" font-size="10">Hi</text>
+<rect x="0" y="0" width="100" height="50" fill="blue"/>
+</svg>"#,
+        )
+        .unwrap();
+        document.duplicate_page(0).unwrap();
+        let pdf = export_pdf(&document, &PdfOptions::default()).unwrap();
+
+        assert_eq!(occurrences(&pdf, b"/Type /Page\n"), 2);
+        assert_eq!(occurrences(&pdf, b"/Count 2"), 1);
+        assert_eq!(occurrences(&pdf, b"/Subtype /Type0"), 1);
+        // The text is mapped back to its characters.
+        assert_eq!(occurrences(&pdf, b"<0048>"), 1);
+        assert_eq!(occurrences(&pdf, b"<0069>"), 1);
+        // The rectangle drawn over the text comes after it.
+        let content = pdf.windows(3).position(|w| w == b" TJ").unwrap();
+        let form = pdf.windows(6).position(|w| w == b"/S2 Do").unwrap();
+        assert!(content < form);
+        assert_eq!(occurrences(&pdf, b"/S1 Do"), 0);
+    }
+
+    #[test]
+    fn collections_are_split_into_single_fonts() {
+        assert_eq!(single_font(b"\0\x01\0\0", 0).unwrap(), b"\0\x01\0\0");
+        assert!(single_font(b"ttcf\0\x01\0\0\0\0\0\x01", 0).is_err());
+    }
+}
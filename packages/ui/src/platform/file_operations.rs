@@ -7,28 +7,30 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 // Web API imports (available on all platforms for development ease)
-use wasm_bindgen::prelude::JsValue;
 use wasm_bindgen::JsCast;
-use web_sys::{window, Blob, Element, HtmlAnchorElement, Url};
+use web_sys::{window, Blob, Element, HtmlAnchorElement, Storage, Url};
 
 // Other imports
+use crate::atomic_save::write_atomically;
+use crate::file_format::is_openable_file_name;
 use anyhow::{anyhow, Context, Result};
-use js_sys::Array;
+use js_sys::{Array, Uint8Array};
 
-/// Saves `content` as `filename`.
-pub fn save_document(content: &str, filename: &str) -> Result<()> {
+/// Saves `content` as `filename`, keeping `backup_generations` backups of
+/// any previous content where files are stored locally.
+pub fn save_document(content: &[u8], filename: &str, backup_generations: usize) -> Result<()> {
     if cfg!(target_arch = "wasm32") {
         download_file(content, filename)
             .with_context(|| format!("Failed to download file '{filename}'"))
     } else if cfg!(feature = "mobile") {
-        save_document_to_storage(content, filename)
+        save_document_to_storage(content, filename, backup_generations)
     } else {
         unreachable!("save_document should not be called on this platform")
     }
 }
 
 /// Returns the content of the file named `filename`.
-pub fn load_document(filename: &str) -> Result<String> {
+pub fn load_document(filename: &str) -> Result<Vec<u8>> {
     if cfg!(feature = "mobile") {
         load_document_from_storage(filename)
             .with_context(|| format!("Failed to load document '{filename}'"))
@@ -50,11 +52,12 @@ pub fn delete_document(filename: &str) -> Result<()> {
 // Helper functions for common operations
 
 /// Returns the full path for `filename` in the storage directory.
-fn file_path(filename: &str) -> PathBuf {
+pub fn storage_path(filename: &str) -> PathBuf {
     storage_directory().join(filename)
 }
 
-/// Returns the JSON files in `storage_dir`.
+/// Returns the document files (in any [`FileFormat`](crate::FileFormat)) and
+/// plain SVG files in `storage_dir`.
 fn collect_json_files_from_dir(storage_dir: &Path) -> Result<Vec<String>> {
     let entries = fs::read_dir(storage_dir)
         .with_context(|| format!("Failed to read directory {storage_dir:?}"))?;
@@ -62,12 +65,12 @@ fn collect_json_files_from_dir(storage_dir: &Path) -> Result<Vec<String>> {
     Ok(entries
         .flatten() // Convert Result<DirEntry, Error> to just DirEntry, skipping errors
         .filter_map(|entry| {
-            // Extract filename and filter for .json files
+            // Extract filename and filter for files that can be opened
             entry
                 .path()
                 .file_name()
                 .and_then(|name| name.to_str())
-                .filter(|name| name.ends_with(".json"))
+                .filter(|name| is_openable_file_name(name))
                 .map(|name| name.to_string())
         })
         .collect())
@@ -76,7 +79,7 @@ fn collect_json_files_from_dir(storage_dir: &Path) -> Result<Vec<String>> {
 // Platform-specific implementation functions
 
 /// Downloads `content` as `filename`.
-fn download_file(content: &str, filename: &str) -> Result<()> {
+fn download_file(content: &[u8], filename: &str) -> Result<()> {
     let window =
         window().ok_or_else(|| anyhow!("Failed to get window object - browser API unavailable"))?;
     let document = window
@@ -84,10 +87,10 @@ fn download_file(content: &str, filename: &str) -> Result<()> {
         .ok_or_else(|| anyhow!("Failed to get document object - browser API unavailable"))?;
 
     let array = Array::new();
-    array.push(&JsValue::from_str(content));
+    array.push(&Uint8Array::from(content));
 
     // Synthesize a link to the content and (programmatically) click it
-    let blob = Blob::new_with_str_sequence(&array)
+    let blob = Blob::new_with_u8_array_sequence(&array)
         .map_err(|_| anyhow!("Failed to create Blob from content"))?;
     let url = Url::create_object_url_with_blob(&blob)
         .map_err(|_| anyhow!("Failed to create object URL for blob"))?;
@@ -119,23 +122,29 @@ fn download_file(content: &str, filename: &str) -> Result<()> {
     Ok(())
 }
 
-/// Saves `content` as `filename` to storage.
-pub fn save_document_to_storage(content: &str, filename: &str) -> Result<()> {
-    fs::write(file_path(filename), content)
-        .with_context(|| format!("Failed to save '{filename}' to {:?}", file_path(filename)))?;
-
-    Ok(())
+/// Saves `content` as `filename` to storage, replacing any previous content
+/// atomically and keeping `backup_generations` backups of it.
+pub fn save_document_to_storage(
+    content: &[u8],
+    filename: &str,
+    backup_generations: usize,
+) -> Result<()> {
+    write_atomically(&storage_path(filename), content, backup_generations).with_context(|| {
+        format!(
+            "Failed to save '{filename}' to {:?}",
+            storage_path(filename)
+        )
+    })
 }
 
 /// Returns the content of the file named `filename`.
-pub fn load_document_from_storage(filename: &str) -> Result<String> {
-    fs::read_to_string(&file_path(filename))
-        .with_context(|| format!("Failed to read file '{filename}'"))
+pub fn load_document_from_storage(filename: &str) -> Result<Vec<u8>> {
+    fs::read(storage_path(filename)).with_context(|| format!("Failed to read file '{filename}'"))
 }
 
 /// Deletes the file named `filename`.
 pub fn delete_document_from_storage(filename: &str) -> Result<()> {
-    fs::remove_file(&file_path(filename))
+    fs::remove_file(storage_path(filename))
         .with_context(|| format!("Failed to delete file '{filename}'"))
 }
 
@@ -155,7 +164,7 @@ pub fn saved_files() -> Result<Vec<String>> {
 
 /// Returns the size of the file named `filename`.
 pub fn file_size(filename: &str) -> Result<usize> {
-    fs::metadata(&file_path(filename))
+    fs::metadata(storage_path(filename))
         .map(|metadata| metadata.len() as usize)
         .with_context(|| format!("Failed to get file size for '{filename}'"))
 }
@@ -212,6 +221,217 @@ pub fn storage_directory() -> PathBuf {
     }
 }
 
+/// The prefix of the local storage keys of recovery entries on the web.
+const RECOVERY_KEY_PREFIX: &str = "pcl-demo.recovery.";
+
+/// Stores `content` as the recovery entry `id`, replacing any previous
+/// content.
+pub fn save_recovery_entry(id: &str, content: &str) -> Result<()> {
+    if cfg!(target_arch = "wasm32") {
+        local_storage()?
+            .set_item(&format!("{RECOVERY_KEY_PREFIX}{id}"), content)
+            .map_err(|_| anyhow!("Failed to store recovery entry '{id}' - storage may be full"))
+    } else {
+        let directory = recovery_directory();
+        fs::create_dir_all(&directory)
+            .with_context(|| format!("Failed to create recovery directory {directory:?}"))?;
+        write_atomically(&directory.join(format!("{id}.json")), content.as_bytes(), 0)
+    }
+}
+
+/// Deletes the recovery entry `id`, if there is one.
+pub fn delete_recovery_entry(id: &str) -> Result<()> {
+    if cfg!(target_arch = "wasm32") {
+        local_storage()?
+            .remove_item(&format!("{RECOVERY_KEY_PREFIX}{id}"))
+            .map_err(|_| anyhow!("Failed to delete recovery entry '{id}'"))
+    } else {
+        let path = recovery_directory().join(format!("{id}.json"));
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Failed to delete recovery entry {path:?}"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Returns the ids and content of all recovery entries.
+pub fn recovery_entries() -> Result<Vec<(String, String)>> {
+    if cfg!(target_arch = "wasm32") {
+        let storage = local_storage()?;
+        let count = storage
+            .length()
+            .map_err(|_| anyhow!("Failed to read local storage"))?;
+        Ok((0..count)
+            .filter_map(|i| storage.key(i).ok().flatten())
+            .filter_map(|key| {
+                let id = key.strip_prefix(RECOVERY_KEY_PREFIX)?.to_string();
+                Some((id, storage.get_item(&key).ok()??))
+            })
+            .collect())
+    } else {
+        let directory = recovery_directory();
+        if !directory.exists() {
+            return Ok(Vec::new());
+        }
+        let entries = fs::read_dir(&directory)
+            .with_context(|| format!("Failed to read directory {directory:?}"))?;
+        Ok(entries
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != "json" {
+                    return None;
+                }
+                let id = path.file_stem()?.to_str()?.to_string();
+                Some((id, fs::read_to_string(&path).ok()?))
+            })
+            .collect())
+    }
+}
+
+/// Returns the directory holding recovery entries on native platforms.
+fn recovery_directory() -> PathBuf {
+    if cfg!(feature = "mobile") {
+        storage_directory().join(".recovery")
+    } else {
+        user_data_directory().join("recovery")
+    }
+}
+
+/// The prefix of the local storage keys of settings on the web.
+const SETTING_KEY_PREFIX: &str = "pcl-demo.setting.";
+
+/// Returns the setting `name`, if it was ever stored.
+pub fn load_setting(name: &str) -> Result<Option<String>> {
+    if cfg!(target_arch = "wasm32") {
+        local_storage()?
+            .get_item(&format!("{SETTING_KEY_PREFIX}{name}"))
+            .map_err(|_| anyhow!("Failed to read setting '{name}'"))
+    } else {
+        let path = settings_directory().join(name);
+        match fs::read_to_string(&path) {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to read setting {path:?}")),
+        }
+    }
+}
+
+/// Stores `content` as the setting `name`, replacing any previous content.
+pub fn save_setting(name: &str, content: &str) -> Result<()> {
+    if cfg!(target_arch = "wasm32") {
+        local_storage()?
+            .set_item(&format!("{SETTING_KEY_PREFIX}{name}"), content)
+            .map_err(|_| anyhow!("Failed to store setting '{name}' - storage may be full"))
+    } else {
+        let directory = settings_directory();
+        fs::create_dir_all(&directory)
+            .with_context(|| format!("Failed to create settings directory {directory:?}"))?;
+        write_atomically(&directory.join(name), content.as_bytes(), 0)
+    }
+}
+
+/// Returns the directory holding settings on native platforms.
+fn settings_directory() -> PathBuf {
+    if cfg!(feature = "mobile") {
+        storage_directory().join(".settings")
+    } else {
+        user_data_directory()
+    }
+}
+
+/// Returns the directory holding the application's data on desktop.
+///
+/// Tests each have a temporary one instead, so that they neither touch the
+/// user's data nor each other's.
+pub(crate) fn user_data_directory() -> PathBuf {
+    if cfg!(test) {
+        let thread = format!("{:?}", std::thread::current().id());
+        let thread: String = thread.chars().filter(char::is_ascii_digit).collect();
+        let name = format!("pcl-demo-test-{}-{thread}", std::process::id());
+        return std::env::temp_dir().join(name);
+    }
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join(".pcl-demo")
+}
+
+/// The prefix of the local storage keys of recent documents on the web,
+/// whose files the browser can't reopen by name.
+const RECENT_DOCUMENT_KEY_PREFIX: &str = "pcl-demo.recent.";
+
+/// Keeps the content of the recent document at `path`, as the JSON `json`
+/// returns, where its file can't be reopened later (on the web).
+pub fn keep_recent_document(path: &Path, json: impl FnOnce() -> Result<String>) -> Result<()> {
+    if cfg!(target_arch = "wasm32") {
+        local_storage()?
+            .set_item(&recent_document_key(path), &json()?)
+            .map_err(|_| anyhow!("Failed to keep recent document {path:?} - storage may be full"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Discards anything kept of the recent document at `path`.
+pub fn forget_recent_document(path: &Path) -> Result<()> {
+    if cfg!(target_arch = "wasm32") {
+        local_storage()?
+            .remove_item(&recent_document_key(path))
+            .map_err(|_| anyhow!("Failed to forget recent document {path:?}"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns `true` iff the recent document at `path` can still be opened.
+pub fn recent_document_exists(path: &Path) -> bool {
+    if cfg!(target_arch = "wasm32") {
+        local_storage()
+            .ok()
+            .and_then(|storage| storage.get_item(&recent_document_key(path)).ok())
+            .flatten()
+            .is_some()
+    } else if cfg!(feature = "mobile") {
+        storage_directory().join(path).is_file()
+    } else {
+        path.is_file()
+    }
+}
+
+/// Returns the content of the recent document at `path`.
+pub fn load_recent_document(path: &Path) -> Result<Vec<u8>> {
+    if cfg!(target_arch = "wasm32") {
+        local_storage()?
+            .get_item(&recent_document_key(path))
+            .map_err(|_| anyhow!("Failed to read local storage"))?
+            .map(String::into_bytes)
+            .ok_or_else(|| anyhow!("Recent document {path:?} is no longer available"))
+    } else if cfg!(feature = "mobile") {
+        fs::read(storage_directory().join(path))
+            .with_context(|| format!("Failed to read file {path:?}"))
+    } else {
+        fs::read(path).with_context(|| format!("Failed to read file {path:?}"))
+    }
+}
+
+/// Returns the local storage key of the recent document at `path`.
+fn recent_document_key(path: &Path) -> String {
+    format!("{RECENT_DOCUMENT_KEY_PREFIX}{}", path.display())
+}
+
+/// Returns the browser's local storage.
+fn local_storage() -> Result<Storage> {
+    window()
+        .ok_or_else(|| anyhow!("Failed to get window object - browser API unavailable"))?
+        .local_storage()
+        .map_err(|_| anyhow!("Failed to access local storage"))?
+        .ok_or_else(|| anyhow!("Local storage is unavailable"))
+}
+
 /// Initializes sample files in the storage directory.
 pub fn initialize_sample_files() {
     let sample_circle = r#"{
@@ -222,17 +442,17 @@ pub fn initialize_sample_files() {
   "html": "<svg viewBox=\"0 0 70 70\" xmlns=\"http://www.w3.org/2000/svg\">\n<rect x=\"15\" y=\"15\" width=\"40\" height=\"40\" fill=\"lightcoral\" stroke=\"darkred\" stroke-width=\"2\"/>\n<text x=\"35\" y=\"40\" text-anchor=\"middle\" font-size=\"8\">Square Doc</text>\n</svg>"
 }"#;
 
-    let _ = save_document_to_storage(sample_circle, "sample_circle.json");
-    let _ = save_document_to_storage(sample_square, "sample_square.json");
+    let _ = save_document_to_storage(sample_circle.as_bytes(), "sample_circle.json", 0);
+    let _ = save_document_to_storage(sample_square.as_bytes(), "sample_square.json", 0);
 }
 
 /// Shares `content` on mobile platforms.
-pub fn share_document_mobile(content: &str) {
+pub fn share_document_mobile(content: &[u8]) {
     if cfg!(target_os = "android") {
         println!("Android: Opening share sheet");
     } else if cfg!(target_os = "ios") {
         println!("iOS: Opening activity view controller");
     } else {
-        println!("Share: Would share document ({} chars)", content.len());
+        println!("Share: Would share document ({} bytes)", content.len());
     }
 }
@@ -0,0 +1,28 @@
+//! Platform-specific keyboard conventions
+//!
+//! This module factors out cfg-dependent code to improve rust-analyzer support.
+
+/// Returns `true` iff the primary modifier key for shortcuts is Cmd rather
+/// than Ctrl.
+pub fn uses_command_key() -> bool {
+    if cfg!(target_arch = "wasm32") {
+        // The browser may run on any operating system.
+        web_sys::window()
+            .and_then(|window| window.navigator().platform().ok())
+            .is_some_and(|platform| platform.starts_with("Mac") || platform.starts_with("iP"))
+    } else {
+        cfg!(any(target_os = "macos", target_os = "ios"))
+    }
+}
+
+/// Returns `true` iff the app runs in a browser, which keeps some shortcuts,
+/// such as closing the tab, for itself.
+pub fn in_browser() -> bool {
+    cfg!(target_arch = "wasm32")
+}
+
+/// Returns `true` iff shortcuts are handled by the platform's native menus
+/// rather than by the UI.
+pub fn has_native_menus() -> bool {
+    !(cfg!(target_arch = "wasm32") || cfg!(feature = "mobile"))
+}
@@ -6,8 +6,15 @@
 
 pub mod file_menu;
 pub mod file_operations;
+pub mod keyboard;
+pub mod timer;
 
 pub use file_menu::FileMenu;
 pub use file_operations::{
-    delete_document, file_size, load_document, save_document, saved_files, share_document_mobile,
+    delete_document, delete_recovery_entry, file_size, forget_recent_document,
+    keep_recent_document, load_document, load_recent_document, load_setting,
+    recent_document_exists, recovery_entries, save_document, save_recovery_entry, save_setting,
+    saved_files, share_document_mobile, storage_path,
 };
+pub use keyboard::{has_native_menus, in_browser, uses_command_key};
+pub use timer::sleep;
@@ -0,0 +1,27 @@
+//! Platform-agnostic timers
+
+use js_sys::Promise;
+use std::time::Duration;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::window;
+
+/// Completes after `duration`, without blocking the UI.
+pub async fn sleep(duration: Duration) {
+    if cfg!(target_arch = "wasm32") {
+        let milliseconds = i32::try_from(duration.as_millis()).unwrap_or(i32::MAX);
+        let timeout = Promise::new(&mut |resolve, _| {
+            if let Some(window) = window() {
+                let _ = window
+                    .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, milliseconds);
+            }
+        });
+        let _ = JsFuture::from(timeout).await;
+    } else {
+        let (sender, receiver) = futures_channel::oneshot::channel();
+        std::thread::spawn(move || {
+            std::thread::sleep(duration);
+            let _ = sender.send(());
+        });
+        let _ = receiver.await;
+    }
+}
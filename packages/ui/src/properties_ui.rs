@@ -0,0 +1,90 @@
+use crate::application_state::ApplicationState;
+use crate::metadata::format_timestamp;
+use crate::notifications::report_failure;
+use dioxus::prelude::*;
+
+/// A panel for viewing and editing the current document's metadata, shown
+/// while `ApplicationState::show_properties` is set.
+#[component]
+pub fn PropertiesPanel(application_state: Signal<ApplicationState>) -> Element {
+    let mut state = application_state;
+    if !state.read().show_properties {
+        return rsx! {};
+    }
+
+    let metadata = state.read().current().document.metadata().clone();
+    let created = metadata
+        .created
+        .as_ref()
+        .map_or("Unknown".to_string(), format_timestamp);
+    let modified = metadata
+        .modified
+        .as_ref()
+        .map_or("Never saved".to_string(), format_timestamp);
+    let tags = metadata.tags_text();
+
+    let close = move |_| state.write().show_properties = false;
+
+    let report = move |result| report_failure(state, "Failed to edit properties", result);
+
+    rsx! {
+        div {
+            class: "properties-panel",
+            div {
+                class: "properties-header",
+                h3 { "Document Properties" }
+                button { class: "close-button", title: "Close", onclick: close, "✕" }
+            }
+            label { r#for: "property-title", "Title" }
+            input {
+                id: "property-title",
+                r#type: "text",
+                value: "{metadata.title}",
+                placeholder: "Untitled",
+                onfocus: move |_| state.write().current_mut().begin_gesture(),
+                onblur: move |_| state.write().current_mut().end_gesture(),
+                oninput: move |e: FormEvent| {
+                    report(state.write().current_mut().edit_metadata(|m| m.title = e.value()));
+                },
+            }
+            label { r#for: "property-author", "Author" }
+            input {
+                id: "property-author",
+                r#type: "text",
+                value: "{metadata.author}",
+                onfocus: move |_| state.write().current_mut().begin_gesture(),
+                onblur: move |_| state.write().current_mut().end_gesture(),
+                oninput: move |e: FormEvent| {
+                    report(state.write().current_mut().edit_metadata(|m| m.author = e.value()));
+                },
+            }
+            label { r#for: "property-description", "Description" }
+            textarea {
+                id: "property-description",
+                rows: "3",
+                value: "{metadata.description}",
+                onfocus: move |_| state.write().current_mut().begin_gesture(),
+                onblur: move |_| state.write().current_mut().end_gesture(),
+                oninput: move |e: FormEvent| {
+                    report(state.write().current_mut().edit_metadata(|m| m.description = e.value()));
+                },
+            }
+            label { r#for: "property-tags", "Tags" }
+            input {
+                id: "property-tags",
+                r#type: "text",
+                value: "{tags}",
+                placeholder: "Comma-separated",
+                // Applied on commit so that typing a separator isn't undone.
+                onchange: move |e: FormEvent| {
+                    report(state.write().current_mut().edit_metadata(|m| m.set_tags_text(&e.value())));
+                },
+            }
+            div {
+                class: "properties-timestamps",
+                div { "Created: {created}" }
+                div { "Modified: {modified}" }
+            }
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! The documents opened or saved most recently, kept across sessions.
+//!
+//! The list is stored as a setting, and the paths in it that can no longer
+//! be opened are dropped whenever it is loaded. On the web, where a file
+//! can't be reopened by name, the content of each recent document is kept
+//! alongside the list.
+
+use crate::document::Document;
+use crate::notifications::Notifications;
+use crate::platform::{
+    forget_recent_document, keep_recent_document, load_setting, recent_document_exists,
+    save_setting,
+};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// How many recent documents are listed unless configured otherwise.
+pub const DEFAULT_RECENT_FILES_LIMIT: usize = 10;
+
+/// The name of the setting holding the list.
+const SETTING_NAME: &str = "recent-files.json";
+
+/// The paths of the documents opened or saved most recently.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecentFiles {
+    /// The paths, most recent first.
+    paths: Vec<PathBuf>,
+
+    /// The most paths listed.
+    limit: usize,
+}
+
+impl RecentFiles {
+    /// Returns the stored list, keeping at most `limit` paths and dropping
+    /// those that can no longer be opened; problems doing so are told in
+    /// `notifications`.
+    pub fn load(limit: usize, notifications: &mut Notifications) -> Self {
+        let paths = read_paths().unwrap_or_else(|e| {
+            notifications.warning("Couldn't load the recent files", &e);
+            Vec::new()
+        });
+        let mut recent = Self { paths, limit };
+        let count = recent.paths.len();
+        let mut result = recent.prune();
+        if recent.paths.len() != count {
+            result = result.and(recent.store());
+        }
+        if let Err(e) = result {
+            notifications.warning("Couldn't update the recent files", &e);
+        }
+        recent
+    }
+
+    /// Returns the paths, most recent first.
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Returns `true` iff no path is listed.
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    // The changes below are made even if they can't be stored, which is
+    // reported for the user to be told.
+
+    /// Lists at most `limit` paths from now on.
+    pub fn set_limit(&mut self, limit: usize) -> Result<()> {
+        self.limit = limit;
+        self.prune().and(self.store())
+    }
+
+    /// Lists `path`, the file of `document`, as the most recent.
+    pub fn add(&mut self, path: &Path, document: &Document) -> Result<()> {
+        let kept = keep_recent_document(path, || document.to_json())
+            .with_context(|| format!("Failed to keep recent document {path:?}"));
+        self.paths.retain(|p| p != path);
+        self.paths.insert(0, path.to_path_buf());
+        kept.and(self.prune()).and(self.store())
+    }
+
+    /// Stops listing `path`.
+    pub fn remove(&mut self, path: &Path) -> Result<()> {
+        self.paths.retain(|p| p != path);
+        forget(path).and(self.store())
+    }
+
+    /// Stops listing any path.
+    pub fn clear(&mut self) -> Result<()> {
+        let failure = self
+            .paths
+            .drain(..)
+            .filter_map(|path| forget(&path).err())
+            .reduce(|first, _| first);
+        failure.map_or(Ok(()), Err).and(self.store())
+    }
+
+    /// Drops the paths beyond the limit and those that can no longer be
+    /// opened.
+    fn prune(&mut self) -> Result<()> {
+        let mut index = 0;
+        let mut failure = None;
+        self.paths.retain(|path| {
+            let keep = index < self.limit && recent_document_exists(path);
+            if keep {
+                index += 1;
+            } else if let Err(e) = forget(path) {
+                failure.get_or_insert(e);
+            }
+            keep
+        });
+        failure.map_or(Ok(()), Err)
+    }
+
+    /// Stores the list, for the next session.
+    fn store(&self) -> Result<()> {
+        serde_json::to_string(&self.paths)
+            .context("Failed to serialize recent files")
+            .and_then(|content| save_setting(SETTING_NAME, &content))
+            .context("Failed to store recent files")
+    }
+}
+
+/// Returns the stored paths, most recent first.
+fn read_paths() -> Result<Vec<PathBuf>> {
+    match load_setting(SETTING_NAME)? {
+        Some(content) => serde_json::from_str(&content).context("Invalid recent files"),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Discards anything kept of the recent document at `path`.
+fn forget(path: &Path) -> Result<()> {
+    forget_recent_document(path)
+        .with_context(|| format!("Failed to forget recent document {path:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::file_operations::user_data_directory;
+
+    /// Returns the paths of `count` existing files, made in the test's data
+    /// directory.
+    fn existing_files(count: usize) -> Vec<PathBuf> {
+        let directory = user_data_directory().join("documents");
+        std::fs::create_dir_all(&directory).unwrap();
+        (0..count)
+            .map(|i| {
+                let path = directory.join(format!("{i}.json"));
+                std::fs::write(&path, "{}").unwrap();
+                path
+            })
+            .collect()
+    }
+
+    #[test]
+    fn at_most_the_limit_is_listed() {
+        let paths = existing_files(4);
+        let mut recent = RecentFiles::load(3, &mut Notifications::default());
+        for path in &paths {
+            recent.add(path, &Document::new()).unwrap();
+        }
+        assert_eq!(
+            recent.paths(),
+            [paths[3].clone(), paths[2].clone(), paths[1].clone()]
+        );
+
+        recent.set_limit(1).unwrap();
+        assert_eq!(recent.paths(), [paths[3].clone()]);
+        let loaded = RecentFiles::load(5, &mut Notifications::default());
+        assert_eq!(loaded.paths(), [paths[3].clone()]);
+        std::fs::remove_dir_all(user_data_directory()).unwrap();
+    }
+
+    #[test]
+    fn added_paths_are_listed_once() {
+        let paths = existing_files(2);
+        let mut recent = RecentFiles::load(5, &mut Notifications::default());
+        for path in [&paths[0], &paths[1], &paths[0]] {
+            recent.add(path, &Document::new()).unwrap();
+        }
+        assert_eq!(recent.paths(), [paths[0].clone(), paths[1].clone()]);
+
+        recent.remove(&paths[0]).unwrap();
+        assert_eq!(recent.paths(), [paths[1].clone()]);
+        recent.clear().unwrap();
+        assert!(recent.is_empty());
+        std::fs::remove_dir_all(user_data_directory()).unwrap();
+    }
+
+    #[test]
+    fn missing_paths_are_dropped() {
+        let paths = existing_files(2);
+        let mut recent = RecentFiles::load(5, &mut Notifications::default());
+        for path in &paths {
+            recent.add(path, &Document::new()).unwrap();
+        }
+        let missing = paths[0].with_file_name("missing.json");
+        recent.add(&missing, &Document::new()).unwrap();
+        assert!(!recent.paths().contains(&missing));
+
+        std::fs::remove_file(&paths[1]).unwrap();
+        let mut notifications = Notifications::default();
+        let loaded = RecentFiles::load(5, &mut notifications);
+        assert_eq!(loaded.paths(), [paths[0].clone()]);
+        assert!(notifications.history().next().is_none());
+        std::fs::remove_dir_all(user_data_directory()).unwrap();
+    }
+}
@@ -0,0 +1,124 @@
+//! Recovery of unsaved work after a crash.
+//!
+//! While a document has unsaved changes, it is periodically autosaved to a
+//! recovery area (a directory natively, local storage on the web), as one
+//! entry per open document. Entries are discarded when their document
+//! is saved, so any found at launch hold work that would otherwise be lost.
+
+use crate::document::Document;
+use crate::notifications::Notifications;
+use crate::platform::{delete_recovery_entry, recovery_entries, save_recovery_entry};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// How often unsaved changes are autosaved unless configured otherwise.
+pub const DEFAULT_AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A recovery entry, as stored.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    /// When the entry was written.
+    saved: DateTime<Utc>,
+
+    /// Where the document was to be saved, if anywhere.
+    path: Option<PathBuf>,
+
+    /// The document, as stored in JSON.
+    document: Value,
+}
+
+/// A document recovered from an earlier session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveredDocument {
+    /// The id of the recovery entry.
+    pub id: String,
+
+    /// When the document was autosaved.
+    pub saved: DateTime<Utc>,
+
+    /// Where the document was to be saved (`None` for untitled documents).
+    pub path: Option<PathBuf>,
+
+    /// The recovered document.
+    pub document: Document,
+}
+
+impl RecoveredDocument {
+    /// Returns the title of the document, for display.
+    pub fn title(&self) -> &str {
+        let file_name = self
+            .path
+            .as_ref()
+            .and_then(|p| p.file_stem())
+            .and_then(|n| n.to_str())
+            .unwrap_or("Untitled");
+        self.document.metadata().title_or(file_name)
+    }
+}
+
+/// Returns a new recovery entry id, unique across sessions.
+pub(crate) fn new_recovery_id() -> String {
+    // Documents opened together may share a clock reading.
+    static COUNT: AtomicU64 = AtomicU64::new(0);
+    let count = COUNT.fetch_add(1, Ordering::Relaxed);
+    format!("{}-{count}", Utc::now().format("%Y%m%d-%H%M%S-%f"))
+}
+
+/// Stores `document`, the JSON form of a document to be saved at `path`, as
+/// the recovery entry `id`.
+pub(crate) fn write_recovery(id: &str, path: Option<&Path>, document: Value) -> Result<()> {
+    let entry = Entry {
+        saved: Utc::now(),
+        path: path.map(Path::to_path_buf),
+        document,
+    };
+    let content = serde_json::to_string(&entry).context("Failed to serialize recovery entry")?;
+    save_recovery_entry(id, &content)
+}
+
+/// Discards the recovery entry `id`, if there is one.
+pub(crate) fn discard_recovery(id: &str) -> Result<()> {
+    delete_recovery_entry(id)
+}
+
+/// Returns the documents in the recovery area, newest first, skipping any
+/// entry that can't be read; problems reading them are told in
+/// `notifications`.
+pub fn recovered_documents(notifications: &mut Notifications) -> Vec<RecoveredDocument> {
+    let entries = recovery_entries().unwrap_or_else(|e| {
+        notifications.warning("Couldn't look for work to recover", &e);
+        Vec::new()
+    });
+    let mut recovered: Vec<_> = entries
+        .into_iter()
+        .filter_map(|(id, content)| match read_entry(&content) {
+            Ok((saved, path, document)) => Some(RecoveredDocument {
+                id,
+                saved,
+                path,
+                document,
+            }),
+            Err(e) => {
+                notifications.warning(format!("Couldn't recover the work saved as '{id}'"), &e);
+                None
+            }
+        })
+        .collect();
+    recovered.sort_by_key(|r| std::cmp::Reverse(r.saved));
+    recovered
+}
+
+/// Returns the time, path and document of the recovery entry `content`.
+fn read_entry(content: &str) -> Result<(DateTime<Utc>, Option<PathBuf>, Document)> {
+    let entry: Entry = serde_json::from_str(content).context("Invalid recovery entry")?;
+    Ok((
+        entry.saved,
+        entry.path,
+        Document::from_value(entry.document)?,
+    ))
+}
@@ -0,0 +1,585 @@
+//! Removal of active content from drawings.
+//!
+//! Drawings end up in the DOM via `dangerous_inner_html`, so anything able to
+//! run script (`<script>`, event handler attributes, `javascript:` URLs,
+//! `<foreignObject>`, …) must be stripped first. Rather than trying to
+//! recognize everything dangerous, only known-safe elements and attributes
+//! are kept.
+
+use crate::page::Page;
+use crate::shape::{Attributes, Drawing, Node, Shape, Style};
+use std::fmt;
+
+/// The elements, besides the typed shapes, that may appear in a drawing.
+const ALLOWED_ELEMENTS: &[&str] = &[
+    "circle",
+    "clipPath",
+    "defs",
+    "desc",
+    "ellipse",
+    "feBlend",
+    "feColorMatrix",
+    "feComposite",
+    "feFlood",
+    "feGaussianBlur",
+    "feMerge",
+    "feMergeNode",
+    "feOffset",
+    "filter",
+    "g",
+    "image",
+    "line",
+    "linearGradient",
+    "marker",
+    "mask",
+    "metadata",
+    "path",
+    "pattern",
+    "polygon",
+    "polyline",
+    "radialGradient",
+    "rect",
+    "stop",
+    "svg",
+    "symbol",
+    "text",
+    "textPath",
+    "title",
+    "tspan",
+    "use",
+];
+
+/// The attributes that may appear on any element of a drawing.
+const ALLOWED_ATTRIBUTES: &[&str] = &[
+    "alignment-baseline",
+    "baseProfile",
+    "class",
+    "clip-path",
+    "clip-rule",
+    "clipPathUnits",
+    "color",
+    "cx",
+    "cy",
+    "d",
+    "display",
+    "dominant-baseline",
+    "dx",
+    "dy",
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "filter",
+    "filterUnits",
+    "flood-color",
+    "flood-opacity",
+    "font-family",
+    "font-size",
+    "font-style",
+    "font-weight",
+    "fx",
+    "fy",
+    "gradientTransform",
+    "gradientUnits",
+    "height",
+    "href",
+    "id",
+    "in",
+    "in2",
+    "k1",
+    "k2",
+    "k3",
+    "k4",
+    "lengthAdjust",
+    "letter-spacing",
+    "marker-end",
+    "marker-mid",
+    "marker-start",
+    "markerHeight",
+    "markerUnits",
+    "markerWidth",
+    "mask",
+    "maskContentUnits",
+    "maskUnits",
+    "mode",
+    "offset",
+    "opacity",
+    "operator",
+    "orient",
+    "patternContentUnits",
+    "patternTransform",
+    "patternUnits",
+    "points",
+    "preserveAspectRatio",
+    "r",
+    "refX",
+    "refY",
+    "result",
+    "role",
+    "rotate",
+    "rx",
+    "ry",
+    "spreadMethod",
+    "stdDeviation",
+    "stop-color",
+    "stop-opacity",
+    "stroke",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-opacity",
+    "stroke-width",
+    "style",
+    "text-anchor",
+    "text-decoration",
+    "textLength",
+    "transform",
+    "type",
+    "values",
+    "version",
+    "viewBox",
+    "visibility",
+    "width",
+    "word-spacing",
+    "x",
+    "x1",
+    "x2",
+    "xlink:href",
+    "xml:space",
+    "y",
+    "y1",
+    "y2",
+];
+
+/// The URL prefixes that may be referenced from a drawing (`asset:` refers
+/// to the document's asset table).
+const ALLOWED_URL_PREFIXES: &[&str] = &[
+    "#",
+    "asset:",
+    "data:image/png",
+    "data:image/jpeg",
+    "data:image/gif",
+    "data:image/webp",
+];
+
+/// Something removed from a drawing by [`sanitize`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Removal {
+    /// An element that isn't allowed, along with its content.
+    Element(String),
+
+    /// An attribute of `element` that isn't allowed or has an unsafe value.
+    Attribute { element: String, attribute: String },
+
+    /// A comment that would end early in HTML.
+    Comment,
+
+    /// A processing instruction with the given target, which would end early
+    /// in HTML or loads a style sheet.
+    Instruction(String),
+}
+
+impl fmt::Display for Removal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Removal::Element(name) => write!(f, "<{name}> element"),
+            Removal::Attribute { element, attribute } => {
+                write!(f, "{attribute} attribute on <{element}>")
+            }
+            Removal::Comment => write!(f, "comment"),
+            Removal::Instruction(target) => write!(f, "<?{target}?> processing instruction"),
+        }
+    }
+}
+
+/// What [`sanitize`] removed from a drawing.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SanitizeReport {
+    /// Everything removed, in document order.
+    pub removals: Vec<Removal>,
+}
+
+impl SanitizeReport {
+    /// Returns `true` iff nothing was removed.
+    pub fn is_empty(&self) -> bool {
+        self.removals.is_empty()
+    }
+
+    /// Returns a human-readable line for each distinct removal, with a count
+    /// when it happened more than once.
+    pub fn lines(&self) -> Vec<String> {
+        let mut counts: Vec<(&Removal, usize)> = Vec::new();
+        for removal in &self.removals {
+            match counts.iter_mut().find(|(r, _)| *r == removal) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((removal, 1)),
+            }
+        }
+        counts
+            .into_iter()
+            .map(|(removal, count)| match count {
+                1 => removal.to_string(),
+                n => format!("{removal} (×{n})"),
+            })
+            .collect()
+    }
+}
+
+/// Removes everything from `drawing` that isn't known to be safe to render,
+/// returning what was removed.
+pub fn sanitize(drawing: &mut Drawing) -> SanitizeReport {
+    let mut report = SanitizeReport::default();
+    retain_attributes("svg", &mut drawing.attributes, &mut report);
+    retain_shapes(&mut drawing.prolog, &mut report);
+    retain_shapes(&mut drawing.shapes, &mut report);
+    report
+}
+
+/// Removes everything from `page` that isn't known to be safe to render,
+/// returning what was removed.
+pub fn sanitize_page(page: &mut Page) -> SanitizeReport {
+    let mut report = SanitizeReport::default();
+    retain_attributes("svg", &mut page.attributes, &mut report);
+    retain_shapes(&mut page.prolog, &mut report);
+    for layer in &mut page.layers {
+        retain_shapes(&mut layer.shapes, &mut report);
+    }
+    report
+}
+
+/// Removes the unsafe elements of `shapes` and sanitizes the rest.
+fn retain_shapes(shapes: &mut Vec<Shape>, report: &mut SanitizeReport) {
+    shapes.retain_mut(|shape| sanitize_shape(shape, report));
+}
+
+/// Sanitizes `shape`, returning `false` if it must be removed entirely.
+fn sanitize_shape(shape: &mut Shape, report: &mut SanitizeReport) -> bool {
+    let name = shape.element_name().to_string();
+
+    match shape {
+        Shape::Other(element) => {
+            if !ALLOWED_ELEMENTS.contains(&name.as_str()) {
+                report.removals.push(Removal::Element(name));
+                return false;
+            }
+            retain_attributes(&name, &mut element.attributes, report);
+            element.content.retain_mut(|node| match node {
+                Node::Shape(shape) => sanitize_shape(shape, report),
+                Node::Text(_) => true,
+            });
+        }
+        Shape::Group(group) => {
+            sanitize_style(&name, &mut group.style, report);
+            retain_shapes(&mut group.children, report);
+        }
+        Shape::Path(path) => {
+            if !is_safe_value("d", &path.d) {
+                path.d.clear();
+                report.removals.push(Removal::Attribute {
+                    element: name.clone(),
+                    attribute: "d".to_string(),
+                });
+            }
+            sanitize_style(&name, &mut path.style, report);
+        }
+        Shape::Comment { text } => {
+            if !is_safe_comment(text) {
+                report.removals.push(Removal::Comment);
+                return false;
+            }
+        }
+        Shape::Instruction(instruction) => {
+            // HTML ends a processing instruction at the first `>`.
+            let unsafe_target = instruction.target.contains(['<', '>'])
+                || instruction.target.eq_ignore_ascii_case("xml")
+                || instruction.target.eq_ignore_ascii_case("xml-stylesheet");
+            if unsafe_target || instruction.value.contains(['<', '>']) {
+                report.removals.push(Removal::Instruction(name));
+                return false;
+            }
+        }
+        _ => {
+            if let Some(style) = shape.style_mut() {
+                sanitize_style(&name, style, report);
+            }
+        }
+    }
+    true
+}
+
+/// Removes the unsafe attributes of `style`, that of an `element`, clearing
+/// its typed fields with unsafe values.
+fn sanitize_style(element: &str, style: &mut Style, report: &mut SanitizeReport) {
+    let fields = [
+        ("fill", &mut style.fill),
+        ("stroke", &mut style.stroke),
+        ("transform", &mut style.transform),
+        ("font-family", &mut style.font_family),
+        ("text-anchor", &mut style.text_anchor),
+    ];
+    for (attribute, field) in fields {
+        if field
+            .as_deref()
+            .is_some_and(|value| !is_safe_value(attribute, value))
+        {
+            *field = None;
+            report.removals.push(Removal::Attribute {
+                element: element.to_string(),
+                attribute: attribute.to_string(),
+            });
+        }
+    }
+    retain_attributes(element, &mut style.attributes, report);
+}
+
+/// Returns `true` iff `text` can be written as a comment that ends where it
+/// does in XML when parsed as HTML.
+fn is_safe_comment(text: &str) -> bool {
+    !(text.starts_with('>')
+        || text.starts_with("->")
+        || text.contains("--")
+        || text.contains("<!-")
+        || text.ends_with('-'))
+}
+
+/// Removes the unsafe attributes of `element` from `attributes`.
+fn retain_attributes(element: &str, attributes: &mut Attributes, report: &mut SanitizeReport) {
+    attributes.retain(|(name, value)| {
+        let allowed = is_allowed_attribute(name) && is_safe_value(name, value);
+        if !allowed {
+            report.removals.push(Removal::Attribute {
+                element: element.to_string(),
+                attribute: name.clone(),
+            });
+        }
+        allowed
+    });
+}
+
+/// Returns `true` iff the attribute `name` may appear in a drawing.
+fn is_allowed_attribute(name: &str) -> bool {
+    ALLOWED_ATTRIBUTES.contains(&name)
+        || name.starts_with("xmlns")
+        || name.starts_with("data-")
+        || name.starts_with("aria-")
+}
+
+/// Returns `true` iff `value`, as the value of the attribute `name`, can't
+/// run script or reference external content.
+fn is_safe_value(name: &str, value: &str) -> bool {
+    // Style values may spell what's looked for with CSS escapes, as in
+    // `u\72l(`, so they're checked as the browser reads them too.
+    is_safe_unescaped_value(name, value) && is_safe_unescaped_value(name, &css_unescaped(value))
+}
+
+/// Returns `value` with its CSS escapes replaced by the characters they
+/// stand for.
+fn css_unescaped(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        let mut code = String::new();
+        while code.len() < 6 {
+            match chars.next_if(char::is_ascii_hexdigit) {
+                Some(digit) => code.push(digit),
+                None => break,
+            }
+        }
+        if code.is_empty() {
+            // Any other character stands for itself, and a newline for
+            // nothing.
+            unescaped.extend(chars.next().filter(|&c| c != '\n'));
+        } else {
+            // A whitespace character may end the escape.
+            chars.next_if(|c| c.is_ascii_whitespace());
+            let code = u32::from_str_radix(&code, 16).unwrap_or(0);
+            unescaped.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+        }
+    }
+    unescaped
+}
+
+/// Returns `true` iff `value`, as the value of the attribute `name`, can't
+/// run script or reference external content, reading it without escapes.
+fn is_safe_unescaped_value(name: &str, value: &str) -> bool {
+    // Browsers ignore embedded whitespace and control characters in URLs.
+    let normalized: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+
+    let scheme_is_safe = !["javascript:", "vbscript:", "expression(", "@import"]
+        .iter()
+        .any(|pattern| normalized.contains(pattern));
+    let urls_are_safe = normalized
+        .split("url(")
+        .skip(1)
+        .all(|rest| is_safe_url(rest.trim_start_matches(['"', '\''])));
+    let is_link = matches!(name, "href" | "xlink:href");
+
+    scheme_is_safe && urls_are_safe && (!is_link || is_safe_url(&normalized))
+}
+
+/// Returns `true` iff the (normalized) URL `url` may be referenced.
+fn is_safe_url(url: &str) -> bool {
+    ALLOWED_URL_PREFIXES
+        .iter()
+        .any(|prefix| url.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Instruction;
+    use crate::svg_parser::parse_svg;
+
+    /// Returns the markup of `svg`, the content of an `<svg>` element,
+    /// sanitized, with what was removed.
+    fn sanitized(svg: &str) -> (String, SanitizeReport) {
+        let mut drawing = parse_svg(&format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">{svg}</svg>"#
+        ))
+        .unwrap();
+        let report = sanitize(&mut drawing);
+        (drawing.to_svg(), report)
+    }
+
+    #[test]
+    fn removes_scripts_and_foreign_objects_with_their_content() {
+        let (svg, report) = sanitized(
+            r#"<script>alert(1)</script><g><foreignObject><div xmlns="http://www.w3.org/1999/xhtml">x</div></foreignObject></g><rect width="1" height="1"/>"#,
+        );
+        assert!(!svg.contains("script") && !svg.contains("foreignObject") && !svg.contains("div"));
+        assert!(svg.contains("<rect"));
+        assert_eq!(
+            report.removals,
+            [
+                Removal::Element("script".to_string()),
+                Removal::Element("foreignObject".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn removes_event_handlers() {
+        let (svg, report) = sanitized(r#"<rect width="1" height="1" onclick="alert(1)"/>"#);
+        assert!(!svg.contains("onclick"));
+        assert_eq!(report.lines(), ["onclick attribute on <rect>"]);
+    }
+
+    #[test]
+    fn removes_script_links() {
+        let (svg, report) = sanitized(
+            r#"<image href="javascript:alert(1)"/><use xlink:href=" JaVa&#10;Script:alert(1)"/><image href="vbscript:msgbox(1)"/>"#,
+        );
+        assert!(!svg.to_ascii_lowercase().contains("script:"));
+        assert_eq!(report.removals.len(), 3);
+    }
+
+    #[test]
+    fn removes_links_to_external_content() {
+        let (svg, report) = sanitized(
+            r##"<image href="http://evil/x.png"/><image href="data:image/svg+xml;base64,PHN2Zy8+"/><image href="data:image/png;base64,iVBORw0KGgo="/><use href="#shape"/>"##,
+        );
+        assert!(!svg.contains("evil") && !svg.contains("svg+xml"));
+        assert!(svg.contains("data:image/png") && svg.contains("href=\"#shape\""));
+        assert_eq!(report.removals.len(), 2);
+    }
+
+    #[test]
+    fn clears_typed_fields_referencing_external_content() {
+        let (svg, report) = sanitized(
+            r##"<rect width="1" height="1" fill="url(http://evil/x#a)" stroke="url('https://evil/y')"/><g transform="url(javascript:alert(1))"><path d="M0 0" fill="url(#gradient)"/></g><text font-family="@import x">t</text>"##,
+        );
+        assert!(!svg.contains("evil") && !svg.contains("javascript") && !svg.contains("@import"));
+        assert!(svg.contains(r##"fill="url(#gradient)""##));
+        assert_eq!(
+            report.lines(),
+            [
+                "fill attribute on <rect>",
+                "stroke attribute on <rect>",
+                "transform attribute on <g>",
+                "font-family attribute on <text>"
+            ]
+        );
+    }
+
+    #[test]
+    fn removes_untyped_attributes_referencing_external_content() {
+        let (svg, report) = sanitized(
+            r##"<rect width="1" height="1" filter="url(http://evil/f)" style="fill: URL( &quot;http://evil/p&quot; )"/><circle r="1" mask="url(#m)"/>"##,
+        );
+        assert!(!svg.contains("evil"));
+        assert!(svg.contains(r##"mask="url(#m)""##));
+        assert_eq!(
+            report.lines(),
+            ["filter attribute on <rect>", "style attribute on <rect>"]
+        );
+    }
+
+    #[test]
+    fn sees_through_css_escapes() {
+        let (svg, report) = sanitized(
+            r#"<rect width="1" height="1" style="fill: u\72l(http://evil/a)"/><rect width="1" height="1" style="fill: \75 rl(http://evil/b)"/><rect width="1" height="1" style="background: \u\r\l(http://evil/c)"/><rect width="1" height="1" style="content: '\\'"/>"#,
+        );
+        assert!(!svg.contains("evil"));
+        assert_eq!(report.removals.len(), 3);
+        assert_eq!(css_unescaped(r"\6a\61 vascript\3a"), "javascript:");
+        assert_eq!(css_unescaped(r"\000075\000072l("), "url(");
+    }
+
+    #[test]
+    fn removes_comments_and_instructions_ending_early_in_html() {
+        let mut drawing = parse_svg(r#"<svg xmlns="http://www.w3.org/2000/svg"/>"#).unwrap();
+        drawing.shapes = vec![
+            Shape::Comment {
+                text: " kept ".to_string(),
+            },
+            Shape::Comment {
+                text: "><img src=x onerror=alert(1)>".to_string(),
+            },
+            Shape::Comment {
+                text: "a--!><script>".to_string(),
+            },
+            Shape::Instruction(Instruction {
+                target: "app".to_string(),
+                value: "kept".to_string(),
+            }),
+            Shape::Instruction(Instruction {
+                target: "app".to_string(),
+                value: "><img src=x onerror=alert(1)>".to_string(),
+            }),
+        ];
+        drawing.prolog = vec![Shape::Instruction(Instruction {
+            target: "xml-stylesheet".to_string(),
+            value: "href=\"http://evil/s.css\"".to_string(),
+        })];
+        let report = sanitize(&mut drawing);
+        assert_eq!(
+            report.lines(),
+            [
+                "<?xml-stylesheet?> processing instruction",
+                "comment (×2)",
+                "<?app?> processing instruction"
+            ]
+        );
+        let svg = drawing.to_svg();
+        assert!(svg.contains("<!-- kept -->") && svg.contains("<?app kept?>"));
+        assert!(!svg.contains("img") && !svg.contains("evil"));
+    }
+
+    #[test]
+    fn keeps_safe_drawings_intact() {
+        let defs = r#"<defs><linearGradient id="g"><stop offset="0" stop-color="red"/></linearGradient></defs>"#;
+        let rect = r##"<rect x="0" y="0" width="1" height="1" fill="url(#g)" data-name="r" aria-label="square"/>"##;
+        let (svg, report) = sanitized(&format!("{defs}{rect}"));
+        assert!(report.is_empty(), "{:?}", report.removals);
+        assert!(svg.contains(defs) && svg.contains(rect), "{svg}");
+    }
+}
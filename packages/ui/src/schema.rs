@@ -0,0 +1,292 @@
+//! Versioning of the stored document format.
+//!
+//! Every stored document carries a `version` field. Documents written by
+//! older versions of the application are upgraded one version at a time by
+//! the steps in [`MIGRATIONS`] before being deserialized.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::page::Page;
+use crate::svg_parser::parse_svg;
+
+mod v2_drawing;
+
+/// The format version written by this application.
+pub const CURRENT_VERSION: u64 = 7;
+
+/// A migration step, upgrading a document by one version.
+type Migration = fn(Value) -> Result<Value>;
+
+/// The migration steps, where `MIGRATIONS[i]` upgrades version `i + 1` to `i + 2`.
+const MIGRATIONS: [Migration; (CURRENT_VERSION - 1) as usize] =
+    [v1_to_v2, v2_to_v3, v3_to_v4, v4_to_v5, v5_to_v6, v6_to_v7];
+
+/// A document's content tagged with the current format version.
+#[derive(Serialize)]
+pub struct Versioned<T> {
+    version: u64,
+    #[serde(flatten)]
+    content: T,
+}
+
+impl<T> Versioned<T> {
+    /// Returns `content` tagged with [`CURRENT_VERSION`].
+    pub fn new(content: T) -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            content,
+        }
+    }
+}
+
+/// Returns the format version of the stored document `value`.
+///
+/// Documents without a `version` field predate versioning and are version 1.
+pub fn version_of(value: &Value) -> Result<u64> {
+    match value.get("version") {
+        None => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .filter(|&v| v >= 1)
+            .ok_or_else(|| anyhow!("Invalid document format version: {version}")),
+    }
+}
+
+/// Returns the stored document `value` upgraded to [`CURRENT_VERSION`].
+pub fn migrate(mut value: Value) -> Result<Value> {
+    let version = version_of(&value)?;
+    if version > CURRENT_VERSION {
+        bail!(
+            "This document uses format version {version}, but this application only \
+             understands versions up to {CURRENT_VERSION}. Please update the application."
+        );
+    }
+
+    // Version 2 can't hold the comments, processing instructions and
+    // attribute order of version 1 markup, so it is parsed again once
+    // upgraded.
+    let markup = match value.get("html").and_then(Value::as_str) {
+        Some(html) if version == 1 => Some(html.to_string()),
+        _ => None,
+    };
+    for (step, migration) in MIGRATIONS.iter().enumerate().skip(version as usize - 1) {
+        let from = step as u64 + 1;
+        value = migration(value)
+            .with_context(|| format!("Failed to upgrade document from version {from}"))?;
+    }
+    match markup {
+        Some(markup) => with_page_from_markup(value, &markup),
+        None => Ok(value),
+    }
+}
+
+/// Returns the upgraded version 1 document `value` with its page replaced by
+/// the one the current parser makes of `markup`, the version 1 content.
+fn with_page_from_markup(mut value: Value, markup: &str) -> Result<Value> {
+    let page = Page::from_drawing(parse_svg(markup)?);
+    value["pages"] = serde_json::to_value([page]).context("Failed to store the page")?;
+    Ok(value)
+}
+
+/// Replaces the HTML string of version 1 with the structured drawing.
+fn v1_to_v2(value: Value) -> Result<Value> {
+    let html = value
+        .get("html")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("Missing \"html\" field"))?;
+    Ok(json!({ "version": 2, "drawing": v2_drawing::drawing(html)? }))
+}
+
+/// Adds empty metadata.
+fn v2_to_v3(mut value: Value) -> Result<Value> {
+    value["metadata"] = json!({});
+    value["version"] = json!(3);
+    Ok(value)
+}
+
+/// Makes the single drawing the first of a list of pages.
+fn v3_to_v4(mut value: Value) -> Result<Value> {
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Expected a JSON object"))?;
+    let drawing = object
+        .remove("drawing")
+        .ok_or_else(|| anyhow!("Missing \"drawing\" field"))?;
+    object.insert("pages".to_string(), json!([{ "drawing": drawing }]));
+    object.insert("version".to_string(), json!(4));
+    Ok(value)
+}
+
+/// Moves the content of each page's drawing into a single layer, and the
+/// drawing's other fields onto the page.
+fn v4_to_v5(mut value: Value) -> Result<Value> {
+    let pages = value
+        .get_mut("pages")
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| anyhow!("Missing \"pages\" field"))?;
+    for page in pages {
+        let page = page
+            .as_object_mut()
+            .ok_or_else(|| anyhow!("Expected a JSON object for each page"))?;
+        let mut drawing = match page.remove("drawing") {
+            Some(Value::Object(drawing)) => drawing,
+            _ => bail!("Missing \"drawing\" field"),
+        };
+        let shapes = drawing.remove("shapes").unwrap_or_else(|| json!([]));
+        page.extend(drawing);
+        page.insert(
+            "layers".to_string(),
+            json!([{ "name": "Layer 1", "shapes": shapes }]),
+        );
+    }
+    value["version"] = json!(5);
+    Ok(value)
+}
+
+/// Adds an empty asset table.
+fn v5_to_v6(mut value: Value) -> Result<Value> {
+    value["assets"] = json!({});
+    value["version"] = json!(6);
+    Ok(value)
+}
+
+/// Marks the document as one that may hold comments and processing
+/// instructions among its shapes, which older versions can't read; older
+/// documents hold none.
+fn v6_to_v7(mut value: Value) -> Result<Value> {
+    value["version"] = json!(7);
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Document;
+    use crate::shape::Shape;
+
+    /// The same document stored in each older format version, oldest first.
+    const FIXTURES: [&str; (CURRENT_VERSION - 1) as usize] = [
+        include_str!("schema/fixtures/v1.json"),
+        include_str!("schema/fixtures/v2.json"),
+        include_str!("schema/fixtures/v3.json"),
+        include_str!("schema/fixtures/v4.json"),
+        include_str!("schema/fixtures/v5.json"),
+        include_str!("schema/fixtures/v6.json"),
+    ];
+
+    /// Returns the fixture stored in format `version`.
+    fn fixture(version: u64) -> Value {
+        serde_json::from_str(FIXTURES[version as usize - 1]).unwrap()
+    }
+
+    /// Checks that each migration step from `version` on gives the next
+    /// fixture, and that the document loads with the fixture's content.
+    fn check_upgrade(version: u64) {
+        let mut value = fixture(version);
+        for next in version + 1..CURRENT_VERSION {
+            value = MIGRATIONS[next as usize - 2](value).unwrap();
+            assert_eq!(value, fixture(next), "upgrading to version {next}");
+        }
+        let mut current = fixture(CURRENT_VERSION - 1);
+        current["version"] = json!(CURRENT_VERSION);
+        let migrated = migrate(fixture(version)).unwrap();
+        if version == 1 {
+            // Version 1 markup is parsed again by the current parser, which
+            // also stores the fields left to their defaults by the fixtures.
+            let stored = |value| Document::from_value(value).unwrap().to_json().unwrap();
+            assert_eq!(stored(migrated), stored(current));
+        } else {
+            assert_eq!(migrated, current);
+        }
+
+        let document = Document::from_value(fixture(version)).unwrap();
+        let [page] = document.pages() else {
+            panic!("Expected one page");
+        };
+        let [layer] = &page.layers[..] else {
+            panic!("Expected one layer");
+        };
+        assert_eq!(layer.name, "Layer 1");
+        let [Shape::Circle(circle), Shape::Text(text), Shape::Other(desc)] = &layer.shapes[..]
+        else {
+            panic!("Unexpected shapes {:?}", layer.shapes);
+        };
+        assert_eq!(
+            (circle.r, circle.style.fill.as_deref()),
+            (25.0, Some("lightblue"))
+        );
+        assert_eq!(
+            (text.content.as_str(), text.style.font_size),
+            ("Hi", Some(8.0))
+        );
+        assert_eq!(desc.name, "desc");
+    }
+
+    #[test]
+    fn upgrades_version_1() {
+        check_upgrade(1);
+    }
+
+    #[test]
+    fn version_1_keeps_comments_and_attribute_order() {
+        let value: Value =
+            serde_json::from_str(include_str!("schema/fixtures/v1_comments.json")).unwrap();
+        let document = Document::from_value(value).unwrap();
+        let reloaded = Document::from_json(&document.to_json().unwrap()).unwrap();
+        let svg = reloaded.page_svg(0);
+        for part in [
+            r#"<svg width="70" viewBox="0 0 70 70" height="70""#,
+            "<!-- Background -->\n<rect",
+            r#"<?editor grid="5"?>"#,
+            r#"<circle r="25" cy="35" cx="35" stroke="navy" fill="lightblue"/>"#,
+        ] {
+            assert!(svg.contains(part), "{part} missing from {svg}");
+        }
+    }
+
+    #[test]
+    fn upgrades_version_2() {
+        check_upgrade(2);
+    }
+
+    #[test]
+    fn upgrades_version_3() {
+        check_upgrade(3);
+    }
+
+    #[test]
+    fn upgrades_version_4() {
+        check_upgrade(4);
+    }
+
+    #[test]
+    fn upgrades_version_5() {
+        check_upgrade(5);
+    }
+
+    #[test]
+    fn upgrades_version_6() {
+        check_upgrade(6);
+    }
+
+    #[test]
+    fn unversioned_documents_are_version_1() {
+        assert_eq!(version_of(&fixture(1)).unwrap(), 1);
+        assert!(version_of(&json!({ "version": 0 })).is_err());
+        assert!(version_of(&json!({ "version": "2" })).is_err());
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let error = migrate(json!({ "version": CURRENT_VERSION + 1 })).unwrap_err();
+        assert!(error.to_string().contains("update the application"));
+    }
+
+    #[test]
+    fn rejects_version_1_without_markup() {
+        assert!(migrate(json!({})).is_err());
+        assert!(migrate(json!({ "html": "<html/>" })).is_err());
+    }
+}
@@ -0,0 +1,286 @@
+//! The structured drawing of format version 2, made from the SVG markup
+//! stored by version 1.
+//!
+//! This is a frozen copy of the parser as it was when version 2 was defined,
+//! writing JSON rather than the model: what version 2 means mustn't change
+//! along with the current model, since the later migrations build on it.
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Map, Value};
+
+/// The namespace of SVG elements.
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+
+/// The namespace of attributes like `xml:space`.
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Returns the version 2 drawing described by the SVG document `markup`.
+pub fn drawing(markup: &str) -> Result<Value> {
+    let document = roxmltree::Document::parse(markup).context("Invalid SVG markup")?;
+    let root = document.root_element();
+    if !is_svg(root, "svg") {
+        bail!(
+            "Expected an <svg> root element, found <{}>",
+            qualified_name(root)
+        );
+    }
+
+    let mut drawing = Map::new();
+    let mut other_attributes = Vec::new();
+    for namespace in root.namespaces() {
+        if let Some(prefix) = namespace.name().filter(|&prefix| prefix != "xml") {
+            other_attributes.push(json!([format!("xmlns:{prefix}"), namespace.uri()]));
+        }
+    }
+    for (name, value) in attributes(root) {
+        let parsed_view_box = (name == "viewBox").then(|| view_box(&value)).flatten();
+        match (name.as_str(), parse_number(&value), parsed_view_box) {
+            (_, _, Some(view_box)) => {
+                drawing.insert("view_box".to_string(), view_box);
+            }
+            ("width" | "height", Some(number), _) => {
+                drawing.insert(name, json!(number));
+            }
+            _ => other_attributes.push(json!([name, value])),
+        }
+    }
+    if !other_attributes.is_empty() {
+        drawing.insert("attributes".to_string(), Value::Array(other_attributes));
+    }
+    drawing.insert("shapes".to_string(), shapes(root));
+    Ok(Value::Object(drawing))
+}
+
+/// Returns the view box described by `s`, if valid.
+fn view_box(s: &str) -> Option<Value> {
+    let numbers: Vec<f64> = s
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|part| !part.is_empty())
+        .map(parse_number)
+        .collect::<Option<_>>()?;
+    match numbers[..] {
+        [min_x, min_y, width, height] if width >= 0.0 && height >= 0.0 => Some(json!({
+            "min_x": min_x,
+            "min_y": min_y,
+            "width": width,
+            "height": height,
+        })),
+        _ => None,
+    }
+}
+
+/// Returns the shapes for the child elements of `node`.
+fn shapes(node: roxmltree::Node) -> Value {
+    node.children()
+        .filter(|n| n.is_element())
+        .map(shape)
+        .collect()
+}
+
+/// Returns the shape for the element `node`.
+fn shape(node: roxmltree::Node) -> Value {
+    typed_shape(node).unwrap_or_else(|| element(node))
+}
+
+/// Returns the typed shape for `node`, or `None` if version 2 can't
+/// represent it.
+fn typed_shape(node: roxmltree::Node) -> Option<Value> {
+    if !in_svg_namespace(node) {
+        return None;
+    }
+
+    let mut attributes = attributes(node);
+    let mut shape = Map::new();
+    // `None` if the attribute is present but not a number.
+    let mut take = |shape: &mut Map<String, Value>, name: &str, default: Option<f64>| {
+        let number = take_number(&mut attributes, name)?.or(default);
+        if let Some(number) = number {
+            shape.insert(name.to_string(), json!(number));
+        }
+        Some(())
+    };
+
+    let kind = match node.tag_name().name() {
+        "rect" => {
+            for name in ["x", "y", "width", "height"] {
+                take(&mut shape, name, Some(0.0))?;
+            }
+            take(&mut shape, "rx", None)?;
+            take(&mut shape, "ry", None)?;
+            "rect"
+        }
+        "circle" => {
+            for name in ["cx", "cy", "r"] {
+                take(&mut shape, name, Some(0.0))?;
+            }
+            "circle"
+        }
+        "ellipse" => {
+            for name in ["cx", "cy", "rx", "ry"] {
+                take(&mut shape, name, Some(0.0))?;
+            }
+            "ellipse"
+        }
+        "line" => {
+            for name in ["x1", "y1", "x2", "y2"] {
+                take(&mut shape, name, Some(0.0))?;
+            }
+            "line"
+        }
+        "path" => {
+            let index = attributes.iter().position(|(name, _)| name == "d");
+            let d = index.map(|i| attributes.remove(i).1).unwrap_or_default();
+            shape.insert("d".to_string(), json!(d));
+            "path"
+        }
+        "text" if node.children().all(|n| n.is_text()) => {
+            take(&mut shape, "x", Some(0.0))?;
+            take(&mut shape, "y", Some(0.0))?;
+            let content: String = node.children().filter_map(|n| n.text()).collect();
+            shape.insert("content".to_string(), json!(content));
+            "text"
+        }
+        "g" if node
+            .children()
+            .all(|n| !n.is_text() || n.text().is_some_and(|t| t.trim().is_empty())) =>
+        {
+            shape.insert("children".to_string(), shapes(node));
+            "group"
+        }
+        _ => return None,
+    };
+
+    shape.insert("type".to_string(), json!(kind));
+    let style = style(attributes);
+    if !style.is_empty() {
+        shape.insert("style".to_string(), Value::Object(style));
+    }
+    Some(Value::Object(shape))
+}
+
+/// Returns the style taking its fields from `attributes`.
+fn style(attributes: Vec<(String, String)>) -> Map<String, Value> {
+    let mut style = Map::new();
+    let mut other_attributes = Vec::new();
+    for (name, value) in attributes {
+        match (name.as_str(), parse_number(&value)) {
+            ("fill" | "stroke" | "transform" | "font-family" | "text-anchor", _) => {
+                style.insert(name.replace('-', "_"), json!(value));
+            }
+            ("stroke-width" | "opacity" | "font-size", Some(number)) => {
+                style.insert(name.replace('-', "_"), json!(number));
+            }
+            _ => other_attributes.push(json!([name, value])),
+        }
+    }
+    if !other_attributes.is_empty() {
+        style.insert("attributes".to_string(), Value::Array(other_attributes));
+    }
+    style
+}
+
+/// Returns `node` as an uninterpreted element.
+fn element(node: roxmltree::Node) -> Value {
+    let mut element_attributes = Vec::new();
+    let inherited: Vec<_> = node
+        .parent_element()
+        .map(|parent| parent.namespaces().collect())
+        .unwrap_or_default();
+    for namespace in node.namespaces() {
+        if !inherited.contains(&namespace) {
+            let name = match namespace.name() {
+                Some("xml") => continue,
+                Some(prefix) => format!("xmlns:{prefix}"),
+                None => "xmlns".to_string(),
+            };
+            element_attributes.push(json!([name, namespace.uri()]));
+        }
+    }
+    element_attributes.extend(
+        attributes(node)
+            .into_iter()
+            .map(|(name, value)| json!([name, value])),
+    );
+
+    let content: Vec<Value> = node
+        .children()
+        .filter_map(|child| {
+            if child.is_element() {
+                Some(json!({ "shape": shape(child) }))
+            } else if child.is_text() {
+                child.text().map(|text| json!({ "text": text }))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    let mut element = Map::new();
+    element.insert("type".to_string(), json!("other"));
+    element.insert("name".to_string(), json!(qualified_name(node)));
+    if !element_attributes.is_empty() {
+        element.insert("attributes".to_string(), Value::Array(element_attributes));
+    }
+    if !content.is_empty() {
+        element.insert("content".to_string(), Value::Array(content));
+    }
+    Value::Object(element)
+}
+
+/// Returns the attributes of `node` with their prefixed names.
+fn attributes(node: roxmltree::Node) -> Vec<(String, String)> {
+    node.attributes()
+        .map(|attribute| {
+            let name = match attribute.namespace() {
+                Some(XML_NAMESPACE) => format!("xml:{}", attribute.name()),
+                Some(uri) => match node.lookup_prefix(uri) {
+                    Some(prefix) => format!("{prefix}:{}", attribute.name()),
+                    None => attribute.name().to_string(),
+                },
+                None => attribute.name().to_string(),
+            };
+            (name, attribute.value().to_string())
+        })
+        .collect()
+}
+
+/// Returns the name of `node` as written in the markup.
+fn qualified_name(node: roxmltree::Node) -> String {
+    let name = node.tag_name();
+    match name.namespace() {
+        Some(SVG_NAMESPACE) | None => name.name().to_string(),
+        Some(uri) => match node.lookup_prefix(uri) {
+            Some(prefix) => format!("{prefix}:{}", name.name()),
+            None => name.name().to_string(),
+        },
+    }
+}
+
+/// Returns `true` iff `node` is an SVG element, treating un-namespaced
+/// markup as SVG.
+fn in_svg_namespace(node: roxmltree::Node) -> bool {
+    matches!(node.tag_name().namespace(), Some(SVG_NAMESPACE) | None)
+}
+
+/// Returns `true` iff `node` is the SVG element `name`.
+fn is_svg(node: roxmltree::Node, name: &str) -> bool {
+    in_svg_namespace(node) && node.tag_name().name() == name
+}
+
+/// Removes the attribute `name` from `attributes` and returns its numeric
+/// value, or `None` if it is present but not a number.
+fn take_number(attributes: &mut Vec<(String, String)>, name: &str) -> Option<Option<f64>> {
+    match attributes.iter().position(|(n, _)| n == name) {
+        None => Some(None),
+        Some(i) => {
+            let number = parse_number(&attributes[i].1)?;
+            attributes.remove(i);
+            Some(Some(number))
+        }
+    }
+}
+
+/// Returns `s` as a number, if it is a plain (unitless) SVG number.
+fn parse_number(s: &str) -> Option<f64> {
+    s.trim().parse().ok().filter(|n: &f64| n.is_finite())
+}
@@ -0,0 +1,272 @@
+//! Finding the shapes of a page under the pointer, for selecting them.
+//!
+//! Shapes are hit-tested as they're rendered: each shape that can be edited,
+//! a top-level shape of a visible and unlocked layer, is rendered in a group
+//! of its own, whose bounds usvg computes and whose pixels around the pointer
+//! resvg draws, so that what's under the pointer is what's on screen.
+
+use crate::document::Document;
+use crate::export::render_tree;
+use crate::layer::{layer_id, Layer};
+use crate::page::Page;
+use crate::shape::{Drawing, Group, Shape, Style, ViewBox};
+use anyhow::{anyhow, Context, Result};
+use resvg::tiny_skia::{Color, Pixmap, Transform};
+use resvg::usvg;
+
+/// The prefix of the ids of the groups shapes are rendered in for hit
+/// testing, followed by the shape's layer and position.
+const HIT_ID_PREFIX: &str = "pcl-hit-";
+
+/// The number of pixels on a side of the area drawn around the pointer to
+/// find the shape under it.
+const HIT_SAMPLES: u32 = 8;
+
+/// A shape of a page, by its position among the page's layers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShapeRef {
+    /// The index of the layer holding the shape.
+    pub layer: usize,
+
+    /// The index of the shape among the layer's top-level shapes.
+    pub index: usize,
+}
+
+/// A rectangle in the user coordinates of a page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Bounds {
+    /// Returns `true` iff (`x`, `y`) is at most `margin` away from `self`.
+    pub fn contains(&self, x: f64, y: f64, margin: f64) -> bool {
+        x >= self.x - margin
+            && x <= self.x + self.width + margin
+            && y >= self.y - margin
+            && y <= self.y + self.height + margin
+    }
+}
+
+/// Returns the bounds of the editable shapes of the page at `index` in
+/// `document`, back to front, including their strokes.
+pub fn shape_bounds(document: &Document, index: usize) -> Result<Vec<(ShapeRef, Bounds)>> {
+    let tree = hit_tree(document, index)?;
+    let mut groups = Vec::new();
+    hit_groups(&tree.root, &mut groups);
+    Ok(groups
+        .into_iter()
+        .filter_map(|(shape, group)| Some((shape, group_bounds(group)?)))
+        .collect())
+}
+
+/// Returns the topmost editable shape of the page at `index` in `document`
+/// drawn within `tolerance` of (`x`, `y`), in the page's user coordinates.
+pub fn shape_at(
+    document: &Document,
+    index: usize,
+    x: f64,
+    y: f64,
+    tolerance: f64,
+) -> Result<Option<ShapeRef>> {
+    let mut tree = hit_tree(document, index)?;
+    let mut groups = Vec::new();
+    hit_groups(&tree.root, &mut groups);
+    let candidates: Vec<ShapeRef> = groups
+        .into_iter()
+        .filter(|(_, group)| group_bounds(group).is_some_and(|b| b.contains(x, y, tolerance)))
+        .map(|(shape, _)| shape)
+        .collect();
+    if candidates.is_empty() {
+        return Ok(None);
+    }
+
+    // Draw the square `tolerance` around the point, which resvg draws in the
+    // tree's size, onto a few pixels.
+    let to_size =
+        usvg::utils::view_box_to_transform(tree.view_box.rect, tree.view_box.aspect, tree.size);
+    let tolerance = tolerance.max(f64::EPSILON) as f32;
+    let samples = HIT_SAMPLES as f32 / (2.0 * tolerance);
+    let transform = Transform::from_scale(samples, samples)
+        .pre_translate(tolerance - x as f32, tolerance - y as f32)
+        .pre_concat(to_size.invert().context("The page has no area")?);
+    let mut pixmap = Pixmap::new(HIT_SAMPLES, HIT_SAMPLES)
+        .ok_or_else(|| anyhow!("No pixels to hit-test with"))?;
+
+    for candidate in candidates.into_iter().rev() {
+        show_only(&mut tree.root, candidate);
+        pixmap.fill(Color::TRANSPARENT);
+        resvg::render(&tree, transform, &mut pixmap.as_mut());
+        if pixmap.pixels().iter().any(|pixel| pixel.alpha() > 0) {
+            return Ok(Some(candidate));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns `shape`, on the page at `index` in `document`, as a standalone SVG
+/// file cropped to its bounds, with its layer's opacity.
+pub fn shape_svg(document: &Document, index: usize, shape: ShapeRef) -> Result<String> {
+    let page = document
+        .page(index)
+        .with_context(|| format!("No page {}", index + 1))?;
+    let layer = page.layers.get(shape.layer).context("No such layer")?;
+    let content = layer.shapes.get(shape.index).context("No such shape")?;
+    let bounds = shape_bounds(document, index)?
+        .into_iter()
+        .find_map(|(s, bounds)| (s == shape).then_some(bounds))
+        .context("The shape draws nothing")?;
+
+    let layer = Layer {
+        shapes: vec![content.clone()],
+        ..layer.clone()
+    };
+    let drawing = Drawing {
+        view_box: Some(ViewBox {
+            min_x: bounds.x,
+            min_y: bounds.y,
+            width: bounds.width,
+            height: bounds.height,
+        }),
+        width: Some(bounds.width),
+        height: Some(bounds.height),
+        attributes: page.attributes.clone(),
+        shapes: vec![layer.to_plain_group(&layer_id(&layer.name))],
+        ..Drawing::default()
+    };
+    let svg = document.render_drawing(drawing, |_, asset| asset.data_url());
+    Ok(format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{svg}\n"
+    ))
+}
+
+/// Returns the page at `index` in `document` parsed for hit testing, with
+/// each of its editable shapes in a group identified by [`hit_id`].
+fn hit_tree(document: &Document, index: usize) -> Result<usvg::Tree> {
+    let page = document
+        .page(index)
+        .with_context(|| format!("No page {}", index + 1))?;
+    let drawing = hit_page(page).to_plain_drawing();
+    render_tree(&document.render_drawing(drawing, |_, asset| asset.data_url()))
+}
+
+/// Returns `page` with each of its editable shapes in a group identified by
+/// [`hit_id`].
+fn hit_page(page: &Page) -> Page {
+    let mut page = page.clone();
+    for (layer_index, layer) in page.layers.iter_mut().enumerate() {
+        if layer.locked {
+            // Drawn undetectably, as shapes other than the one tested are,
+            // while what it defines can still be referred to.
+            layer.opacity = 0.0;
+            continue;
+        }
+        for (index, shape) in layer.shapes.iter_mut().enumerate() {
+            let id = hit_id(ShapeRef {
+                layer: layer_index,
+                index,
+            });
+            let mut style = Style::default();
+            style.attributes.push(("id".to_string(), id));
+            let children = vec![std::mem::replace(shape, Shape::Group(Group::default()))];
+            *shape = Shape::Group(Group { children, style });
+        }
+    }
+    page
+}
+
+/// Returns the id of the group `shape` is rendered in for hit testing.
+fn hit_id(shape: ShapeRef) -> String {
+    format!("{HIT_ID_PREFIX}{}-{}", shape.layer, shape.index)
+}
+
+/// Returns the shape rendered in the group identified by `id`, if that's a
+/// group made for hit testing.
+fn hit_shape(id: &str) -> Option<ShapeRef> {
+    let (layer, index) = id.strip_prefix(HIT_ID_PREFIX)?.split_once('-')?;
+    Some(ShapeRef {
+        layer: layer.parse().ok()?,
+        index: index.parse().ok()?,
+    })
+}
+
+/// Appends the groups made for hit testing within `parent` to `found`, back
+/// to front, with the shapes they hold.
+fn hit_groups<'a>(parent: &'a usvg::Group, found: &mut Vec<(ShapeRef, &'a usvg::Group)>) {
+    for node in &parent.children {
+        if let usvg::Node::Group(group) = node {
+            match hit_shape(&group.id) {
+                Some(shape) => found.push((shape, group)),
+                None => hit_groups(group, found),
+            }
+        }
+    }
+}
+
+/// Makes the group holding `shape`, within `parent`, the only group made for
+/// hit testing that's drawn.
+fn show_only(parent: &mut usvg::Group, shape: ShapeRef) {
+    for node in &mut parent.children {
+        if let usvg::Node::Group(group) = node {
+            match hit_shape(&group.id) {
+                Some(held) if held == shape => group.opacity = usvg::Opacity::ONE,
+                Some(_) => group.opacity = usvg::Opacity::ZERO,
+                None => show_only(group, shape),
+            }
+        }
+    }
+}
+
+/// Returns the bounds of what `group` draws, in the page's user coordinates.
+fn group_bounds(group: &usvg::Group) -> Option<Bounds> {
+    let rect = match group.stroke_bounding_box {
+        Some(rect) => rect.transform(group.abs_transform)?.to_rect(),
+        None => group.bounding_box?.transform(group.abs_transform)?,
+    };
+    Some(Bounds {
+        x: rect.x() as f64,
+        y: rect.y() as f64,
+        width: rect.width() as f64,
+        height: rect.height() as f64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapes_are_copied_cropped_to_their_bounds() {
+        let document = Document::from_html(
+            r#"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg"><rect x="10" y="20" width="30" height="40" fill="red"/></svg>"#,
+        )
+        .unwrap();
+        let shape = ShapeRef { layer: 0, index: 0 };
+        assert_eq!(
+            shape_at(&document, 0, 25.0, 40.0, 1.0).unwrap(),
+            Some(shape)
+        );
+        assert_eq!(shape_at(&document, 0, 80.0, 80.0, 1.0).unwrap(), None);
+
+        let svg = shape_svg(&document, 0, shape).unwrap();
+        assert!(svg.contains(r#"viewBox="10 20 30 40""#), "{svg}");
+        assert!(svg.contains(r#"<rect x="10" y="20" width="30" height="40""#));
+    }
+
+    #[test]
+    fn locked_layers_are_not_hit() {
+        let document = Document::from_html(
+            r#"<svg viewBox="0 0 100 100" xmlns="http://www.w3.org/2000/svg">
+<g data-layer="Background" data-locked="true"><rect width="100" height="100" fill="white"/></g>
+<g data-layer="Drawing"><line x1="0" y1="0" x2="100" y2="100" stroke="black"/></g>
+</svg>"#,
+        )
+        .unwrap();
+        let line = ShapeRef { layer: 1, index: 0 };
+        assert_eq!(shape_at(&document, 0, 50.0, 50.0, 1.0).unwrap(), Some(line));
+        assert_eq!(shape_at(&document, 0, 90.0, 10.0, 1.0).unwrap(), None);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::application_state::ApplicationState;
+use crate::notifications::report_failure;
+use crate::selection::{shape_at, shape_bounds, Bounds};
+use crate::shape::ViewBox;
+use dioxus::html::geometry::{ClientPoint, PixelsRect};
+use dioxus::prelude::*;
+use std::rc::Rc;
+
+/// The side, in pixels, of the handles drawn around the selected shape.
+const HANDLE_SIZE: f64 = 8.0;
+
+/// How far, in pixels, from a shape a click still selects it.
+const HIT_TOLERANCE: f64 = 3.0;
+
+/// Where a page is shown on screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Placement {
+    /// The horizontal screen position of the page's user coordinate origin.
+    left: f64,
+
+    /// The vertical screen position of the page's user coordinate origin.
+    top: f64,
+
+    /// The number of pixels per user unit.
+    scale: f64,
+}
+
+impl Placement {
+    /// Returns the placement of a page whose coordinate system is
+    /// `view_box` (if any) shown in `rect`, scaled to fit and centered as
+    /// SVG does by default.
+    fn new(rect: PixelsRect, view_box: Option<ViewBox>) -> Option<Self> {
+        let (x, y) = (rect.origin.x, rect.origin.y);
+        let placement = match view_box.filter(|v| v.width > 0.0 && v.height > 0.0) {
+            Some(v) => {
+                let scale = (rect.size.width / v.width).min(rect.size.height / v.height);
+                Self {
+                    left: x + (rect.size.width - v.width * scale) / 2.0 - v.min_x * scale,
+                    top: y + (rect.size.height - v.height * scale) / 2.0 - v.min_y * scale,
+                    scale,
+                }
+            }
+            None => Self {
+                left: x,
+                top: y,
+                scale: 1.0,
+            },
+        };
+        (placement.scale > 0.0).then_some(placement)
+    }
+
+    /// Returns `point`, on screen, in the page's user coordinates.
+    fn to_page(self, point: ClientPoint) -> (f64, f64) {
+        (
+            (point.x - self.left) / self.scale,
+            (point.y - self.top) / self.scale,
+        )
+    }
+
+    /// Returns `distance`, in user units, rounded to the largest power of ten
+    /// no bigger than a pixel, so that moves don't write needless digits.
+    fn round(self, distance: f64) -> f64 {
+        let step = 10f64.powf((1.0 / self.scale).log10().floor());
+        (distance / step).round() * step
+    }
+}
+
+/// A drag of the selected shape in progress.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Drag {
+    /// Where the page is shown.
+    placement: Placement,
+
+    /// Where the shape was last moved to follow the pointer, in the page's
+    /// user coordinates.
+    x: f64,
+    y: f64,
+
+    /// How far the shape has moved since the drag started.
+    dx: f64,
+    dy: f64,
+
+    /// The revision of the document when the drag started.
+    revision: u64,
+}
+
+/// The displayed page of the current document, whose shapes can be selected
+/// by clicking them and moved by dragging them.
+#[component]
+pub fn PageCanvas(application_state: Signal<ApplicationState>) -> Element {
+    let mut state = application_state;
+
+    // The overlay the selection is drawn on, which receives the pointer.
+    let mut overlay = use_signal(|| None::<Rc<MountedData>>);
+    // The number of pixels per user unit, for sizing the handles.
+    let mut scale = use_signal(|| 1.0);
+    // Whether the pointer is down, which it may no longer be once the shape
+    // under it is found.
+    let mut pressed = use_signal(|| false);
+    let mut drag = use_signal(|| None::<Drag>);
+
+    let (html, view_box) = {
+        let current_state = state.read();
+        let open = current_state.current();
+        let html = open.document.page_html(open.current_page);
+        (html, open.current_page().and_then(|page| page.view_box))
+    };
+    // What the bounds of the selected shape depend on. Finding them renders
+    // the page, so during a drag they are offset rather than found again.
+    let selection_key = use_memo(move || {
+        let current_state = state.read();
+        let open = current_state.current();
+        let revision = drag().map_or(open.revision(), |drag| drag.revision);
+        (open.current_page, open.selection(), revision)
+    });
+    let selection_bounds: Memo<Option<Bounds>> = use_memo(move || {
+        let (page, shape, _) = selection_key();
+        let shape = shape?;
+        shape_bounds(&state.peek().current().document, page)
+            .ok()?
+            .into_iter()
+            .find_map(|(s, bounds)| (s == shape).then_some(bounds))
+    });
+    let selected = move || {
+        let bounds = selection_bounds()?;
+        Some(match drag() {
+            Some(drag) => Bounds {
+                x: bounds.x + drag.dx,
+                y: bounds.y + drag.dy,
+                ..bounds
+            },
+            None => bounds,
+        })
+    };
+
+    let report = move |result| report_failure(state, "Couldn't move the shape", result);
+
+    // Finds where the page is on screen, which scrolling changes.
+    let measure = move || async move {
+        let mounted = overlay.peek().clone()?;
+        let rect = mounted.get_client_rect().await.ok()?;
+        let view_box = state.peek().current().current_page()?.view_box;
+        let placement = Placement::new(rect, view_box)?;
+        scale.set(placement.scale);
+        Some(placement)
+    };
+
+    let pointer_down = move |e: PointerEvent| {
+        if !e.is_primary() {
+            return;
+        }
+        let point = e.client_coordinates();
+        let pointer_id = e.pointer_id();
+        pressed.set(true);
+        spawn(async move {
+            let Some(placement) = measure().await else {
+                return;
+            };
+            let (x, y) = placement.to_page(point);
+            let hit = {
+                let current_state = state.peek();
+                let open = current_state.current();
+                let tolerance = HIT_TOLERANCE / placement.scale;
+                shape_at(&open.document, open.current_page, x, y, tolerance)
+            };
+            let shape = match hit {
+                Ok(shape) => shape,
+                Err(e) => return report(Err(e)),
+            };
+            let mut current_state = state.write();
+            let open = current_state.current_mut();
+            open.select(shape);
+            if shape.is_some() && *pressed.peek() {
+                // The moves of a drag are undone as one change.
+                open.begin_gesture();
+                drag.set(Some(Drag {
+                    placement,
+                    x,
+                    y,
+                    dx: 0.0,
+                    dy: 0.0,
+                    revision: open.revision(),
+                }));
+                // Keep receiving the pointer when it leaves the page.
+                document::eval(&format!(
+                    "document.querySelector('.selection-overlay')?.setPointerCapture({pointer_id})"
+                ));
+            }
+        });
+    };
+
+    let pointer_move = move |e: PointerEvent| {
+        let Some(mut current) = drag() else {
+            return;
+        };
+        let (x, y) = current.placement.to_page(e.client_coordinates());
+        let dx = current.placement.round(x - current.x);
+        let dy = current.placement.round(y - current.y);
+        if dx == 0.0 && dy == 0.0 {
+            return;
+        }
+        let moved = state.write().current_mut().move_selection(dx, dy);
+        if moved.is_err() {
+            drag.set(None);
+            state.write().current_mut().end_gesture();
+            return report(moved);
+        }
+        current.x += dx;
+        current.y += dy;
+        current.dx += dx;
+        current.dy += dy;
+        drag.set(Some(current));
+    };
+
+    let pointer_up = move |_: PointerEvent| {
+        pressed.set(false);
+        if drag.write().take().is_some() {
+            state.write().current_mut().end_gesture();
+        }
+    };
+
+    let handle = scale().recip() * HANDLE_SIZE;
+    let handles: Vec<(f64, f64)> = selected()
+        .map(|b| {
+            let (left, right, top, bottom) = (b.x, b.x + b.width, b.y, b.y + b.height);
+            let (middle, center) = ((top + bottom) / 2.0, (left + right) / 2.0);
+            vec![
+                (left, top),
+                (center, top),
+                (right, top),
+                (right, middle),
+                (right, bottom),
+                (center, bottom),
+                (left, bottom),
+                (left, middle),
+            ]
+        })
+        .unwrap_or_default();
+
+    rsx! {
+        div {
+            class: "page-canvas",
+            div { class: "page-content", dangerous_inner_html: html }
+            svg {
+                class: if drag().is_some() { "selection-overlay dragging" } else { "selection-overlay" },
+                view_box: view_box.map(|v| v.to_string()),
+                onmounted: move |e: MountedEvent| overlay.set(Some(e.data())),
+                onresize: move |_| {
+                    spawn(async move {
+                        measure().await;
+                    });
+                },
+                onpointerdown: pointer_down,
+                onpointermove: pointer_move,
+                onpointerup: pointer_up,
+                onpointercancel: pointer_up,
+                if let Some(b) = selected() {
+                    rect {
+                        class: "selection-bounds",
+                        x: "{b.x}",
+                        y: "{b.y}",
+                        width: "{b.width}",
+                        height: "{b.height}",
+                    }
+                    for (x, y) in handles {
+                        rect {
+                            class: "selection-handle",
+                            x: "{x - handle / 2.0}",
+                            y: "{y - handle / 2.0}",
+                            width: "{handle}",
+                            height: "{handle}",
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
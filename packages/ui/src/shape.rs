@@ -0,0 +1,690 @@
+//! The structured model of a drawing.
+//!
+//! A [`Drawing`] is a typed view of an SVG document: the shapes the
+//! application understands become [`Shape`] variants, while anything else is
+//! kept verbatim as an [`Element`], a comment or a processing instruction, so
+//! that rendering never loses content. The order attributes were written in
+//! is kept too, so that unedited markup is written back as it was read.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// The namespace of SVG elements.
+pub const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+
+/// Attribute name/value pairs, in document order.
+pub type Attributes = Vec<(String, String)>;
+
+/// The root of a drawing, corresponding to an `<svg>` element.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Drawing {
+    /// The user coordinate system, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub view_box: Option<ViewBox>,
+
+    /// The rendered width, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<f64>,
+
+    /// The rendered height, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<f64>,
+
+    /// Attributes of the `<svg>` element the model doesn't interpret.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Attributes,
+
+    /// The order the attributes of the `<svg>` element were written in (see
+    /// [`Style::attribute_order`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attribute_order: Vec<String>,
+
+    /// The comments and processing instructions before the `<svg>` element.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prolog: Vec<Shape>,
+
+    /// The top-level shapes, back to front.
+    #[serde(default)]
+    pub shapes: Vec<Shape>,
+}
+
+/// The `viewBox` of a drawing.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ViewBox {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// A drawable element, or other markup kept in its place.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Shape {
+    Rect(Rect),
+    Circle(Circle),
+    Ellipse(Ellipse),
+    Line(Line),
+    Path(Path),
+    Text(Text),
+    Group(Group),
+    /// An element the model doesn't interpret, kept verbatim.
+    Other(Element),
+    /// A comment, with the text between `<!--` and `-->`.
+    Comment {
+        text: String,
+    },
+    /// A processing instruction.
+    Instruction(Instruction),
+}
+
+/// A `<rect>`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rx: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ry: Option<f64>,
+    #[serde(default, skip_serializing_if = "Style::is_empty")]
+    pub style: Style,
+}
+
+/// A `<circle>`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Circle {
+    pub cx: f64,
+    pub cy: f64,
+    pub r: f64,
+    #[serde(default, skip_serializing_if = "Style::is_empty")]
+    pub style: Style,
+}
+
+/// An `<ellipse>`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Ellipse {
+    pub cx: f64,
+    pub cy: f64,
+    pub rx: f64,
+    pub ry: f64,
+    #[serde(default, skip_serializing_if = "Style::is_empty")]
+    pub style: Style,
+}
+
+/// A `<line>`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Line {
+    pub x1: f64,
+    pub y1: f64,
+    pub x2: f64,
+    pub y2: f64,
+    #[serde(default, skip_serializing_if = "Style::is_empty")]
+    pub style: Style,
+}
+
+/// A `<path>`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Path {
+    /// The path data.
+    pub d: String,
+    #[serde(default, skip_serializing_if = "Style::is_empty")]
+    pub style: Style,
+}
+
+/// A `<text>` holding only character data.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Text {
+    pub x: f64,
+    pub y: f64,
+    /// The characters displayed.
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Style::is_empty")]
+    pub style: Style,
+}
+
+/// A `<g>`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Group {
+    /// The grouped shapes, back to front.
+    #[serde(default)]
+    pub children: Vec<Shape>,
+    #[serde(default, skip_serializing_if = "Style::is_empty")]
+    pub style: Style,
+}
+
+/// An arbitrary element, stored with its (possibly prefixed) name.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Element {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Attributes,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub content: Vec<Node>,
+}
+
+/// A processing instruction, `<?target value?>`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub target: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub value: String,
+}
+
+/// The content of an [`Element`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Node {
+    Shape(Box<Shape>),
+    Text(String),
+}
+
+/// Presentation attributes shared by all shapes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Style {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fill: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stroke: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stroke_width: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opacity: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_family: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_anchor: Option<String>,
+
+    /// Any other attributes (`id`, `class`, …), kept verbatim.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Attributes,
+
+    /// The names of the shape's attributes in the order they were written,
+    /// when that isn't the order the shape writes them in; attributes not
+    /// listed are written after those listed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attribute_order: Vec<String>,
+}
+
+impl ViewBox {
+    /// Returns the view box described by `s` (e.g. `"0 0 70 70"`), if valid.
+    pub fn parse(s: &str) -> Option<Self> {
+        let numbers: Vec<f64> = s
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|part| !part.is_empty())
+            .map(parse_number)
+            .collect::<Option<_>>()?;
+
+        match numbers[..] {
+            [min_x, min_y, width, height] if width >= 0.0 && height >= 0.0 => Some(Self {
+                min_x,
+                min_y,
+                width,
+                height,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ViewBox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.min_x, self.min_y, self.width, self.height
+        )
+    }
+}
+
+impl Style {
+    /// Returns `true` iff `self` sets no attributes.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns a style taking its fields from `attributes`.
+    ///
+    /// Attributes that aren't presentation attributes, or whose value can't
+    /// be represented by the typed field, are kept in `self.attributes`.
+    pub fn from_attributes(attributes: Attributes) -> Self {
+        let mut style = Self::default();
+        for (name, value) in attributes {
+            match (name.as_str(), parse_length(&value)) {
+                ("fill", _) => style.fill = Some(value),
+                ("stroke", _) => style.stroke = Some(value),
+                ("transform", _) => style.transform = Some(value),
+                ("font-family", _) => style.font_family = Some(value),
+                ("text-anchor", _) => style.text_anchor = Some(value),
+                ("stroke-width", Some(n)) => style.stroke_width = Some(n),
+                ("font-size", Some(n)) => style.font_size = Some(n),
+                ("opacity", _) if parse_number(&value).is_some() => {
+                    style.opacity = parse_number(&value)
+                }
+                _ => style.attributes.push((name, value)),
+            }
+        }
+        style
+    }
+
+    /// Appends the attributes of `self` to `attributes`.
+    fn push_attributes(&self, attributes: &mut Attributes) {
+        push_optional(attributes, "fill", &self.fill);
+        push_optional(attributes, "stroke", &self.stroke);
+        push_optional(attributes, "stroke-width", &self.stroke_width);
+        push_optional(attributes, "opacity", &self.opacity);
+        push_optional(attributes, "transform", &self.transform);
+        push_optional(attributes, "font-family", &self.font_family);
+        push_optional(attributes, "font-size", &self.font_size);
+        push_optional(attributes, "text-anchor", &self.text_anchor);
+        attributes.extend(self.attributes.iter().cloned());
+    }
+}
+
+impl Drawing {
+    /// Returns the SVG markup for `self`.
+    pub fn to_svg(&self) -> String {
+        let mut out = String::new();
+        for node in &self.prolog {
+            node.write_svg(&mut out);
+            out.push('\n');
+        }
+        out.push_str("<svg");
+        write_in_order(&mut out, self.root_attributes(), &self.attribute_order);
+        out.push('>');
+        for shape in &self.shapes {
+            out.push('\n');
+            shape.write_svg(&mut out);
+        }
+        out.push_str("\n</svg>");
+        out
+    }
+
+    /// Returns the attributes of the `<svg>` element of `self`, in the order
+    /// it writes them in when none is recorded.
+    pub(crate) fn root_attributes(&self) -> Attributes {
+        let mut attributes = Attributes::new();
+        push_optional(&mut attributes, "viewBox", &self.view_box);
+        push_optional(&mut attributes, "width", &self.width);
+        push_optional(&mut attributes, "height", &self.height);
+        push_attribute(&mut attributes, "xmlns", SVG_NAMESPACE);
+        attributes.extend(self.attributes.iter().cloned());
+        attributes
+    }
+}
+
+impl Shape {
+    /// Returns the SVG element name of `self`, or its DOM node name if it
+    /// isn't an element.
+    pub fn element_name(&self) -> &str {
+        match self {
+            Shape::Rect(_) => "rect",
+            Shape::Circle(_) => "circle",
+            Shape::Ellipse(_) => "ellipse",
+            Shape::Line(_) => "line",
+            Shape::Path(_) => "path",
+            Shape::Text(_) => "text",
+            Shape::Group(_) => "g",
+            Shape::Other(e) => &e.name,
+            Shape::Comment { .. } => "#comment",
+            Shape::Instruction(i) => &i.target,
+        }
+    }
+
+    /// Returns the style of `self`, if it is a typed shape.
+    pub fn style(&self) -> Option<&Style> {
+        match self {
+            Shape::Rect(s) => Some(&s.style),
+            Shape::Circle(s) => Some(&s.style),
+            Shape::Ellipse(s) => Some(&s.style),
+            Shape::Line(s) => Some(&s.style),
+            Shape::Path(s) => Some(&s.style),
+            Shape::Text(s) => Some(&s.style),
+            Shape::Group(s) => Some(&s.style),
+            Shape::Other(_) | Shape::Comment { .. } | Shape::Instruction(_) => None,
+        }
+    }
+
+    /// Returns the mutable style of `self`, if it is a typed shape.
+    pub fn style_mut(&mut self) -> Option<&mut Style> {
+        match self {
+            Shape::Rect(s) => Some(&mut s.style),
+            Shape::Circle(s) => Some(&mut s.style),
+            Shape::Ellipse(s) => Some(&mut s.style),
+            Shape::Line(s) => Some(&mut s.style),
+            Shape::Path(s) => Some(&mut s.style),
+            Shape::Text(s) => Some(&mut s.style),
+            Shape::Group(s) => Some(&mut s.style),
+            Shape::Other(_) | Shape::Comment { .. } | Shape::Instruction(_) => None,
+        }
+    }
+
+    /// Moves `self` by (`dx`, `dy`) in the coordinates it's drawn in.
+    ///
+    /// Untransformed rectangles, circles, ellipses, lines and text have their
+    /// geometry moved; other shapes get a translation ahead of their
+    /// transform.
+    pub fn translate(&mut self, dx: f64, dy: f64) {
+        match self {
+            Shape::Rect(r) if r.style.transform.is_none() => {
+                r.x += dx;
+                r.y += dy;
+            }
+            Shape::Circle(c) if c.style.transform.is_none() => {
+                c.cx += dx;
+                c.cy += dy;
+            }
+            Shape::Ellipse(e) if e.style.transform.is_none() => {
+                e.cx += dx;
+                e.cy += dy;
+            }
+            Shape::Line(l) if l.style.transform.is_none() => {
+                l.x1 += dx;
+                l.y1 += dy;
+                l.x2 += dx;
+                l.y2 += dy;
+            }
+            Shape::Text(t) if t.style.transform.is_none() => {
+                t.x += dx;
+                t.y += dy;
+            }
+            Shape::Other(e) => match e.attributes.iter_mut().find(|(n, _)| n == "transform") {
+                Some((_, transform)) => *transform = translated(Some(transform), dx, dy),
+                None => e
+                    .attributes
+                    .push(("transform".to_string(), translated(None, dx, dy))),
+            },
+            _ => {
+                if let Some(style) = self.style_mut() {
+                    style.transform = Some(translated(style.transform.as_deref(), dx, dy));
+                }
+            }
+        }
+    }
+
+    /// Returns the attributes of `self`, if it is a typed shape, in the order
+    /// it writes them in when none is recorded.
+    pub(crate) fn typed_attributes(&self) -> Option<Attributes> {
+        let mut attributes = Attributes::new();
+        let style = match self {
+            Shape::Rect(r) => {
+                push_attribute(&mut attributes, "x", r.x);
+                push_attribute(&mut attributes, "y", r.y);
+                push_attribute(&mut attributes, "width", r.width);
+                push_attribute(&mut attributes, "height", r.height);
+                push_optional(&mut attributes, "rx", &r.rx);
+                push_optional(&mut attributes, "ry", &r.ry);
+                &r.style
+            }
+            Shape::Circle(c) => {
+                push_attribute(&mut attributes, "cx", c.cx);
+                push_attribute(&mut attributes, "cy", c.cy);
+                push_attribute(&mut attributes, "r", c.r);
+                &c.style
+            }
+            Shape::Ellipse(e) => {
+                push_attribute(&mut attributes, "cx", e.cx);
+                push_attribute(&mut attributes, "cy", e.cy);
+                push_attribute(&mut attributes, "rx", e.rx);
+                push_attribute(&mut attributes, "ry", e.ry);
+                &e.style
+            }
+            Shape::Line(l) => {
+                push_attribute(&mut attributes, "x1", l.x1);
+                push_attribute(&mut attributes, "y1", l.y1);
+                push_attribute(&mut attributes, "x2", l.x2);
+                push_attribute(&mut attributes, "y2", l.y2);
+                &l.style
+            }
+            Shape::Path(p) => {
+                push_attribute(&mut attributes, "d", &p.d);
+                &p.style
+            }
+            Shape::Text(t) => {
+                push_attribute(&mut attributes, "x", t.x);
+                push_attribute(&mut attributes, "y", t.y);
+                &t.style
+            }
+            Shape::Group(g) => &g.style,
+            Shape::Other(_) | Shape::Comment { .. } | Shape::Instruction(_) => return None,
+        };
+        style.push_attributes(&mut attributes);
+        Some(attributes)
+    }
+
+    /// Appends the SVG markup for `self` to `out`.
+    pub fn write_svg(&self, out: &mut String) {
+        match self {
+            Shape::Other(e) => return e.write_svg(out),
+            Shape::Comment { text } => {
+                let _ = write!(out, "<!--{text}-->");
+                return;
+            }
+            Shape::Instruction(i) => {
+                out.push_str("<?");
+                out.push_str(&i.target);
+                if !i.value.is_empty() {
+                    out.push(' ');
+                    out.push_str(&i.value);
+                }
+                out.push_str("?>");
+                return;
+            }
+            _ => {}
+        }
+
+        let name = self.element_name();
+        let (Some(attributes), Some(style)) = (self.typed_attributes(), self.style()) else {
+            return;
+        };
+        out.push('<');
+        out.push_str(name);
+        write_in_order(out, attributes, &style.attribute_order);
+        match self {
+            Shape::Text(t) => {
+                out.push('>');
+                out.push_str(&escape(&t.content));
+                out.push_str("</text>");
+            }
+            Shape::Group(g) => {
+                out.push('>');
+                for child in &g.children {
+                    out.push('\n');
+                    child.write_svg(out);
+                }
+                out.push_str("\n</g>");
+            }
+            _ => out.push_str("/>"),
+        }
+    }
+}
+
+impl Element {
+    /// Appends the markup for `self` to `out`.
+    pub fn write_svg(&self, out: &mut String) {
+        out.push('<');
+        out.push_str(&self.name);
+        write_attributes(out, &self.attributes);
+        if self.content.is_empty() {
+            out.push_str("/>");
+            return;
+        }
+        out.push('>');
+        for node in &self.content {
+            match node {
+                Node::Shape(shape) => shape.write_svg(out),
+                Node::Text(text) => out.push_str(&escape(text)),
+            }
+        }
+        let _ = write!(out, "</{}>", self.name);
+    }
+}
+
+/// The absolute CSS units and their length in user units (CSS pixels).
+const ABSOLUTE_UNITS: &[(&str, f64)] = &[
+    ("px", 1.0),
+    ("pt", 96.0 / 72.0),
+    ("pc", 16.0),
+    ("mm", 96.0 / 25.4),
+    ("cm", 96.0 / 2.54),
+    ("in", 96.0),
+];
+
+/// Returns `s` as a number, if it is a plain (unitless) SVG number.
+pub(crate) fn parse_number(s: &str) -> Option<f64> {
+    s.trim().parse().ok().filter(|n: &f64| n.is_finite())
+}
+
+/// Returns the length `s` in user units, if it is a number, possibly
+/// followed by an absolute CSS unit (`10px`, `2.5mm`, …).
+///
+/// Relative lengths (`50%`, `2em`, …) depend on where they're used, so
+/// they're left to the renderer.
+pub(crate) fn parse_length(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let (number, scale) = ABSOLUTE_UNITS
+        .iter()
+        .find_map(|&(unit, scale)| Some((s.strip_suffix(unit)?, scale)))
+        .unwrap_or((s, 1.0));
+    if number.ends_with(char::is_whitespace) {
+        return None;
+    }
+    parse_number(number).map(|n| n * scale)
+}
+
+/// Returns the `transform` attribute value translating by (`dx`, `dy`) after
+/// `transform`, folding the translation into a leading `translate()` if
+/// there is one.
+fn translated(transform: Option<&str>, dx: f64, dy: f64) -> String {
+    let transform = transform.unwrap_or_default().trim();
+    let leading = transform
+        .strip_prefix("translate")
+        .map(str::trim_start)
+        .and_then(|rest| rest.strip_prefix('('))
+        .and_then(|rest| rest.split_once(')'));
+    if let Some((arguments, rest)) = leading {
+        let numbers: Option<Vec<f64>> = arguments
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|part| !part.is_empty())
+            .map(parse_number)
+            .collect();
+        let offset = match numbers.as_deref() {
+            Some(&[x]) => Some((x, 0.0)),
+            Some(&[x, y]) => Some((x, y)),
+            _ => None,
+        };
+        if let Some((x, y)) = offset {
+            return format!("translate({} {}){rest}", x + dx, y + dy);
+        }
+    }
+    if transform.is_empty() {
+        format!("translate({dx} {dy})")
+    } else {
+        format!("translate({dx} {dy}) {transform}")
+    }
+}
+
+/// Appends the attribute `name` with `value` to `attributes`.
+fn push_attribute(attributes: &mut Attributes, name: &str, value: impl std::fmt::Display) {
+    attributes.push((name.to_string(), value.to_string()));
+}
+
+/// Appends the attribute `name` to `attributes` if `value` is present.
+fn push_optional<T: std::fmt::Display>(attributes: &mut Attributes, name: &str, value: &Option<T>) {
+    if let Some(value) = value {
+        push_attribute(attributes, name, value);
+    }
+}
+
+/// Appends ` name="value"` to `out` for each of `attributes`.
+fn write_attributes(out: &mut String, attributes: &Attributes) {
+    for (name, value) in attributes {
+        let _ = write!(out, " {name}=\"{}\"", escape(value));
+    }
+}
+
+/// Appends `attributes` to `out`, those named in `order` first and in that
+/// order, the others in the order they're given.
+fn write_in_order(out: &mut String, mut attributes: Attributes, order: &[String]) {
+    attributes.sort_by_key(|(name, _)| order.iter().position(|n| n == name).unwrap_or(order.len()));
+    write_attributes(out, &attributes);
+}
+
+/// Returns `s` with XML special characters escaped.
+pub fn escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translations_fold_into_a_leading_translate() {
+        assert_eq!(translated(None, 3.0, 4.0), "translate(3 4)");
+        assert_eq!(
+            translated(Some("translate(10, 20) rotate(5)"), 3.0, 4.0),
+            "translate(13 24) rotate(5)"
+        );
+        assert_eq!(
+            translated(Some(" translate (10)"), 3.0, 4.0),
+            "translate(13 4)"
+        );
+        assert_eq!(
+            translated(Some("rotate(5) translate(10)"), 3.0, 4.0),
+            "translate(3 4) rotate(5) translate(10)"
+        );
+        assert_eq!(
+            translated(Some("translate(1 2 3)"), 3.0, 4.0),
+            "translate(3 4) translate(1 2 3)"
+        );
+    }
+
+    #[test]
+    fn untransformed_geometry_moves() {
+        let mut rect = Shape::Rect(Rect {
+            x: 1.0,
+            y: 2.0,
+            width: 5.0,
+            height: 5.0,
+            rx: None,
+            ry: None,
+            style: Style::default(),
+        });
+        rect.translate(3.0, 4.0);
+        let Shape::Rect(moved) = &rect else {
+            panic!("Expected a rect");
+        };
+        assert_eq!((moved.x, moved.y), (4.0, 6.0));
+        assert_eq!(moved.style.transform, None);
+
+        if let Some(style) = rect.style_mut() {
+            style.transform = Some("scale(2)".into());
+        }
+        rect.translate(1.0, 1.0);
+        let Shape::Rect(moved) = &rect else {
+            panic!("Expected a rect");
+        };
+        assert_eq!((moved.x, moved.y), (4.0, 6.0));
+        assert_eq!(
+            moved.style.transform.as_deref(),
+            Some("translate(1 1) scale(2)")
+        );
+    }
+}
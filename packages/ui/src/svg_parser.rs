@@ -0,0 +1,422 @@
+//! Conversion of SVG markup into the structured drawing model.
+
+use crate::shape::{
+    parse_length, Attributes, Circle, Drawing, Element, Ellipse, Group, Instruction, Line, Node,
+    Path, Rect, Shape, Style, Text, ViewBox, SVG_NAMESPACE,
+};
+use anyhow::{bail, Context, Result};
+
+/// The namespace of attributes like `xml:space`.
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Returns the drawing described by the SVG document `markup`.
+pub fn parse_svg(markup: &str) -> Result<Drawing> {
+    let document = roxmltree::Document::parse(markup).context("Invalid SVG markup")?;
+    let root = document.root_element();
+    if !is_svg(root, "svg") {
+        bail!(
+            "Expected an <svg> root element, found <{}>",
+            qualified_name(root)
+        );
+    }
+
+    let mut drawing = Drawing {
+        prolog: document
+            .root()
+            .children()
+            .take_while(|&n| n != root)
+            .filter_map(comment_or_instruction)
+            .collect(),
+        ..Drawing::default()
+    };
+
+    // Keep prefixed namespace declarations so that prefixed names still resolve.
+    for namespace in root.namespaces() {
+        if let Some(prefix) = namespace.name().filter(|&prefix| prefix != "xml") {
+            drawing
+                .attributes
+                .push((format!("xmlns:{prefix}"), namespace.uri().to_string()));
+        }
+    }
+
+    let attributes = attributes(root);
+    let order = names(&attributes);
+    for (name, value) in attributes {
+        match name.as_str() {
+            "viewBox" if ViewBox::parse(&value).is_some() => {
+                drawing.view_box = ViewBox::parse(&value)
+            }
+            "width" if parse_length(&value).is_some() => drawing.width = parse_length(&value),
+            "height" if parse_length(&value).is_some() => drawing.height = parse_length(&value),
+            _ => drawing.attributes.push((name, value)),
+        }
+    }
+    // Namespace declarations aren't attributes to the parser, so their
+    // position among the attributes isn't kept.
+    let written = names(&drawing.root_attributes());
+    if written
+        .iter()
+        .filter(|name| !name.starts_with("xmlns"))
+        .ne(&order)
+    {
+        drawing.attribute_order = order;
+    }
+
+    drawing.shapes = root.children().filter_map(child).collect();
+    Ok(drawing)
+}
+
+/// Returns the shape for `node`, if it is an element, a comment or a
+/// processing instruction.
+fn child(node: roxmltree::Node) -> Option<Shape> {
+    if node.is_element() {
+        Some(shape(node))
+    } else {
+        comment_or_instruction(node)
+    }
+}
+
+/// Returns the comment or processing instruction `node`, if it is one.
+fn comment_or_instruction(node: roxmltree::Node) -> Option<Shape> {
+    match node.node_type() {
+        roxmltree::NodeType::Comment => node.text().map(|text| Shape::Comment {
+            text: text.to_string(),
+        }),
+        roxmltree::NodeType::PI => node.pi().map(|pi| {
+            Shape::Instruction(Instruction {
+                target: pi.target.to_string(),
+                value: pi.value.unwrap_or_default().to_string(),
+            })
+        }),
+        _ => None,
+    }
+}
+
+/// Returns the shape for the element `node`.
+fn shape(node: roxmltree::Node) -> Shape {
+    typed_shape(node).unwrap_or_else(|| Shape::Other(element(node)))
+}
+
+/// Returns the typed shape for `node`, or `None` if the model can't represent it.
+fn typed_shape(node: roxmltree::Node) -> Option<Shape> {
+    if !in_svg_namespace(node) {
+        return None;
+    }
+
+    let mut attributes = attributes(node);
+    let order = names(&attributes);
+    // `None` if the attribute is present but not a length.
+    let mut take = |name: &str| take_length(&mut attributes, name);
+
+    let shape = match node.tag_name().name() {
+        "rect" => {
+            let (x, y) = (take("x")?.unwrap_or(0.0), take("y")?.unwrap_or(0.0));
+            let width = take("width")?.unwrap_or(0.0);
+            let height = take("height")?.unwrap_or(0.0);
+            let (rx, ry) = (take("rx")?, take("ry")?);
+            Shape::Rect(Rect {
+                x,
+                y,
+                width,
+                height,
+                rx,
+                ry,
+                style: Style::from_attributes(attributes),
+            })
+        }
+        "circle" => {
+            let (cx, cy) = (take("cx")?.unwrap_or(0.0), take("cy")?.unwrap_or(0.0));
+            let r = take("r")?.unwrap_or(0.0);
+            Shape::Circle(Circle {
+                cx,
+                cy,
+                r,
+                style: Style::from_attributes(attributes),
+            })
+        }
+        "ellipse" => {
+            let (cx, cy) = (take("cx")?.unwrap_or(0.0), take("cy")?.unwrap_or(0.0));
+            let (rx, ry) = (take("rx")?.unwrap_or(0.0), take("ry")?.unwrap_or(0.0));
+            Shape::Ellipse(Ellipse {
+                cx,
+                cy,
+                rx,
+                ry,
+                style: Style::from_attributes(attributes),
+            })
+        }
+        "line" => {
+            let (x1, y1) = (take("x1")?.unwrap_or(0.0), take("y1")?.unwrap_or(0.0));
+            let (x2, y2) = (take("x2")?.unwrap_or(0.0), take("y2")?.unwrap_or(0.0));
+            Shape::Line(Line {
+                x1,
+                y1,
+                x2,
+                y2,
+                style: Style::from_attributes(attributes),
+            })
+        }
+        "path" => {
+            let index = attributes.iter().position(|(name, _)| name == "d");
+            let d = index.map(|i| attributes.remove(i).1).unwrap_or_default();
+            Shape::Path(Path {
+                d,
+                style: Style::from_attributes(attributes),
+            })
+        }
+        "text" if node.children().all(|n| n.is_text()) => {
+            let (x, y) = (take("x")?.unwrap_or(0.0), take("y")?.unwrap_or(0.0));
+            Shape::Text(Text {
+                x,
+                y,
+                content: node.children().filter_map(|n| n.text()).collect(),
+                style: Style::from_attributes(attributes),
+            })
+        }
+        "g" if node
+            .children()
+            .all(|n| !n.is_text() || n.text().is_some_and(|t| t.trim().is_empty())) =>
+        {
+            Shape::Group(Group {
+                children: node.children().filter_map(child).collect(),
+                style: Style::from_attributes(attributes),
+            })
+        }
+        _ => return None,
+    };
+    Some(with_attribute_order(shape, order))
+}
+
+/// Returns the typed `shape` recording `order`, the names of its attributes
+/// in the order they were written, unless that's the order it writes them in.
+fn with_attribute_order(mut shape: Shape, order: Vec<String>) -> Shape {
+    let written = shape.typed_attributes().map(|a| names(&a));
+    if written.is_some_and(|written| written != order) {
+        if let Some(style) = shape.style_mut() {
+            style.attribute_order = order;
+        }
+    }
+    shape
+}
+
+/// Returns the names of `attributes`, in order.
+fn names(attributes: &Attributes) -> Vec<String> {
+    attributes.iter().map(|(name, _)| name.clone()).collect()
+}
+
+/// Returns `node` as an uninterpreted element.
+fn element(node: roxmltree::Node) -> Element {
+    let mut attributes = Attributes::new();
+
+    // Namespaces first declared on `node` must be redeclared for its name to resolve.
+    let inherited: Vec<_> = node
+        .parent_element()
+        .map(|parent| parent.namespaces().collect())
+        .unwrap_or_default();
+    for namespace in node.namespaces() {
+        if !inherited.contains(&namespace) {
+            let name = match namespace.name() {
+                Some("xml") => continue,
+                Some(prefix) => format!("xmlns:{prefix}"),
+                None => "xmlns".to_string(),
+            };
+            attributes.push((name, namespace.uri().to_string()));
+        }
+    }
+    attributes.extend(self::attributes(node));
+
+    let content = node
+        .children()
+        .filter_map(|node| {
+            if node.is_text() {
+                node.text().map(|text| Node::Text(text.to_string()))
+            } else {
+                child(node).map(|shape| Node::Shape(Box::new(shape)))
+            }
+        })
+        .collect();
+
+    Element {
+        name: qualified_name(node),
+        attributes,
+        content,
+    }
+}
+
+/// Returns the attributes of `node` with their prefixed names.
+fn attributes(node: roxmltree::Node) -> Attributes {
+    node.attributes()
+        .map(|attribute| {
+            let name = match attribute.namespace() {
+                Some(XML_NAMESPACE) => format!("xml:{}", attribute.name()),
+                Some(uri) => match node.lookup_prefix(uri) {
+                    Some(prefix) => format!("{prefix}:{}", attribute.name()),
+                    None => attribute.name().to_string(),
+                },
+                None => attribute.name().to_string(),
+            };
+            (name, attribute.value().to_string())
+        })
+        .collect()
+}
+
+/// Returns the name of `node` as written in the markup.
+fn qualified_name(node: roxmltree::Node) -> String {
+    let name = node.tag_name();
+    match name.namespace() {
+        Some(SVG_NAMESPACE) | None => name.name().to_string(),
+        Some(uri) => match node.lookup_prefix(uri) {
+            Some(prefix) => format!("{prefix}:{}", name.name()),
+            None => name.name().to_string(),
+        },
+    }
+}
+
+/// Returns `true` iff `node` is an SVG element, treating un-namespaced
+/// markup (as found inline in HTML) as SVG.
+fn in_svg_namespace(node: roxmltree::Node) -> bool {
+    matches!(node.tag_name().namespace(), Some(SVG_NAMESPACE) | None)
+}
+
+/// Returns `true` iff `node` is the SVG element `name`.
+fn is_svg(node: roxmltree::Node, name: &str) -> bool {
+    in_svg_namespace(node) && node.tag_name().name() == name
+}
+
+/// Removes the attribute `name` from `attributes` and returns its value in
+/// user units, or `None` if it is present but not an absolute length.
+fn take_length(attributes: &mut Attributes, name: &str) -> Option<Option<f64>> {
+    match attributes.iter().position(|(n, _)| n == name) {
+        None => Some(None),
+        Some(i) => {
+            let number = parse_length(&attributes[i].1)?;
+            attributes.remove(i);
+            Some(Some(number))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns `markup` parsed, written and parsed again, checking that the
+    /// second parse finds the same drawing.
+    fn round_trip(markup: &str) -> String {
+        let drawing = parse_svg(markup).unwrap();
+        let written = drawing.to_svg();
+        assert_eq!(parse_svg(&written).unwrap(), drawing, "{written}");
+        written
+    }
+
+    #[test]
+    fn typed_shapes_round_trip() {
+        let markup = r#"<svg viewBox="0 0 70 70" xmlns="http://www.w3.org/2000/svg">
+<rect x="1" y="2" width="3" height="4" rx="1" fill="red" id="r"/>
+<circle cx="35" cy="35" r="25" stroke="blue" stroke-width="2"/>
+<ellipse cx="1" cy="2" rx="3" ry="4"/>
+<line x1="0" y1="0" x2="10" y2="10" transform="rotate(45)"/>
+<path d="M0 0L10 10" opacity="0.5"/>
+<text x="35" y="40" text-anchor="middle" font-size="8">A &amp; B</text>
+<g class="c">
+<rect x="0" y="0" width="1" height="1"/>
+</g>
+</svg>"#;
+        let drawing = parse_svg(markup).unwrap();
+        assert!(drawing
+            .shapes
+            .iter()
+            .all(|shape| !matches!(shape, Shape::Other(_))));
+        assert_eq!(round_trip(markup), markup);
+    }
+
+    #[test]
+    fn uninterpreted_elements_round_trip() {
+        let markup = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+<defs><linearGradient id="g"><stop offset="0" stop-color="red"/></linearGradient></defs>
+<use xlink:href="#g" x="50%"/>
+<text x="1" y="2">A<tspan>B</tspan></text>
+</svg>"##;
+        let written = round_trip(markup);
+        assert!(written.contains(r##"<use xlink:href="#g" x="50%"/>"##));
+        assert!(written.contains("<text x=\"1\" y=\"2\">A<tspan>B</tspan></text>"));
+    }
+
+    #[test]
+    fn comments_and_instructions_are_kept() {
+        let markup = r#"<!-- Generator: test -->
+<?app-data version="1"?>
+<svg xmlns="http://www.w3.org/2000/svg">
+<!-- top -->
+<g>
+<!-- in group -->
+<rect x="0" y="0" width="1" height="1"/>
+</g>
+<defs><?in-element?></defs>
+</svg>"#;
+        let drawing = parse_svg(markup).unwrap();
+        assert_eq!(drawing.prolog.len(), 2);
+        assert_eq!(
+            drawing.shapes[0],
+            Shape::Comment {
+                text: " top ".to_string()
+            }
+        );
+        let written = round_trip(markup);
+        for part in [
+            "<!-- Generator: test -->\n<?app-data version=\"1\"?>\n<svg",
+            "<!-- top -->",
+            "<g>\n<!-- in group -->\n<rect",
+            "<defs><?in-element?></defs>",
+        ] {
+            assert!(written.contains(part), "{part} not in {written}");
+        }
+    }
+
+    #[test]
+    fn attribute_order_is_kept() {
+        let markup = r#"<svg xmlns="http://www.w3.org/2000/svg" height="10" viewBox="0 0 10 10">
+<rect fill="red" id="a" height="4" width="3" y="2" x="1"/>
+<g id="g" transform="scale(2)"/>
+</svg>"#;
+        let written = round_trip(markup);
+        assert!(written.starts_with(r#"<svg height="10" viewBox="0 0 10 10""#));
+        assert!(written.contains(r#"<rect fill="red" id="a" height="4" width="3" y="2" x="1"/>"#));
+        assert!(written.contains(r#"<g id="g" transform="scale(2)">"#));
+    }
+
+    #[test]
+    fn canonical_order_is_not_recorded() {
+        let drawing = parse_svg(
+            r#"<svg viewBox="0 0 1 1" xmlns="http://www.w3.org/2000/svg"><circle cx="0" cy="0" r="1"/></svg>"#,
+        )
+        .unwrap();
+        assert!(drawing.attribute_order.is_empty());
+        assert!(drawing.shapes[0].style().unwrap().is_empty());
+    }
+
+    #[test]
+    fn absolute_lengths_are_typed() {
+        let drawing = parse_svg(
+            r#"<svg width="1in" height="10mm" xmlns="http://www.w3.org/2000/svg">
+<rect x="10px" y="3pt" width="1pc" height="2" stroke-width="2px"/>
+<rect x="50%" y="0" width="1" height="1"/>
+</svg>"#,
+        )
+        .unwrap();
+        assert_eq!(drawing.width, Some(96.0));
+        assert!((drawing.height.unwrap() - 37.795).abs() < 0.001);
+        let Shape::Rect(rect) = &drawing.shapes[0] else {
+            panic!("Expected a rect, found {:?}", drawing.shapes[0]);
+        };
+        assert_eq!((rect.x, rect.y, rect.width), (10.0, 4.0, 16.0));
+        assert_eq!(rect.style.stroke_width, Some(2.0));
+        assert!(matches!(drawing.shapes[1], Shape::Other(_)));
+    }
+
+    #[test]
+    fn rejects_non_svg_roots() {
+        assert!(parse_svg("<html/>").is_err());
+        assert!(parse_svg("<svg").is_err());
+    }
+}
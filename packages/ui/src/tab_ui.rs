@@ -0,0 +1,59 @@
+use crate::application_state::{ApplicationState, DocumentAction};
+use dioxus::prelude::*;
+
+/// A strip of tabs, one per open document, for switching between them,
+/// closing them and opening new ones.
+#[component]
+pub fn DocumentTabs(application_state: Signal<ApplicationState>) -> Element {
+    let mut state = application_state;
+    let (tabs, current) = {
+        let current_state = state.read();
+        let tabs: Vec<(String, bool)> = current_state
+            .documents()
+            .iter()
+            .map(|document| (document.title(), document.is_dirty()))
+            .collect();
+        (tabs, current_state.current_index())
+    };
+
+    // Closing asks about unsaved changes through the platform's prompt.
+    let mut close = move |index: usize| {
+        state.write().select_document(index);
+        if state.write().check_unsaved_changes(DocumentAction::Close) {
+            state.write().close_document();
+        }
+    };
+
+    rsx! {
+        div {
+            class: "document-tabs",
+            for (index, (title, dirty)) in tabs.into_iter().enumerate() {
+                div {
+                    key: "{index}",
+                    class: if index == current { "document-tab document-tab-current" } else { "document-tab" },
+                    title: "{title}",
+                    onclick: move |_| state.write().select_document(index),
+                    if dirty {
+                        span { class: "document-tab-dirty", title: "Unsaved changes", "•" }
+                    }
+                    span { class: "document-tab-title", "{title}" }
+                    button {
+                        class: "document-tab-close",
+                        title: "Close document",
+                        onclick: move |e: MouseEvent| {
+                            e.stop_propagation();
+                            close(index);
+                        },
+                        "✕"
+                    }
+                }
+            }
+            button {
+                class: "document-tab-new",
+                title: "New document",
+                onclick: move |_| state.write().new_document(),
+                "＋"
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use crate::application_state::{ApplicationState, DocumentAction};
+use dioxus::prelude::*;
+
+/// A dialog asking whether to save the changes to the current document that
+/// the pending action would lose, shown while
+/// `ApplicationState::pending_action` is set.
+///
+/// `on_save` saves the document in the platform's way, returning `true` iff
+/// it was saved; `on_proceed` goes on with the action once the changes are
+/// saved or discarded.
+#[component]
+pub fn UnsavedChangesPrompt(
+    application_state: Signal<ApplicationState>,
+    on_save: Callback<(), bool>,
+    on_proceed: EventHandler<DocumentAction>,
+) -> Element {
+    let mut state = application_state;
+    let Some(action) = state.read().pending_action else {
+        return rsx! {};
+    };
+    let title = state.read().document_title();
+
+    let save = move |_| {
+        if on_save.call(()) {
+            state.write().pending_action = None;
+            on_proceed.call(action);
+        }
+    };
+    let discard = move |_| {
+        state.write().pending_action = None;
+        on_proceed.call(action);
+    };
+    let cancel = move |_| state.write().pending_action = None;
+
+    rsx! {
+        div {
+            class: "dialog-overlay",
+            div {
+                class: "dialog",
+                h3 { "Save changes to \"{title}\"?" }
+                p { "Your changes will be lost if you don't save them." }
+                div {
+                    class: "dialog-buttons",
+                    button { class: "dialog-button", onclick: cancel, "Cancel" }
+                    button { class: "dialog-button", onclick: discard, "Discard" }
+                    button {
+                        class: "dialog-button dialog-button-primary",
+                        onclick: save,
+                        "Save"
+                    }
+                }
+            }
+        }
+    }
+}
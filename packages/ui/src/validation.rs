@@ -0,0 +1,325 @@
+//! Validation of SVG markup, with diagnostics located in the markup.
+
+use crate::shape::{parse_number, ViewBox, SVG_NAMESPACE};
+use std::fmt;
+
+/// The elements defined by SVG.
+const SVG_ELEMENTS: &[&str] = &[
+    "a",
+    "animate",
+    "animateMotion",
+    "animateTransform",
+    "circle",
+    "clipPath",
+    "defs",
+    "desc",
+    "ellipse",
+    "feBlend",
+    "feColorMatrix",
+    "feComponentTransfer",
+    "feComposite",
+    "feConvolveMatrix",
+    "feDiffuseLighting",
+    "feDisplacementMap",
+    "feDistantLight",
+    "feDropShadow",
+    "feFlood",
+    "feFuncA",
+    "feFuncB",
+    "feFuncG",
+    "feFuncR",
+    "feGaussianBlur",
+    "feImage",
+    "feMerge",
+    "feMergeNode",
+    "feMorphology",
+    "feOffset",
+    "fePointLight",
+    "feSpecularLighting",
+    "feSpotLight",
+    "feTile",
+    "feTurbulence",
+    "filter",
+    "foreignObject",
+    "g",
+    "image",
+    "line",
+    "linearGradient",
+    "marker",
+    "mask",
+    "metadata",
+    "mpath",
+    "path",
+    "pattern",
+    "polygon",
+    "polyline",
+    "radialGradient",
+    "rect",
+    "script",
+    "set",
+    "stop",
+    "style",
+    "svg",
+    "switch",
+    "symbol",
+    "text",
+    "textPath",
+    "title",
+    "tspan",
+    "use",
+    "view",
+];
+
+/// The attributes holding a single coordinate or length.
+const COORDINATE_ATTRIBUTES: &[&str] = &[
+    "x", "y", "width", "height", "cx", "cy", "r", "rx", "ry", "x1", "y1", "x2", "y2",
+];
+
+/// The units a length may carry.
+const LENGTH_UNITS: &[&str] = &["px", "pt", "pc", "mm", "cm", "in", "em", "ex", "%"];
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The markup can't be loaded.
+    Error,
+
+    /// The markup loads, but may not render as intended.
+    Warning,
+}
+
+/// A problem found in SVG markup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+
+    /// Where the problem is in the markup, if the user wrote that markup.
+    pub position: Option<Position>,
+
+    pub message: String,
+}
+
+/// A position within markup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// The 1-based line.
+    pub line: u32,
+
+    /// The 1-based column.
+    pub column: u32,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        if let Some(Position { line, column }) = self.position {
+            write!(f, "line {line}, column {column}: ")?;
+        }
+        write!(f, "{severity}: {}", self.message)
+    }
+}
+
+/// The error for markup that can't be loaded, carrying its diagnostics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidSvg {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl fmt::Display for InvalidSvg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid SVG markup")?;
+        for diagnostic in &self.diagnostics {
+            write!(f, "\n{diagnostic}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidSvg {}
+
+/// Returns the problems found in the SVG document `markup`.
+pub fn validate_svg(markup: &str) -> Vec<Diagnostic> {
+    let document = match roxmltree::Document::parse(markup) {
+        Ok(document) => document,
+        Err(error) => return vec![parse_error(markup, &error)],
+    };
+
+    let mut diagnostics = Vec::new();
+    let mut report = |pos: usize, severity, message| {
+        let pos = document.text_pos_at(pos);
+        diagnostics.push(Diagnostic {
+            severity,
+            position: Some(Position {
+                line: pos.row,
+                column: pos.col,
+            }),
+            message,
+        });
+    };
+
+    let root = document.root_element();
+    if !is_svg_element(root) || root.tag_name().name() != "svg" {
+        report(
+            root.range().start,
+            Severity::Error,
+            format!(
+                "expected an <svg> root element, found <{}>",
+                root.tag_name().name()
+            ),
+        );
+        return diagnostics;
+    }
+
+    for node in root.descendants().filter(|n| n.is_element()) {
+        let name = node.tag_name().name();
+        if !is_svg_element(node) {
+            // Elements of other namespaces (editor metadata, …) are not our concern.
+            continue;
+        }
+        if !SVG_ELEMENTS.contains(&name) {
+            report(
+                node.range().start,
+                Severity::Warning,
+                format!("unknown element <{name}>"),
+            );
+            continue;
+        }
+
+        for attribute in node.attributes().filter(|a| a.namespace().is_none()) {
+            let (attribute_name, value) = (attribute.name(), attribute.value());
+            let problem = if attribute_name == "viewBox" && ViewBox::parse(value).is_none() {
+                Some(format!(
+                    "invalid viewBox \"{value}\": expected four numbers, with a non-negative size"
+                ))
+            } else if COORDINATE_ATTRIBUTES.contains(&attribute_name)
+                && !is_coordinate(name, attribute_name, value)
+            {
+                Some(format!(
+                    "non-numeric value \"{value}\" for {attribute_name} on <{name}>"
+                ))
+            } else {
+                None
+            };
+
+            if let Some(message) = problem {
+                report(attribute.range_value().start, Severity::Warning, message);
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Returns the diagnostics for `markup`, or an [`InvalidSvg`] error if any of
+/// them prevents loading it.
+pub fn check_svg(markup: &str) -> Result<Vec<Diagnostic>, InvalidSvg> {
+    let diagnostics = validate_svg(markup);
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        Err(InvalidSvg { diagnostics })
+    } else {
+        Ok(diagnostics)
+    }
+}
+
+/// Returns the diagnostic for the XML syntax error `error` in `markup`.
+fn parse_error(markup: &str, error: &roxmltree::Error) -> Diagnostic {
+    let (line, column, message) = match error {
+        // Reported at the start of the input, but only detectable at its end.
+        roxmltree::Error::UnclosedRootNode => {
+            let line = markup.lines().count().max(1);
+            let column = markup.lines().last().map_or(0, |l| l.chars().count()) + 1;
+            (
+                line as u32,
+                column as u32,
+                "unclosed element at end of markup".to_string(),
+            )
+        }
+        _ => {
+            let pos = error.pos();
+            let message = error.to_string();
+            let message = message
+                .strip_suffix(&format!(" at {pos}"))
+                .unwrap_or(&message)
+                .to_string();
+            (pos.row, pos.col, message)
+        }
+    };
+    Diagnostic {
+        severity: Severity::Error,
+        position: Some(Position { line, column }),
+        message,
+    }
+}
+
+/// Returns `true` iff `node` is in the SVG namespace (or in none, as inline in HTML).
+fn is_svg_element(node: roxmltree::Node) -> bool {
+    matches!(node.tag_name().namespace(), Some(SVG_NAMESPACE) | None)
+}
+
+/// Returns `true` iff `value` is valid for the coordinate `attribute` of `element`.
+fn is_coordinate(element: &str, attribute: &str, value: &str) -> bool {
+    let keywords_allowed = matches!(attribute, "width" | "height" | "rx" | "ry");
+    if keywords_allowed && value.trim() == "auto" {
+        return true;
+    }
+
+    // Text positions may list one coordinate per character.
+    if matches!(element, "text" | "tspan") {
+        let mut lengths = value
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|part| !part.is_empty())
+            .peekable();
+        lengths.peek().is_some() && lengths.all(is_length)
+    } else {
+        is_length(value)
+    }
+}
+
+/// Returns `true` iff `value` is a number, optionally followed by a unit.
+fn is_length(value: &str) -> bool {
+    let value = value.trim();
+    let number = LENGTH_UNITS
+        .iter()
+        .find_map(|unit| value.strip_suffix(unit))
+        .unwrap_or(value);
+    parse_number(number).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locates_problems_in_the_markup() {
+        let diagnostics = validate_svg(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\">\n  <rect x=\"one\"/>\n  <blink/>\n</svg>",
+        );
+        let lines: Vec<String> = diagnostics.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            [
+                "line 2, column 12: warning: non-numeric value \"one\" for x on <rect>",
+                "line 3, column 3: warning: unknown element <blink>",
+            ]
+        );
+    }
+
+    #[test]
+    fn syntax_errors_prevent_loading() {
+        let error = check_svg("<svg>\n<rect>\n</svg>").unwrap_err();
+        let [diagnostic] = &error.diagnostics[..] else {
+            panic!("Expected one diagnostic, found {:?}", error.diagnostics);
+        };
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.position.map(|p| p.line), Some(3));
+        assert!(check_svg("<svg").is_err());
+    }
+
+    #[test]
+    fn accepts_lengths_with_units() {
+        let markup = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100%"><rect width="2em" height="auto"/><text x="1 2,3">t</text></svg>"#;
+        assert_eq!(check_svg(markup), Ok(Vec::new()));
+    }
+}
@@ -1,43 +1,80 @@
+use crate::actions::{action, menu_actions, perform, proceed, Action, ActionHost, MenuName};
 use crate::application_state::ApplicationState;
-use crate::Document;
+use crate::export_ui::ExportDialog;
+use crate::file_format::{openable_extensions, FileFormat};
+use crate::keybindings::use_pressed_actions;
+use crate::notifications::{report_failure, report_warning};
+use crate::open_document::OpenDocument;
+use crate::unsaved_changes_ui::UnsavedChangesPrompt;
+use anyhow::{anyhow, Context, Result};
 use dioxus::prelude::*;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 // Web API imports (available on all platforms for development ease)
-use js_sys::Array;
-use serde_json::{from_str, to_string_pretty};
+use js_sys::{Array, Reflect, Uint8Array};
 use wasm_bindgen::closure::Closure;
-use wasm_bindgen::{JsCast, JsValue};
-use web_sys::{console::log_1, window, Blob, FileReader, HtmlAnchorElement, HtmlInputElement, Url};
+use wasm_bindgen::JsCast;
+use web_sys::{
+    console::log_1, window, Blob, FileReader, HtmlAnchorElement, HtmlElement, HtmlInputElement, Url,
+};
 
-/// Menu button for creating a new document.
-#[component]
-fn NewButton(mut state: Signal<ApplicationState>) -> Element {
-    let handle_click = move |_| {
-        state.write().new_document();
-    };
+/// The id of the hidden file input for opening documents.
+const OPEN_INPUT_ID: &str = "file-input-hidden";
 
-    rsx! {
-        button {
-            class: "menu-button",
-            title: "New document (Ctrl+N)",
-            onclick: handle_click,
-            "New"
-        }
+/// The id of the hidden file input for inserting images.
+const IMAGE_INPUT_ID: &str = "image-input-hidden";
+
+/// The web app's side of carrying out actions.
+#[derive(Clone, Copy, PartialEq)]
+struct WebHost {
+    state: Signal<ApplicationState>,
+
+    /// The format chosen for Save As.
+    save_as_format: Signal<FileFormat>,
+}
+
+impl ActionHost for WebHost {
+    fn state(&self) -> Signal<ApplicationState> {
+        self.state
+    }
+
+    fn offers(&self, id: &str) -> bool {
+        // Sharing is for mobile, and closing the tab is up to the browser.
+        !matches!(id, "share" | "quit")
+    }
+
+    fn open(&self) -> Result<()> {
+        // The document is opened once a file is chosen and read.
+        click_element(OPEN_INPUT_ID)
+    }
+
+    fn save(&self) -> Result<bool> {
+        save(self.state).map(|()| true)
+    }
+
+    fn save_as(&self) -> Result<()> {
+        let mut state = self.state;
+        let saved = state.read().current().stamped_for_saving();
+        let format = (self.save_as_format)();
+        let content = saved.to_bytes(format)?;
+        download_file(&content, &format.file_name("document"))
+            .map_err(|e| anyhow!("Failed to download file: {e}"))?;
+        state.write().mark_saved(&saved);
+        Ok(())
+    }
+
+    fn insert_image(&self) -> Result<()> {
+        click_element(IMAGE_INPUT_ID)
     }
 }
 
-/// Hidden file input and open button for loading documents.
+/// Hidden file input for choosing documents to open.
 #[component]
-fn OpenButton(mut state: Signal<ApplicationState>) -> Element {
+fn OpenInput(mut state: Signal<ApplicationState>) -> Element {
     let mut file_input_ref = use_signal(|| None::<HtmlInputElement>);
     let mut onload_closure = use_signal(|| None::<Closure<dyn FnMut(web_sys::Event)>>);
 
-    let handle_open = move |_| {
-        if let Some(input) = file_input_ref.read().as_ref() {
-            input.click();
-        }
-    };
-
     let handle_file_input_mounted = move |element: MountedEvent| {
         if let Some(web_element) = element.downcast::<web_sys::Element>() {
             match web_element.clone().dyn_into::<HtmlInputElement>() {
@@ -45,7 +82,10 @@ fn OpenButton(mut state: Signal<ApplicationState>) -> Element {
                     *file_input_ref.write() = Some(input);
                 }
                 Err(e) => {
-                    eprintln!("Failed to cast element to HtmlInputElement: {e:?}");
+                    state.write().notifications.error(
+                        "File input unavailable",
+                        &anyhow!("Failed to cast element to HtmlInputElement: {e:?}"),
+                    );
                 }
             }
         }
@@ -59,11 +99,15 @@ fn OpenButton(mut state: Signal<ApplicationState>) -> Element {
             .and_then(|files| files.get(0))
         {
             log_1(&format!("Selected file: {}", file.name()).into());
+            let file_name = file.name();
 
             let file_reader = match FileReader::new() {
                 Ok(reader) => reader,
                 Err(_) => {
-                    eprintln!("Failed to create FileReader - browser API unavailable");
+                    state.write().notifications.error(
+                        "Failed to read file",
+                        &anyhow!("Failed to create FileReader - browser API unavailable"),
+                    );
                     return;
                 }
             };
@@ -72,26 +116,24 @@ fn OpenButton(mut state: Signal<ApplicationState>) -> Element {
 
             let onload = Closure::<dyn FnMut(web_sys::Event)>::new(move |_| {
                 if let Ok(result) = file_reader_clone.result() {
-                    if let Some(text) = result.as_string() {
-                        log_1(&format!("File content read: {} chars", text.len()).into());
-
-                        match from_str::<Document>(&text) {
-                            Ok(document) => {
-                                log_1(&"Successfully parsed document".into());
-                                state_clone.write().the_only_document = document;
-                                state_clone.write().current_file_path = None;
-                            }
-                            Err(e) => {
-                                eprintln!("Parse error: {e}");
-                            }
-                        }
-                    }
+                    let content = Uint8Array::new(&result).to_vec();
+                    log_1(&format!("File content read: {} bytes", content.len()).into());
+
+                    let result = state_clone.write().open_file_content(&file_name, &content);
+                    report_failure(
+                        state_clone,
+                        &format!("Couldn't open \"{file_name}\""),
+                        result,
+                    );
                 }
             });
 
             file_reader.set_onload(Some(onload.as_ref().unchecked_ref()));
-            if let Err(_) = file_reader.read_as_text(&file) {
-                eprintln!("Failed to read file as text");
+            if file_reader.read_as_array_buffer(&file).is_err() {
+                state.write().notifications.error(
+                    "Failed to read file",
+                    &anyhow!("The browser couldn't read \"{}\"", file.name()),
+                );
                 return;
             }
 
@@ -99,87 +141,283 @@ fn OpenButton(mut state: Signal<ApplicationState>) -> Element {
         }
     };
 
+    let accept = openable_extensions()
+        .into_iter()
+        .map(|extension| format!(".{extension}"))
+        .collect::<Vec<_>>()
+        .join(",");
+
     rsx! {
         input {
             r#type: "file",
-            accept: ".json",
+            accept: "{accept}",
             style: "display: none",
-            id: "file-input-hidden",
+            id: OPEN_INPUT_ID,
             onmounted: handle_file_input_mounted,
             onchange: handle_file_change
         }
 
-        button {
-            class: "menu-button",
-            title: "Open document (Ctrl+O)",
-            onclick: handle_open,
-            "Open"
+    }
+}
+
+/// Menu button listing the recent documents, to reopen one.
+#[component]
+fn RecentButton(mut state: Signal<ApplicationState>) -> Element {
+    let mut open = use_signal(|| false);
+    let recent: Vec<PathBuf> = state.read().recent_files.paths().to_vec();
+    let empty = recent.is_empty();
+
+    let mut open_recent = move |path: PathBuf| {
+        open.set(false);
+        let result = state.write().open_recent(&path);
+        report_failure(state, "Failed to open recent document", result);
+    };
+    let clear = move |_| {
+        open.set(false);
+        let cleared = state.write().recent_files.clear();
+        report_warning(state, "Couldn't clear the recent files", cleared);
+    };
+
+    rsx! {
+        div {
+            class: "menu-dropdown",
+            button {
+                class: "menu-button",
+                title: "Recent documents",
+                disabled: empty,
+                onclick: move |_| open.set(!open()),
+                "Recent ▾"
+            }
+            if open() {
+                div {
+                    class: "menu-dropdown-list",
+                    for path in recent {
+                        button {
+                            key: "{path.display()}",
+                            class: "menu-dropdown-item",
+                            onclick: {
+                                let path = path.clone();
+                                move |_| open_recent(path.clone())
+                            },
+                            "{path.display()}"
+                        }
+                    }
+                    button { class: "menu-dropdown-item menu-dropdown-clear", onclick: clear, "Clear" }
+                }
+            }
         }
     }
 }
 
-/// Menu button for saving the current document.
+/// Hidden file input for choosing images to insert into the document.
 #[component]
-fn SaveButton(state: Signal<ApplicationState>) -> Element {
-    let handle_click = move |_| {
-        let current_state = state.read();
-        match to_string_pretty(&current_state.the_only_document) {
-            Ok(json_content) => {
-                let filename = current_state
-                    .current_file_path
-                    .as_ref()
-                    .and_then(|p| p.file_name())
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("document.json");
-                if let Err(e) = download_file(&json_content, filename) {
-                    eprintln!("Failed to download file for save: {e}");
+fn ImageInput(mut state: Signal<ApplicationState>) -> Element {
+    let mut file_input_ref = use_signal(|| None::<HtmlInputElement>);
+    let mut onload_closure = use_signal(|| None::<Closure<dyn FnMut(web_sys::Event)>>);
+
+    let handle_file_input_mounted = move |element: MountedEvent| {
+        if let Some(web_element) = element.downcast::<web_sys::Element>() {
+            match web_element.clone().dyn_into::<HtmlInputElement>() {
+                Ok(input) => {
+                    *file_input_ref.write() = Some(input);
+                }
+                Err(e) => {
+                    state.write().notifications.error(
+                        "File input unavailable",
+                        &anyhow!("Failed to cast element to HtmlInputElement: {e:?}"),
+                    );
                 }
             }
-            Err(e) => {
-                eprintln!("Failed to serialize document for save: {e}");
+        }
+    };
+
+    let handle_file_change = move |_event| {
+        if let Some(file) = file_input_ref
+            .read()
+            .as_ref()
+            .and_then(|input| input.files())
+            .and_then(|files| files.get(0))
+        {
+            let file_reader = match FileReader::new() {
+                Ok(reader) => reader,
+                Err(_) => {
+                    state.write().notifications.error(
+                        "Failed to read file",
+                        &anyhow!("Failed to create FileReader - browser API unavailable"),
+                    );
+                    return;
+                }
+            };
+            let mut state_clone = state;
+            let file_reader_clone = file_reader.clone();
+
+            let onload = Closure::<dyn FnMut(web_sys::Event)>::new(move |_| {
+                if let Ok(result) = file_reader_clone.result() {
+                    let data = Uint8Array::new(&result).to_vec();
+                    let result = state_clone.write().current_mut().insert_image(data);
+                    report_failure(state_clone, "Failed to insert image", result);
+                }
+            });
+
+            file_reader.set_onload(Some(onload.as_ref().unchecked_ref()));
+            if file_reader.read_as_array_buffer(&file).is_err() {
+                state.write().notifications.error(
+                    "Failed to read image file",
+                    &anyhow!("The browser couldn't read \"{}\"", file.name()),
+                );
+                return;
             }
+
+            *onload_closure.write() = Some(onload);
         }
     };
 
+    rsx! {
+        input {
+            r#type: "file",
+            accept: "image/png,image/jpeg,image/gif",
+            style: "display: none",
+            id: IMAGE_INPUT_ID,
+            onmounted: handle_file_input_mounted,
+            onchange: handle_file_change
+        }
+
+    }
+}
+
+/// Menu button carrying out the action `id`.
+#[component]
+fn ActionButton(host: WebHost, id: &'static str) -> Element {
+    let Some(action) = action(id) else {
+        return rsx! {};
+    };
+    let (enabled, tooltip) = {
+        let state = host.state.read();
+        ((action.enabled)(&state), action.tooltip(&state.keybindings))
+    };
+
     rsx! {
         button {
             class: "menu-button",
-            title: "Save document (Ctrl+S)",
-            onclick: handle_click,
-            "Save"
+            title: tooltip,
+            disabled: !enabled,
+            onclick: move |_| perform(&host, id),
+            "{action.label}"
         }
     }
 }
 
-/// Menu button for saving the document with a new name.
+/// Selection of the format for Save As.
 #[component]
-fn SaveAsButton(state: Signal<ApplicationState>) -> Element {
-    let handle_click = move |_| match to_string_pretty(&state.read().the_only_document) {
-        Ok(json_content) => {
-            if let Err(e) = download_file(&json_content, "document.json") {
-                eprintln!("Failed to download file for save as: {e}");
-            }
-        }
-        Err(e) => {
-            eprintln!("Failed to serialize document for save as: {e}");
+fn FormatSelect(format: Signal<FileFormat>) -> Element {
+    let handle_format_change = move |e: FormEvent| {
+        if let Some(chosen) = FileFormat::from_extension(&e.value()) {
+            format.set(chosen);
         }
     };
 
     rsx! {
-        button {
-            class: "menu-button",
-            title: "Save document as... (Ctrl+Shift+S)",
-            onclick: handle_click,
-            "Save As"
+        select {
+            class: "menu-select",
+            title: "Format for Save As",
+            onchange: handle_format_change,
+            for option_format in FileFormat::ALL {
+                option {
+                    value: option_format.extension(),
+                    selected: option_format == format(),
+                    "{option_format.description()} (.{option_format.extension()})"
+                }
+            }
         }
     }
 }
 
+/// The title and author of the current document, for the menu bar.
+#[component]
+fn DocumentSummary(state: Signal<ApplicationState>) -> Element {
+    let current_state = state.read();
+    let title = current_state.document_title();
+    let author = current_state.current().document.metadata().author.clone();
+
+    rsx! {
+        div {
+            class: "menu-document",
+            span { class: "menu-document-title", "{title}" }
+            if !author.is_empty() {
+                span { class: "menu-document-author", "by {author}" }
+            }
+        }
+    }
+}
+
+/// Has the browser ask before leaving the page while any open document has
+/// unsaved changes.
+fn use_leave_guard(state: Signal<ApplicationState>) {
+    let guard = use_hook(|| {
+        Rc::new(Closure::<dyn FnMut(web_sys::Event)>::new(
+            |event: web_sys::Event| {
+                event.prevent_default();
+                // Older browsers only ask when a return value is set.
+                let _ = Reflect::set(&event, &"returnValue".into(), &"".into());
+            },
+        ))
+    });
+
+    let handler = guard.clone();
+    use_effect(move || {
+        let dirty = state.read().documents().iter().any(OpenDocument::is_dirty);
+        if let Some(window) = window() {
+            window.set_onbeforeunload(dirty.then(|| handler.as_ref().as_ref().unchecked_ref()));
+        }
+    });
+
+    // The handler goes with the menu, so it mustn't be left registered.
+    use_drop(move || {
+        if let Some(window) = window() {
+            window.set_onbeforeunload(None);
+        }
+        drop(guard);
+    });
+}
+
 /// The web app's file menu.
 #[component]
 pub fn WebFileMenu(application_state: Signal<ApplicationState>) -> Element {
+    let save_as_format = use_signal(FileFormat::default);
+    let host = WebHost {
+        state: application_state,
+        save_as_format,
+    };
+    use_pressed_actions(host);
+    use_leave_guard(application_state);
+    let actions: Vec<&Action> = [MenuName::File, MenuName::Edit]
+        .into_iter()
+        .flat_map(|menu| menu_actions(&host, menu))
+        .collect();
+
     rsx! {
         document::Link { rel: "stylesheet", href: asset!("/assets/styling/file_menu.css") }
+        UnsavedChangesPrompt {
+            application_state,
+            on_save: move |_| match host.save() {
+                Ok(saved) => saved,
+                Err(e) => {
+                    host.report("Save", e);
+                    false
+                }
+            },
+            on_proceed: move |action| proceed(&host, action),
+        }
+        ExportDialog {
+            application_state,
+            on_export: move |(content, file_name): (Vec<u8>, String)| {
+                if let Err(e) = host.export(&content, &file_name) {
+                    host.report("Export", e);
+                }
+            },
+        }
+        OpenInput { state: application_state }
+        ImageInput { state: application_state }
         div {
             class: "file-menu",
             div {
@@ -187,30 +425,74 @@ pub fn WebFileMenu(application_state: Signal<ApplicationState>) -> Element {
                 span { class: "menu-title", "File" }
                 div {
                     class: "menu-buttons",
-                    NewButton { state: application_state }
-                    OpenButton { state: application_state }
-                    SaveButton { state: application_state }
-                    SaveAsButton { state: application_state }
+                    for (index, action) in actions.iter().enumerate() {
+                        if index > 0
+                            && (actions[index - 1].menu, actions[index - 1].group)
+                                != (action.menu, action.group)
+                        {
+                            span { class: "menu-separator" }
+                        }
+                        ActionButton { host, id: action.id }
+                        if action.id == "open" {
+                            RecentButton { state: application_state }
+                        }
+                        if action.id == "save_as" {
+                            FormatSelect { format: save_as_format }
+                        }
+                    }
                 }
+                DocumentSummary { state: application_state }
             }
         }
     }
 }
 
+/// Downloads the current document under its name, in the format that names
+/// (JSON if none).
+fn save(mut state: Signal<ApplicationState>) -> Result<()> {
+    let saved = state.read().current().stamped_for_saving();
+    {
+        let current_state = state.read();
+        let filename = current_state
+            .current()
+            .file_path
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+            .unwrap_or("document.json");
+        let format = FileFormat::from_path(Path::new(filename)).unwrap_or_default();
+        let content = saved.to_bytes(format)?;
+        download_file(&content, filename).map_err(|e| anyhow!("Failed to download file: {e}"))?;
+    }
+    state.write().mark_saved(&saved);
+    Ok(())
+}
+
+/// Clicks the element with the id `id`, such as a hidden file input.
+fn click_element(id: &str) -> Result<()> {
+    window()
+        .and_then(|w| w.document())
+        .and_then(|d| d.get_element_by_id(id))
+        .and_then(|e| e.dyn_into::<HtmlElement>().ok())
+        .with_context(|| format!("Failed to find element '{id}'"))?
+        .click();
+    Ok(())
+}
+
 // Browser API functions for file operations
 
 /// Saves a file called `filename` containing `content`.
-fn download_file(content: &str, filename: &str) -> Result<(), String> {
+fn download_file(content: &[u8], filename: &str) -> Result<(), String> {
     let document = window()
         .ok_or_else(|| "Failed to get window object - browser API unavailable".to_string())?
         .document()
         .ok_or_else(|| "Failed to get document object - browser API unavailable".to_string())?;
 
     let array = Array::new();
-    array.push(&JsValue::from_str(content));
+    array.push(&Uint8Array::from(content));
 
     let url = Url::create_object_url_with_blob(
-        &Blob::new_with_str_sequence(&array)
+        &Blob::new_with_u8_array_sequence(&array)
             .map_err(|_| "Failed to create Blob from content".to_string())?,
     )
     .map_err(|_| "Failed to create object URL for blob".to_string())?;